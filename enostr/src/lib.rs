@@ -13,6 +13,7 @@ pub use ewebsock;
 pub use filter::Filter;
 pub use profile::Profile;
 pub use pubkey::Pubkey;
+pub use relay::info::{RelayInfo, RelayLimitation};
 pub use relay::message::{RelayEvent, RelayMessage};
 pub use relay::pool::{PoolEvent, RelayPool};
 pub use relay::{Relay, RelayStatus};
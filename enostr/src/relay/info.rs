@@ -0,0 +1,77 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+
+/// A relay's NIP-11 information document, which it serves over http at
+/// its websocket url with `Accept: application/nostr+json`. Every field is
+/// optional, and fields we don't know are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// The operator's pubkey, in hex
+    pub pubkey: Option<String>,
+    /// How to reach the operator, often an email address or url
+    pub contact: Option<String>,
+    pub supported_nips: Vec<u32>,
+    pub software: Option<String>,
+    pub version: Option<String>,
+    pub limitation: Option<RelayLimitation>,
+    pub payments_url: Option<String>,
+}
+
+/// The limits a relay puts on its clients
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelayLimitation {
+    /// The longest websocket message it accepts, in bytes
+    pub max_message_length: Option<u64>,
+    pub max_subscriptions: Option<u64>,
+    pub max_filters: Option<u64>,
+    /// The most events it returns for a filter
+    pub max_limit: Option<u64>,
+    pub max_content_length: Option<u64>,
+    pub min_pow_difficulty: Option<u32>,
+    pub auth_required: Option<bool>,
+    pub payment_required: Option<bool>,
+    pub restricted_writes: Option<bool>,
+}
+
+impl RelayInfo {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn supports(&self, nip: u32) -> bool {
+        self.supported_nips.contains(&nip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_info_documents() {
+        let info = RelayInfo::from_json(
+            r#"{
+                "name": "damus.io",
+                "supported_nips": [1, 11, 42],
+                "limitation": {"max_message_length": 16384, "payment_required": true},
+                "retention": [{"kinds": [0], "time": 3600}]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(info.name.as_deref(), Some("damus.io"));
+        assert!(info.supports(11));
+        assert!(!info.supports(50));
+        let limitation = info.limitation.unwrap();
+        assert_eq!(limitation.max_message_length, Some(16384));
+        assert_eq!(limitation.payment_required, Some(true));
+        assert_eq!(limitation.auth_required, None);
+
+        assert_eq!(RelayInfo::from_json("{}").unwrap(), RelayInfo::default());
+        assert!(RelayInfo::from_json("not json").is_err());
+    }
+}
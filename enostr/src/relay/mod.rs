@@ -5,6 +5,7 @@ use log::info;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 
+pub mod info;
 pub mod message;
 pub mod pool;
 
@@ -13,6 +14,8 @@ pub enum RelayStatus {
     Connected,
     Connecting,
     Disconnected,
+    /// The connection failed with this error
+    Error(String),
 }
 
 pub struct Relay {
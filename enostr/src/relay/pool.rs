@@ -20,6 +20,8 @@ pub struct PoolRelay {
     pub last_ping: Instant,
     pub last_connect_attempt: Instant,
     pub retry_connect_after: Duration,
+    /// When we last received a message from this relay
+    pub last_message: Option<Instant>,
 }
 
 impl PoolRelay {
@@ -29,6 +31,7 @@ impl PoolRelay {
             last_ping: Instant::now(),
             last_connect_attempt: Instant::now(),
             retry_connect_after: Self::initial_reconnect_duration(),
+            last_message: None,
         }
     }
 
@@ -78,7 +81,7 @@ impl RelayPool {
             let now = std::time::Instant::now();
 
             match relay.relay.status {
-                RelayStatus::Disconnected => {
+                RelayStatus::Disconnected | RelayStatus::Error(_) => {
                     let reconnect_at = relay.last_connect_attempt + relay.retry_connect_after;
                     if now > reconnect_at {
                         relay.last_connect_attempt = now;
@@ -145,8 +148,8 @@ impl RelayPool {
     /// receive a message from each. If a message is received, return it.
     /// If no message is received from any relays, None is returned.
     pub fn try_recv<'a>(&'a mut self) -> Option<PoolEvent<'a>> {
-        for relay in &mut self.relays {
-            let relay = &mut relay.relay;
+        for pool_relay in &mut self.relays {
+            let relay = &mut pool_relay.relay;
             if let Some(event) = relay.receiver.try_recv() {
                 match &event {
                     WsEvent::Opened => {
@@ -157,9 +160,11 @@ impl RelayPool {
                     }
                     WsEvent::Error(err) => {
                         error!("{:?}", err);
-                        relay.status = RelayStatus::Disconnected;
+                        relay.status = RelayStatus::Error(err.to_owned());
                     }
                     WsEvent::Message(ev) => {
+                        pool_relay.last_message = Some(Instant::now());
+
                         // let's just handle pongs here.
                         // We only need to do this natively.
                         #[cfg(not(target_arch = "wasm32"))]
@@ -1,20 +1,14 @@
-#[inline]
-pub fn floor_char_boundary(s: &str, index: usize) -> usize {
-    if index >= s.len() {
-        s.len()
-    } else {
-        let lower_bound = index.saturating_sub(3);
-        let new_index = s.as_bytes()[lower_bound..=index]
-            .iter()
-            .rposition(|b| is_utf8_char_boundary(*b));
+use crate::ui::text::grapheme_boundary;
+use unicode_segmentation::UnicodeSegmentation;
 
-        // SAFETY: we know that the character boundary will be within four bytes
-        unsafe { lower_bound + new_index.unwrap_unchecked() }
+/// Keep the start and end of a long identifier, eg. "npub1abc…wxyz"
+pub fn abbreviate_middle(s: &str, head: usize, tail: usize) -> String {
+    let len = s.graphemes(true).count();
+    if len <= head + tail {
+        return s.to_owned();
     }
-}
 
-#[inline]
-fn is_utf8_char_boundary(c: u8) -> bool {
-    // This is bit magic equivalent to: b < 128 || b >= 192
-    (c as i8) >= -0x40
+    let head_end = grapheme_boundary(s, head);
+    let tail_start = grapheme_boundary(s, len - tail);
+    format!("{}…{}", &s[..head_end], &s[tail_start..])
 }
@@ -2,6 +2,7 @@ use crate::app_style::NotedeckTextStyle;
 use crate::key_parsing::LoginError;
 use crate::login_manager::LoginManager;
 use crate::ui;
+use crate::ui::i18n::tr;
 use crate::ui::{Preview, View};
 use egui::{
     Align, Align2, Button, Color32, Frame, Id, LayerId, Margin, Pos2, Rect, RichText, Rounding, Ui,
@@ -15,21 +16,22 @@ pub struct AccountLoginView<'a> {
 }
 
 impl<'a> View for AccountLoginView<'a> {
-    fn ui(&mut self, ui: &mut egui::Ui) {
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
         let is_mobile = ui::is_mobile(ui.ctx());
         if let Some(key) = self.manager.check_for_successful_login() {
             // TODO: route to "home"
             println!("successful login with key: {:?}", key);
-            return if is_mobile {
+            if is_mobile {
                 // route to "home" on mobile
             } else {
                 // route to "home" on desktop
             };
+            return ui.allocate_response(Vec2::ZERO, egui::Sense::hover());
         }
         if is_mobile {
-            self.show_mobile(ui);
+            self.show_mobile(ui)
         } else {
-            self.show(ui);
+            self.show(ui)
         }
     }
 }
@@ -92,34 +94,34 @@ impl<'a> AccountLoginView<'a> {
             ui.vertical_centered(|ui| {
                 ui.add(logo_unformatted().max_width(256.0));
                 ui.add_space(64.0);
-                ui.label(login_info_text());
+                ui.label(login_info_text(ui.ctx()));
                 ui.add_space(32.0);
-                ui.label(login_title_text());
+                ui.label(login_title_text(ui.ctx()));
             });
 
             ui.horizontal(|ui| {
-                ui.label(login_textedit_info_text());
+                ui.label(login_textedit_info_text(ui.ctx()));
             });
 
             ui.vertical_centered_justified(|ui| {
-                ui.add(login_textedit(self.manager));
+                ui.add(login_textedit(ui.ctx(), self.manager));
 
                 self.loading_and_error(ui);
 
-                if ui.add(login_button()).clicked() {
+                if ui.add(login_button(ui.ctx())).clicked() {
                     self.manager.apply_login();
                 }
             });
 
             ui.horizontal(|ui| {
                 ui.label(
-                    RichText::new("New to Nostr?")
+                    RichText::new(tr!(ui.ctx(), "New to Nostr?"))
                         .color(ui.style().visuals.noninteractive().fg_stroke.color)
                         .text_style(NotedeckTextStyle::Body.text_style()),
                 );
 
                 if ui
-                    .add(Button::new(RichText::new("Create Account")).frame(false))
+                    .add(Button::new(RichText::new(tr!(ui.ctx(), "Create Account"))).frame(false))
                     .clicked()
                 {
                     // TODO: navigate to 'create account' screen
@@ -168,7 +170,7 @@ impl<'a> AccountLoginView<'a> {
                 ui.vertical_centered(|ui| {
                     ui.add_space(40.0);
 
-                    ui.label(login_title_text());
+                    ui.label(login_title_text(ui.ctx()));
 
                     ui.add_space(16f32);
 
@@ -217,23 +219,23 @@ impl<'a> AccountLoginView<'a> {
             //         .strong()
             //         .line_height(Some(72.0)),
             // );
-            ui.label(login_info_text());
+            ui.label(login_info_text(ui.ctx()));
         });
     }
 
     fn login_form(&mut self, ui: &mut egui::Ui) {
         ui.vertical_centered_justified(|ui| {
             ui.horizontal(|ui| {
-                ui.label(login_textedit_info_text());
+                ui.label(login_textedit_info_text(ui.ctx()));
             });
 
             ui.add_space(8f32);
 
-            ui.add(login_textedit(self.manager).min_size(Vec2::new(440.0, 40.0)));
+            ui.add(login_textedit(ui.ctx(), self.manager).min_size(Vec2::new(440.0, 40.0)));
 
             self.loading_and_error(ui);
 
-            let login_button = login_button().min_size(Vec2::new(442.0, 40.0));
+            let login_button = login_button(ui.ctx()).min_size(Vec2::new(442.0, 40.0));
 
             if ui.add(login_button).clicked() {
                 self.manager.apply_login()
@@ -260,11 +262,12 @@ impl<'a> AccountLoginView<'a> {
     fn generate_group(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.label(
-                RichText::new("New in nostr?").text_style(NotedeckTextStyle::Heading3.text_style()),
+                RichText::new(tr!(ui.ctx(), "New in nostr?"))
+                    .text_style(NotedeckTextStyle::Heading3.text_style()),
             );
 
             ui.label(
-                RichText::new(" — we got you!")
+                RichText::new(tr!(ui.ctx(), " — we got you!"))
                     .text_style(NotedeckTextStyle::Heading3.text_style())
                     .color(ui.visuals().noninteractive().fg_stroke.color),
             );
@@ -273,12 +276,14 @@ impl<'a> AccountLoginView<'a> {
         ui.add_space(6.0);
 
         ui.horizontal(|ui| {
-            ui.label(generate_info_text().color(ui.visuals().noninteractive().fg_stroke.color));
+            ui.label(
+                generate_info_text(ui.ctx()).color(ui.visuals().noninteractive().fg_stroke.color),
+            );
         });
 
         ui.add_space(16.0);
 
-        let generate_button = generate_keys_button().min_size(Vec2::new(442.0, 40.0));
+        let generate_button = generate_keys_button(ui.ctx()).min_size(Vec2::new(442.0, 40.0));
         if ui.add(generate_button).clicked() {
             // TODO: keygen
         }
@@ -288,9 +293,9 @@ impl<'a> AccountLoginView<'a> {
 fn show_error(ui: &mut egui::Ui, err: &LoginError) {
     ui.horizontal(|ui| {
         let error_label = match err {
-            LoginError::InvalidKey => {
-                egui::Label::new(RichText::new("Invalid key.").color(ui.visuals().error_fg_color))
-            }
+            LoginError::InvalidKey => egui::Label::new(
+                RichText::new(tr!(ui.ctx(), "Invalid key.")).color(ui.visuals().error_fg_color),
+            ),
             LoginError::Nip05Failed(e) => {
                 egui::Label::new(RichText::new(e).color(ui.visuals().error_fg_color))
             }
@@ -299,25 +304,31 @@ fn show_error(ui: &mut egui::Ui, err: &LoginError) {
     });
 }
 
-fn login_title_text() -> RichText {
-    RichText::new("Login")
+fn login_title_text(ctx: &egui::Context) -> RichText {
+    RichText::new(tr!(ctx, "Login"))
         .text_style(NotedeckTextStyle::Heading2.text_style())
         .strong()
 }
 
-fn login_info_text() -> RichText {
-    RichText::new("The best alternative to tweetDeck built in nostr protocol")
-        .text_style(NotedeckTextStyle::Heading3.text_style())
+fn login_info_text(ctx: &egui::Context) -> RichText {
+    RichText::new(tr!(
+        ctx,
+        "The best alternative to tweetDeck built in nostr protocol"
+    ))
+    .text_style(NotedeckTextStyle::Heading3.text_style())
 }
 
 fn login_window_info_text(ui: &Ui) -> RichText {
-    RichText::new("Enter your private key to start using Notedeck")
-        .text_style(NotedeckTextStyle::Body.text_style())
-        .color(ui.visuals().noninteractive().fg_stroke.color)
+    RichText::new(tr!(
+        ui.ctx(),
+        "Enter your private key to start using Notedeck"
+    ))
+    .text_style(NotedeckTextStyle::Body.text_style())
+    .color(ui.visuals().noninteractive().fg_stroke.color)
 }
 
-fn login_textedit_info_text() -> RichText {
-    RichText::new("Enter your key")
+fn login_textedit_info_text(ctx: &egui::Context) -> RichText {
+    RichText::new(tr!(ctx, "Enter your key"))
         .strong()
         .text_style(NotedeckTextStyle::Body.text_style())
 }
@@ -327,18 +338,23 @@ fn logo_unformatted() -> Image<'static> {
     return egui::Image::new(logo_gradient_data);
 }
 
-fn generate_info_text() -> RichText {
-    RichText::new("Quickly generate your keys. Make sure you save them safely.")
-        .text_style(NotedeckTextStyle::Body.text_style())
+fn generate_info_text(ctx: &egui::Context) -> RichText {
+    RichText::new(tr!(
+        ctx,
+        "Quickly generate your keys. Make sure you save them safely."
+    ))
+    .text_style(NotedeckTextStyle::Body.text_style())
 }
 
-fn generate_keys_button() -> Button<'static> {
-    Button::new(RichText::new("Generate keys").text_style(NotedeckTextStyle::Body.text_style()))
+fn generate_keys_button(ctx: &egui::Context) -> Button<'static> {
+    Button::new(
+        RichText::new(tr!(ctx, "Generate keys")).text_style(NotedeckTextStyle::Body.text_style()),
+    )
 }
 
-fn login_button() -> Button<'static> {
+fn login_button(ctx: &egui::Context) -> Button<'static> {
     Button::new(
-        RichText::new("Login now — let's do this!")
+        RichText::new(tr!(ctx, "Login now — let's do this!"))
             .text_style(NotedeckTextStyle::Body.text_style())
             .strong(),
     )
@@ -346,16 +362,18 @@ fn login_button() -> Button<'static> {
     .min_size(Vec2::new(0.0, 40.0))
 }
 
-fn login_textedit(manager: &mut LoginManager) -> TextEdit {
-    manager.get_login_textedit(|text| {
-        egui::TextEdit::singleline(text)
-            .hint_text(
-                RichText::new("Your key here...").text_style(NotedeckTextStyle::Body.text_style()),
-            )
-            .vertical_align(Align::Center)
-            .min_size(Vec2::new(0.0, 40.0))
-            .margin(Margin::same(12.0))
-    })
+fn login_textedit<'a>(ctx: &egui::Context, manager: &'a mut LoginManager) -> TextEdit<'a> {
+    manager
+        .get_login_textedit(|text| {
+            egui::TextEdit::singleline(text)
+                .vertical_align(Align::Center)
+                .min_size(Vec2::new(0.0, 40.0))
+                .margin(Margin::same(12.0))
+        })
+        .hint_text(
+            RichText::new(tr!(ctx, "Your key here..."))
+                .text_style(NotedeckTextStyle::Body.text_style()),
+        )
 }
 
 pub struct AccountLoginPreview {
@@ -363,8 +381,8 @@ pub struct AccountLoginPreview {
 }
 
 impl View for AccountLoginPreview {
-    fn ui(&mut self, ui: &mut egui::Ui) {
-        AccountLoginView::new(&mut self.manager).ui(ui);
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        AccountLoginView::new(&mut self.manager).ui(ui)
     }
 }
 
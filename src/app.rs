@@ -3,10 +3,14 @@ use crate::app_style::user_requested_visuals_change;
 use crate::error::Error;
 use crate::frame_history::FrameHistory;
 use crate::imgcache::ImageCache;
+use crate::link_preview::LinkPreviews;
 use crate::notecache::NoteCache;
 use crate::timeline;
 use crate::timeline::{NoteRef, Timeline};
+use crate::ui::i18n::tr;
 use crate::ui::is_mobile;
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::ProfileRequests;
 use crate::Result;
 
 use egui::{Context, Frame, Margin, Style};
@@ -18,11 +22,19 @@ use nostrdb::{BlockType, Config, Mention, Ndb, Note, NoteKey, Transaction};
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
 use enostr::RelayPool;
 
+/// How long a profile a relay said it didn't have waits before we ask for
+/// it again
+const PROFILE_RETRY_AFTER_EOSE: Duration = Duration::from_secs(30);
+
+/// How long we wait on a profile request no relay has answered before
+/// asking again
+const PROFILE_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum DamusState {
     Initializing,
@@ -40,8 +52,18 @@ pub struct Damus {
     pub timelines: Vec<Timeline>,
 
     pub img_cache: ImageCache,
+    pub link_previews: LinkPreviews,
     pub ndb: Ndb,
 
+    /// Profiles we've asked relays for, and when we may ask again if they
+    /// still haven't turned up
+    requested_profiles: HashMap<[u8; 32], Instant>,
+    /// The pubkeys in each profile prefetch waiting on its EOSE, by
+    /// subscription id
+    profile_batches: HashMap<String, Vec<[u8; 32]>>,
+    /// How many profile prefetches we've sent, for their subscription ids
+    profile_prefetches: u64,
+
     frame_history: crate::frame_history::FrameHistory,
 }
 
@@ -392,6 +414,10 @@ fn handle_eose(damus: &mut Damus, subid: &str, relay_url: &str) -> Result<()> {
     } else if subid == "unknown_ids" {
         let msg = ClientMessage::close("unknown_ids".to_string());
         damus.pool.send_to(&msg, relay_url);
+    } else if subid.starts_with("profiles") {
+        damus.finish_profile_prefetch(subid);
+        let msg = ClientMessage::close(subid.to_string());
+        damus.pool.send_to(&msg, relay_url);
     } else {
         warn!("got unknown eose subid {}", subid);
     }
@@ -419,6 +445,10 @@ fn render_damus(damus: &mut Damus, ctx: &Context) {
         render_damus_desktop(ctx, damus);
     }
 
+    // everything the timelines wanted a profile for, in one go
+    let pubkeys = ProfileRequests::take(ctx);
+    damus.prefetch_profiles(&pubkeys);
+
     ctx.request_repaint_after(Duration::from_secs(1));
 
     #[cfg(feature = "profiling")]
@@ -464,16 +494,90 @@ impl Damus {
         Self {
             state: DamusState::Initializing,
             pool: RelayPool::new(),
-            img_cache: ImageCache::new(imgcache_dir),
+            img_cache: ImageCache::new(imgcache_dir, ImageCache::default_max_bytes()),
+            link_previews: LinkPreviews::default(),
             note_cache: HashMap::new(),
             timelines,
             textmode: false,
             ndb: Ndb::new(data_path.as_ref().to_str().expect("db path ok"), &config).expect("ndb"),
+            requested_profiles: HashMap::new(),
+            profile_batches: HashMap::new(),
+            profile_prefetches: 0,
             //compose: "".to_string(),
             frame_history: FrameHistory::default(),
         }
     }
 
+    /// Ask relays for the profiles of `pubkeys` in a single REQ, leaving out
+    /// ones we have or have recently asked for. Called once a frame with
+    /// the pubkeys the visible mentions and names wanted.
+    pub fn prefetch_profiles(&mut self, pubkeys: &[[u8; 32]]) {
+        if pubkeys.is_empty() {
+            return;
+        }
+
+        // checking what we've asked for is much cheaper than asking ndb
+        let now = Instant::now();
+        let unrequested: Vec<&[u8; 32]> = pubkeys
+            .iter()
+            .filter(|pk| !matches!(self.requested_profiles.get(*pk), Some(retry) if *retry > now))
+            .collect();
+        if unrequested.is_empty() {
+            return;
+        }
+
+        let txn = match Transaction::new(&self.ndb) {
+            Ok(txn) => txn,
+            Err(err) => {
+                error!("couldn't prefetch profiles: {}", err);
+                return;
+            }
+        };
+        let batch: Vec<[u8; 32]> = unrequested
+            .into_iter()
+            .filter(|pk| self.ndb.get_profile_by_pubkey(&txn, pk).is_err())
+            .copied()
+            .collect();
+        if batch.is_empty() {
+            return;
+        }
+
+        // forget the requests that have run out, so this doesn't grow with
+        // every profile we've ever asked for
+        self.requested_profiles.retain(|_, retry| *retry > now);
+        for pk in &batch {
+            self.requested_profiles
+                .insert(*pk, now + PROFILE_REQUEST_TIMEOUT);
+        }
+
+        info!("Prefetching {} profiles from relays", batch.len());
+        // each batch gets its own subscription, so a new one doesn't
+        // replace one that's still loading
+        let subid = format!("profiles{}", self.profile_prefetches);
+        self.profile_prefetches += 1;
+        let filter = Filter::new()
+            .authors(batch.iter().map(Pubkey::new).collect())
+            .kinds(vec![0]);
+        self.pool
+            .send(&ClientMessage::req(subid.clone(), vec![filter]));
+        self.profile_batches.insert(subid, batch);
+    }
+
+    /// A relay has sent all it has for a profile prefetch. Whichever of its
+    /// profiles didn't come can be asked for again in a little while, by
+    /// when another relay may have them.
+    fn finish_profile_prefetch(&mut self, subid: &str) {
+        let Some(batch) = self.profile_batches.remove(subid) else {
+            return;
+        };
+        let retry = Instant::now() + PROFILE_RETRY_AFTER_EOSE;
+        for pk in batch {
+            if let Some(when) = self.requested_profiles.get_mut(&pk) {
+                *when = retry.min(*when);
+            }
+        }
+    }
+
     pub fn get_note_cache_mut(&mut self, note_key: NoteKey, note: &Note<'_>) -> &mut NoteCache {
         self.note_cache
             .entry(note_key)
@@ -559,12 +663,13 @@ fn render_panel(ctx: &egui::Context, app: &mut Damus, timeline_ind: usize) {
             if let Some(new_visuals) =
                 user_requested_visuals_change(is_mobile(ctx), ctx.style().visuals.dark_mode, ui)
             {
-                ctx.set_visuals(new_visuals)
+                ctx.set_visuals(new_visuals);
+                NotedeckTheme::install(ctx);
             }
 
             if ui
                 .add(egui::Button::new("A").frame(false))
-                .on_hover_text("Text mode")
+                .on_hover_text(tr!(ctx, "Text mode"))
                 .clicked()
             {
                 app.textmode = !app.textmode;
@@ -601,6 +706,14 @@ fn render_panel(ctx: &egui::Context, app: &mut Damus, timeline_ind: usize) {
                     "{} notes",
                     &app.timelines[timeline_ind].notes.len()
                 ));
+
+                let images = app.img_cache.stats();
+                ui.weak(format!(
+                    "{} images decoded in {:.0}ms, {:.0}% cached",
+                    images.decodes,
+                    images.decode_time.as_secs_f64() * 1e3,
+                    images.hit_rate() * 100.0
+                ));
             }
         });
     });
@@ -706,5 +819,6 @@ impl eframe::App for Damus {
         puffin::GlobalProfiler::lock().new_frame();
         update_damus(self, ctx);
         render_damus(self, ctx);
+        self.img_cache.evict_textures();
     }
 }
@@ -1,6 +1,8 @@
 use crate::app_style::{create_custom_style, dark_mode, desktop_font_size, mobile_font_size};
 use crate::fonts::setup_fonts;
+use crate::ui::direction::LayoutDirection;
 use crate::ui::is_mobile;
+use crate::ui::theme::NotedeckTheme;
 use eframe::NativeOptions;
 
 //pub const UI_SCALE_FACTOR: f32 = 0.2;
@@ -37,7 +39,11 @@ pub fn generate_mobile_emulator_native_options() -> eframe::NativeOptions {
 }
 
 pub fn setup_cc(cc: &eframe::CreationContext<'_>) {
-    let ctx = &cc.egui_ctx;
+    setup_ctx(&cc.egui_ctx);
+}
+
+/// Fonts, image loaders and styling for a context, with or without a window
+pub fn setup_ctx(ctx: &egui::Context) {
     setup_fonts(ctx);
 
     //ctx.set_pixels_per_point(ctx.pixels_per_point() + UI_SCALE_FACTOR);
@@ -45,6 +51,8 @@ pub fn setup_cc(cc: &eframe::CreationContext<'_>) {
     egui_extras::install_image_loaders(ctx);
 
     ctx.set_visuals(dark_mode(is_mobile(ctx)));
+    NotedeckTheme::install(ctx);
+    LayoutDirection::from_env().set(ctx);
 
     ctx.set_style(if is_mobile(ctx) {
         create_custom_style(ctx, mobile_font_size)
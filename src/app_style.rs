@@ -1,6 +1,7 @@
 use crate::colors::{
     desktop_dark_color_theme, light_color_theme, mobile_dark_color_theme, ColorTheme,
 };
+use crate::ui::i18n::tr;
 use egui::{
     epaint::Shadow,
     style::{Interaction, Selection, WidgetVisuals, Widgets},
@@ -34,14 +35,14 @@ pub fn user_requested_visuals_change(
     if cur_darkmode {
         if ui
             .add(Button::new("☀").frame(false))
-            .on_hover_text("Switch to light mode")
+            .on_hover_text(tr!(ui.ctx(), "Switch to light mode"))
             .clicked()
         {
             return Some(light_mode());
         }
     } else if ui
         .add(Button::new("🌙").frame(false))
-        .on_hover_text("Switch to dark mode")
+        .on_hover_text(tr!(ui.ctx(), "Switch to dark mode"))
         .clicked()
     {
         return Some(dark_mode(mobile));
@@ -0,0 +1,287 @@
+use std::borrow::Cow;
+use unicode_bidi::{BidiInfo, Level};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// A piece of text to lay out, in the order it should be added to the ui
+#[derive(Debug, PartialEq, Eq)]
+pub enum TextRun<'a> {
+    Text(Cow<'a, str>),
+    /// The end of a paragraph
+    Break,
+}
+
+/// Whether text should be laid out right to left, going by its first
+/// strongly directional character
+pub fn is_rtl(text: &str) -> bool {
+    BidiInfo::new(text, None)
+        .paragraphs
+        .first()
+        .is_some_and(|para| para.level.is_rtl())
+}
+
+/// Whether text contains anything that needs bidi handling. Cheap enough to
+/// check on every frame.
+pub fn has_rtl(text: &str) -> bool {
+    text.chars().any(|c| {
+        matches!(
+            c,
+            '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFE}'
+        )
+    })
+}
+
+/// Break text up into runs that display correctly when added one after the
+/// other to a wrapping layout: a right to left layout if `rtl`, otherwise a
+/// left to right one. Arabic is converted to its joined presentation forms,
+/// since egui doesn't do any shaping of its own.
+///
+/// In a right to left layout each rtl word is its own run, so that egui
+/// wraps lines in the right place.
+pub fn runs(text: &str, rtl: bool) -> Vec<TextRun<'_>> {
+    let base = if rtl { Level::rtl() } else { Level::ltr() };
+    let info = BidiInfo::new(text, Some(base));
+    let mut runs = vec![];
+
+    for para in &info.paragraphs {
+        let line = para.range.start..para.range.end;
+        let (levels, level_runs) = info.visual_runs(para, line);
+
+        let mut push_run = |range: std::ops::Range<usize>| {
+            let run = text[range.clone()].trim_end_matches(['\n', '\r']);
+            if run.is_empty() {
+                return;
+            }
+
+            if !levels[range.start].is_rtl() {
+                runs.push(TextRun::Text(Cow::Borrowed(run)));
+            } else if rtl {
+                for word in run.split_inclusive(' ') {
+                    runs.push(TextRun::Text(Cow::Owned(reverse(&shape_arabic(word)))));
+                }
+            } else {
+                runs.push(TextRun::Text(Cow::Owned(reverse(&shape_arabic(run)))));
+            }
+        };
+
+        // runs are in visual order, left to right
+        if rtl {
+            level_runs.into_iter().rev().for_each(&mut push_run);
+        } else {
+            level_runs.into_iter().for_each(&mut push_run);
+        }
+
+        if text[para.range.clone()].ends_with('\n') {
+            runs.push(TextRun::Break);
+        }
+    }
+
+    runs
+}
+
+/// Reverse text for display, keeping combining marks with their base
+/// character
+fn reverse(text: &str) -> String {
+    text.graphemes(true).rev().collect()
+}
+
+/// How an arabic letter connects to its neighbours
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Only joins to the letter before it, eg. alef
+    Right,
+    /// Joins on both sides
+    Dual,
+}
+
+/// The isolated presentation form of an arabic letter and how it joins.
+/// The final, initial and medial forms follow the isolated one.
+fn arabic_forms(c: char) -> Option<(u32, Joining)> {
+    use Joining::*;
+
+    let forms = match c {
+        '\u{0621}' => (0xFE80, Right),
+        '\u{0622}' => (0xFE81, Right),
+        '\u{0623}' => (0xFE83, Right),
+        '\u{0624}' => (0xFE85, Right),
+        '\u{0625}' => (0xFE87, Right),
+        '\u{0626}' => (0xFE89, Dual),
+        '\u{0627}' => (0xFE8D, Right),
+        '\u{0628}' => (0xFE8F, Dual),
+        '\u{0629}' => (0xFE93, Right),
+        '\u{062A}' => (0xFE95, Dual),
+        '\u{062B}' => (0xFE99, Dual),
+        '\u{062C}' => (0xFE9D, Dual),
+        '\u{062D}' => (0xFEA1, Dual),
+        '\u{062E}' => (0xFEA5, Dual),
+        '\u{062F}' => (0xFEA9, Right),
+        '\u{0630}' => (0xFEAB, Right),
+        '\u{0631}' => (0xFEAD, Right),
+        '\u{0632}' => (0xFEAF, Right),
+        '\u{0633}' => (0xFEB1, Dual),
+        '\u{0634}' => (0xFEB5, Dual),
+        '\u{0635}' => (0xFEB9, Dual),
+        '\u{0636}' => (0xFEBD, Dual),
+        '\u{0637}' => (0xFEC1, Dual),
+        '\u{0638}' => (0xFEC5, Dual),
+        '\u{0639}' => (0xFEC9, Dual),
+        '\u{063A}' => (0xFECD, Dual),
+        '\u{0641}' => (0xFED1, Dual),
+        '\u{0642}' => (0xFED5, Dual),
+        '\u{0643}' => (0xFED9, Dual),
+        '\u{0644}' => (0xFEDD, Dual),
+        '\u{0645}' => (0xFEE1, Dual),
+        '\u{0646}' => (0xFEE5, Dual),
+        '\u{0647}' => (0xFEE9, Dual),
+        '\u{0648}' => (0xFEED, Right),
+        '\u{0649}' => (0xFEEF, Right),
+        '\u{064A}' => (0xFEF1, Dual),
+        _ => return None,
+    };
+
+    Some(forms)
+}
+
+/// The isolated lam-alef ligature for an alef, the final form follows it
+fn lam_alef(alef: char) -> Option<u32> {
+    match alef {
+        '\u{0622}' => Some(0xFEF5),
+        '\u{0623}' => Some(0xFEF7),
+        '\u{0625}' => Some(0xFEF9),
+        '\u{0627}' => Some(0xFEFB),
+        _ => None,
+    }
+}
+
+/// Harakat and other marks that sit on a letter without affecting joining
+fn is_transparent(c: char) -> bool {
+    matches!(c, '\u{064B}'..='\u{065F}' | '\u{0670}')
+}
+
+/// Whether `c` connects to the letter after it
+fn joins_next(c: char) -> bool {
+    // tatweel is just a connecting stroke
+    c == '\u{0640}' || matches!(arabic_forms(c), Some((_, Joining::Dual)))
+}
+
+/// Whether `c` connects to the letter before it
+fn joins_prev(c: char) -> bool {
+    c == '\u{0640}' || (arabic_forms(c).is_some() && c != '\u{0621}')
+}
+
+/// Replace arabic letters with the presentation form for their position in
+/// the word, so they render joined up without a shaping engine
+pub fn shape_arabic(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|c| arabic_forms(c).is_some()) {
+        return Cow::Borrowed(text);
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let neighbour = |mut i: usize, forward: bool| -> Option<char> {
+        loop {
+            if forward {
+                i += 1;
+            } else {
+                i = i.checked_sub(1)?;
+            }
+            let c = *chars.get(i)?;
+            if !is_transparent(c) {
+                return Some(c);
+            }
+        }
+    };
+
+    let mut shaped = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let Some((isolated, joining)) = arabic_forms(c) else {
+            shaped.push(c);
+            i += 1;
+            continue;
+        };
+
+        let prev = neighbour(i, false).is_some_and(joins_next) && c != '\u{0621}';
+
+        // lam followed directly by alef becomes a single ligature
+        if c == '\u{0644}' {
+            if let Some(ligature) = chars.get(i + 1).and_then(|alef| lam_alef(*alef)) {
+                let form = if prev { ligature + 1 } else { ligature };
+                shaped.extend(char::from_u32(form));
+                i += 2;
+                continue;
+            }
+        }
+
+        let next = joining == Joining::Dual && neighbour(i, true).is_some_and(joins_prev);
+        let form = match (prev, next) {
+            (false, false) => isolated,
+            (true, false) => isolated + 1,
+            (false, true) => isolated + 2,
+            (true, true) => isolated + 3,
+        };
+        shaped.extend(char::from_u32(form));
+        i += 1;
+    }
+
+    Cow::Owned(shaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_word_positions() {
+        // beh beh beh: initial, medial, final
+        assert_eq!(
+            shape_arabic("\u{0628}\u{0628}\u{0628}"),
+            "\u{FE91}\u{FE92}\u{FE90}"
+        );
+        // alef doesn't join forwards, so the beh after it is isolated
+        assert_eq!(shape_arabic("\u{0627}\u{0628}"), "\u{FE8D}\u{FE8F}");
+    }
+
+    #[test]
+    fn shapes_lam_alef() {
+        assert_eq!(shape_arabic("\u{0644}\u{0627}"), "\u{FEFB}");
+        assert_eq!(shape_arabic("\u{0628}\u{0644}\u{0627}"), "\u{FE91}\u{FEFC}");
+    }
+
+    #[test]
+    fn leaves_other_text_alone() {
+        assert!(matches!(shape_arabic("hello"), Cow::Borrowed("hello")));
+    }
+
+    #[test]
+    fn detects_direction() {
+        assert!(is_rtl("שלום עולם"));
+        assert!(!is_rtl("hello שלום"));
+        assert!(has_rtl("hello שלום"));
+        assert!(!has_rtl("hello"));
+    }
+
+    #[test]
+    fn rtl_words_in_order() {
+        let runs = runs("שלום עולם", true);
+        assert_eq!(
+            runs,
+            vec![
+                TextRun::Text(Cow::Owned(" םולש".to_string())),
+                TextRun::Text(Cow::Owned("םלוע".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn rtl_inside_ltr() {
+        let runs = runs("say שלום ok", false);
+        assert_eq!(
+            runs,
+            vec![
+                TextRun::Text(Cow::Borrowed("say ")),
+                TextRun::Text(Cow::Owned("םולש".to_string())),
+                TextRun::Text(Cow::Borrowed(" ok")),
+            ]
+        );
+    }
+}
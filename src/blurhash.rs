@@ -0,0 +1,137 @@
+use crate::{Error, Result};
+use egui::{Color32, ColorImage};
+use std::f32::consts::PI;
+
+const BASE83: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn decode83(s: &str) -> Result<u32> {
+    let mut value: u32 = 0;
+    for c in s.bytes() {
+        let digit = BASE83
+            .iter()
+            .position(|b| *b == c)
+            .ok_or_else(|| Error::Generic(format!("invalid blurhash character {:?}", c)))?;
+        value = value * 83 + digit as u32;
+    }
+    Ok(value)
+}
+
+fn srgb_to_linear(value: u32) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    if v <= 0.003_130_8 {
+        (v * 12.92 * 255.0 + 0.5) as u8
+    } else {
+        ((1.055 * v.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u8
+    }
+}
+
+fn sign_pow(value: f32, exp: f32) -> f32 {
+    value.abs().powf(exp).copysign(value)
+}
+
+/// Decode a blurhash into a `width` x `height` image
+pub fn decode(hash: &str, width: usize, height: usize) -> Result<ColorImage> {
+    if hash.len() < 6 || !hash.is_ascii() {
+        return Err(Error::Generic(format!("invalid blurhash {:?}", hash)));
+    }
+
+    let size_flag = decode83(&hash[0..1])?;
+    let num_y = (size_flag / 9 + 1) as usize;
+    let num_x = (size_flag % 9 + 1) as usize;
+
+    let expected_len = 4 + 2 * num_x * num_y;
+    if hash.len() != expected_len {
+        return Err(Error::Generic(format!(
+            "blurhash length {} should be {}",
+            hash.len(),
+            expected_len
+        )));
+    }
+
+    let max_value = (decode83(&hash[1..2])? + 1) as f32 / 166.0;
+
+    let mut colors: Vec<[f32; 3]> = Vec::with_capacity(num_x * num_y);
+
+    let dc = decode83(&hash[2..6])?;
+    colors.push([
+        srgb_to_linear(dc >> 16),
+        srgb_to_linear((dc >> 8) & 255),
+        srgb_to_linear(dc & 255),
+    ]);
+
+    for i in 1..num_x * num_y {
+        let start = 4 + i * 2;
+        let ac = decode83(&hash[start..start + 2])?;
+        let quant = |v: u32| sign_pow((v as f32 - 9.0) / 9.0, 2.0) * max_value;
+        colors.push([quant(ac / (19 * 19)), quant((ac / 19) % 19), quant(ac % 19)]);
+    }
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel = [0.0f32; 3];
+            for j in 0..num_y {
+                for i in 0..num_x {
+                    let basis = (PI * x as f32 * i as f32 / width as f32).cos()
+                        * (PI * y as f32 * j as f32 / height as f32).cos();
+                    let color = colors[i + j * num_x];
+                    pixel[0] += color[0] * basis;
+                    pixel[1] += color[1] * basis;
+                    pixel[2] += color[2] * basis;
+                }
+            }
+
+            pixels.push(Color32::from_rgb(
+                linear_to_srgb(pixel[0]),
+                linear_to_srgb(pixel[1]),
+                linear_to_srgb(pixel[2]),
+            ));
+        }
+    }
+
+    Ok(ColorImage {
+        size: [width, height],
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_to_requested_size() {
+        let img = decode("LEHV6nWB2yk8pyo0adR*.7kCMdnj", 8, 4).expect("valid blurhash");
+        assert_eq!(img.size, [8, 4]);
+        assert_eq!(img.pixels.len(), 32);
+    }
+
+    #[test]
+    fn solid_color() {
+        // a single DC component of pure red
+        let img = decode("00TI:j", 2, 2).expect("valid blurhash");
+        for pixel in img.pixels {
+            assert_eq!(pixel, Color32::from_rgb(0xff, 0x00, 0x00));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_length() {
+        assert!(decode("LEHV6nWB2yk8", 4, 4).is_err());
+    }
+
+    #[test]
+    fn rejects_bad_chars() {
+        assert!(decode("00\"\"\"\"", 4, 4).is_err());
+    }
+}
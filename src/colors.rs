@@ -4,7 +4,7 @@ pub const PURPLE: Color32 = Color32::from_rgb(0xCC, 0x43, 0xC5);
 //pub const DARK_BG: Color32 = egui::Color32::from_rgb(40, 44, 52);
 pub const GRAY_SECONDARY: Color32 = Color32::from_rgb(0x8A, 0x8A, 0x8A);
 const BLACK: Color32 = Color32::from_rgb(0x00, 0x00, 0x00);
-const RED_700: Color32 = Color32::from_rgb(0xC7, 0x37, 0x5A);
+pub const RED_700: Color32 = Color32::from_rgb(0xC7, 0x37, 0x5A);
 const GREEN_700: Color32 = Color32::from_rgb(0x24, 0xEC, 0xC9);
 const ORANGE_700: Color32 = Color32::from_rgb(0xF6, 0xB1, 0x4A);
 
@@ -12,13 +12,14 @@ const ORANGE_700: Color32 = Color32::from_rgb(0xF6, 0xB1, 0x4A);
 const SEMI_DARKER_BG: Color32 = Color32::from_rgb(0x39, 0x39, 0x39);
 const DARKER_BG: Color32 = Color32::from_rgb(0x1F, 0x1F, 0x1F);
 const DARK_BG: Color32 = Color32::from_rgb(0x2C, 0x2C, 0x2C);
-const DARK_ISH_BG: Color32 = Color32::from_rgb(0x22, 0x22, 0x22);
+pub const DARK_ISH_BG: Color32 = Color32::from_rgb(0x22, 0x22, 0x22);
 const SEMI_DARK_BG: Color32 = Color32::from_rgb(0x44, 0x44, 0x44);
 
 const LIGHT_GRAY: Color32 = Color32::from_rgb(0xc8, 0xc8, 0xc8); // 78%
 pub const MID_GRAY: Color32 = Color32::from_rgb(0xbd, 0xbd, 0xbd);
 const DARKER_GRAY: Color32 = Color32::from_rgb(0xa5, 0xa5, 0xa5); // 65%
-const EVEN_DARKER_GRAY: Color32 = Color32::from_rgb(0x89, 0x89, 0x89); // 54%
+pub const EVEN_DARKER_GRAY: Color32 = Color32::from_rgb(0x89, 0x89, 0x89); // 54%
+pub const LIGHTER_GRAY: Color32 = Color32::from_rgb(0xf2, 0xf2, 0xf2); // 95%
 
 pub struct ColorTheme {
     // VISUALS
@@ -8,6 +8,12 @@ pub enum Error {
     Nostr(enostr::Error),
     Ndb(nostrdb::Error),
     Image(image::error::ImageError),
+    /// A download was larger than we are willing to buffer
+    TooLarge {
+        max_bytes: u64,
+    },
+    /// A fetch was given up on before it finished
+    Canceled,
     Generic(String),
 }
 
@@ -23,6 +29,10 @@ impl fmt::Display for Error {
             Self::Nostr(e) => write!(f, "{e}"),
             Self::Ndb(e) => write!(f, "{e}"),
             Self::Image(e) => write!(f, "{e}"),
+            Self::TooLarge { max_bytes } => {
+                write!(f, "download exceeds the {max_bytes} byte limit")
+            }
+            Self::Canceled => write!(f, "canceled"),
             Self::Generic(e) => write!(f, "{e}"),
             Self::Io(e) => write!(f, "{e}"),
         }
@@ -1,15 +1,26 @@
-use egui::{FontData, FontDefinitions, FontTweak};
+use egui::{FontData, FontDefinitions, FontFamily, FontTweak};
 use std::collections::BTreeMap;
 use tracing::debug;
 
+#[cfg(not(feature = "embedded-fonts"))]
+use std::{
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+#[cfg(not(feature = "embedded-fonts"))]
+use tracing::error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NamedFontFamily {
     Medium,
+    Bold,
 }
 
 impl NamedFontFamily {
     pub fn as_str(&mut self) -> &'static str {
         match self {
-            //Self::Bold => "bold",
+            Self::Bold => "bold",
             Self::Medium => "medium",
         }
     }
@@ -19,6 +30,233 @@ impl NamedFontFamily {
     }
 }
 
+/// Which family a runtime-loaded font is added to
+pub enum FontSlot {
+    Proportional,
+    Monospace,
+    Named(NamedFontFamily),
+}
+
+impl FontSlot {
+    fn family(self) -> FontFamily {
+        match self {
+            Self::Proportional => FontFamily::Proportional,
+            Self::Monospace => FontFamily::Monospace,
+            Self::Named(mut named) => named.as_family(),
+        }
+    }
+}
+
+fn fonts_id() -> egui::Id {
+    egui::Id::new("notedeck_font_definitions")
+}
+
+fn font_scale_id() -> egui::Id {
+    egui::Id::new("notedeck_font_scale")
+}
+
+/// Apply font definitions, remembering them so they can be extended later.
+/// egui doesn't let us read back what we gave it. The definitions are
+/// stored unscaled and the global font scale is applied on top.
+fn set_fonts(ctx: &egui::Context, defs: FontDefinitions) {
+    ctx.data_mut(|d| d.insert_temp(fonts_id(), defs.clone()));
+    ctx.set_fonts(scaled_fonts(defs, font_scale(ctx)));
+}
+
+fn scaled_fonts(mut defs: FontDefinitions, scale: f32) -> FontDefinitions {
+    for data in defs.font_data.values_mut() {
+        // compose with any per-font tweak, eg. Inconsolata's
+        data.tweak.scale *= scale;
+    }
+    defs
+}
+
+/// The global multiplier applied to every font's size
+pub fn font_scale(ctx: &egui::Context) -> f32 {
+    ctx.data(|d| d.get_temp(font_scale_id())).unwrap_or(1.0)
+}
+
+/// Scale all text in the app by `scale`, on top of each font's own tweaks.
+/// This is finer grained than egui's zoom, which also scales the layout.
+pub fn apply_font_scale(ctx: &egui::Context, scale: f32) {
+    ctx.data_mut(|d| d.insert_temp(font_scale_id(), scale));
+    set_fonts(ctx, current_fonts(ctx));
+}
+
+fn current_fonts(ctx: &egui::Context) -> FontDefinitions {
+    ctx.data(|d| d.get_temp(fonts_id())).unwrap_or_default()
+}
+
+/// Append a font to the end of a family's fallback chain, unless it's
+/// already there
+fn push_fallback(defs: &mut FontDefinitions, family: FontFamily, name: &str) {
+    let chain = defs.families.entry(family).or_default();
+    if !chain.iter().any(|font| font == name) {
+        chain.push(name.to_owned());
+    }
+}
+
+/// Add a font at runtime, eg. one the user picked to cover a script we
+/// don't ship. The font is appended to the end of `slot`'s fallback chain.
+/// Calling this again with the same name replaces the font data.
+pub fn add_font(ctx: &egui::Context, name: &str, data: Vec<u8>, slot: FontSlot) {
+    let mut defs = current_fonts(ctx);
+    defs.font_data
+        .insert(name.to_owned(), FontData::from_owned(data));
+    push_fallback(&mut defs, slot.family(), name);
+    set_fonts(ctx, defs);
+}
+
+/// Large fonts that are only needed for some scripts. With the
+/// `embedded-fonts` feature these are built into the binary, otherwise
+/// they're loaded from disk by a [`FontLoader`] the first time we see a
+/// character they cover.
+struct OptionalFont {
+    name: &'static str,
+    #[cfg(feature = "embedded-fonts")]
+    data: &'static [u8],
+    #[cfg(not(feature = "embedded-fonts"))]
+    file: &'static str,
+    #[cfg(not(feature = "embedded-fonts"))]
+    ranges: &'static [RangeInclusive<char>],
+    tweak: FontTweak,
+    /// Whether this is a fallback for every family or just proportional text
+    all_families: bool,
+}
+
+const OPTIONAL_FONTS: [OptionalFont; 2] = [
+    // Some good looking emojis. Use as first priority:
+    OptionalFont {
+        name: "NotoEmoji",
+        #[cfg(feature = "embedded-fonts")]
+        data: include_bytes!("../assets/fonts/NotoEmoji-Regular.ttf"),
+        #[cfg(not(feature = "embedded-fonts"))]
+        file: "NotoEmoji-Regular.ttf",
+        #[cfg(not(feature = "embedded-fonts"))]
+        ranges: &['\u{2600}'..='\u{27BF}', '\u{1F000}'..='\u{1FAFF}'],
+        tweak: FontTweak {
+            scale: 1.1, // make them a touch larger
+            y_offset_factor: 0.0,
+            y_offset: 0.0,
+            baseline_offset_factor: 0.0,
+        },
+        all_families: true,
+    },
+    OptionalFont {
+        name: "NotoSansCJK",
+        #[cfg(feature = "embedded-fonts")]
+        data: include_bytes!("../assets/fonts/NotoSansCJK-Regular.ttc"),
+        #[cfg(not(feature = "embedded-fonts"))]
+        file: "NotoSansCJK-Regular.ttc",
+        #[cfg(not(feature = "embedded-fonts"))]
+        ranges: &[
+            '\u{1100}'..='\u{11FF}', // hangul jamo
+            '\u{2E80}'..='\u{9FFF}', // cjk radicals through unified ideographs
+            '\u{AC00}'..='\u{D7AF}', // hangul syllables
+            '\u{F900}'..='\u{FAFF}', // compatibility ideographs
+            '\u{FF00}'..='\u{FFEF}', // full and half width forms
+        ],
+        tweak: FontTweak {
+            scale: 1.0,
+            y_offset_factor: 0.0,
+            y_offset: 0.0,
+            baseline_offset_factor: 0.0,
+        },
+        all_families: false,
+    },
+];
+
+fn insert_optional_font(defs: &mut FontDefinitions, font: &OptionalFont, data: FontData) {
+    defs.font_data
+        .insert(font.name.to_owned(), data.tweak(font.tweak));
+
+    push_fallback(defs, FontFamily::Proportional, font.name);
+    if font.all_families {
+        push_fallback(defs, FontFamily::Monospace, font.name);
+        push_fallback(defs, NamedFontFamily::Medium.as_family(), font.name);
+        push_fallback(defs, NamedFontFamily::Bold.as_family(), font.name);
+    }
+}
+
+/// Loads the [`OPTIONAL_FONTS`] from `dir` as text needing them shows up
+#[cfg(not(feature = "embedded-fonts"))]
+pub struct FontLoader {
+    dir: PathBuf,
+    /// Fonts we've loaded, or tried to and failed
+    attempted: Vec<&'static str>,
+}
+
+#[cfg(not(feature = "embedded-fonts"))]
+impl FontLoader {
+    pub fn new(dir: PathBuf) -> Self {
+        FontLoader {
+            dir,
+            attempted: vec![],
+        }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        PathBuf::from("assets/fonts")
+    }
+
+    fn id() -> egui::Id {
+        egui::Id::new("notedeck_font_loader")
+    }
+
+    /// Make this the loader used by [`load_fonts_for`]
+    pub fn install(self, ctx: &egui::Context) {
+        ctx.data_mut(|d| d.insert_temp(Self::id(), Arc::new(Mutex::new(self))));
+    }
+
+    /// Load any fonts needed to render `text` that we haven't loaded yet
+    pub fn load_for(&mut self, ctx: &egui::Context, text: &str) {
+        for font in &OPTIONAL_FONTS {
+            if self.attempted.contains(&font.name) {
+                continue;
+            }
+
+            let needed = text
+                .chars()
+                .any(|c| font.ranges.iter().any(|range| range.contains(&c)));
+            if !needed {
+                continue;
+            }
+
+            self.attempted.push(font.name);
+            let path = self.dir.join(font.file);
+            match std::fs::read(&path) {
+                Ok(data) => {
+                    debug!("lazily loaded font {} from {:?}", font.name, path);
+                    let mut defs = current_fonts(ctx);
+                    insert_optional_font(&mut defs, font, FontData::from_owned(data));
+                    set_fonts(ctx, defs);
+                }
+                Err(err) => error!("could not load font {:?}: {}", path, err),
+            }
+        }
+    }
+}
+
+/// Make sure the fonts needed to render `text` are loaded. This is a no-op
+/// when the optional fonts are embedded.
+pub fn load_fonts_for(ctx: &egui::Context, text: &str) {
+    // everything ascii is covered by the fonts we always ship
+    if text.is_ascii() {
+        return;
+    }
+
+    #[cfg(not(feature = "embedded-fonts"))]
+    {
+        let loader: Option<Arc<Mutex<FontLoader>>> = ctx.data(|d| d.get_temp(FontLoader::id()));
+        if let Some(loader) = loader {
+            loader.lock().unwrap().load_for(ctx, text);
+        }
+    }
+
+    #[cfg(feature = "embedded-fonts")]
+    let _ = ctx;
+}
+
 // Use gossip's approach to font loading. This includes japanese fonts
 // for rending stuff from japanese users.
 pub fn setup_fonts(ctx: &egui::Context) {
@@ -43,7 +281,7 @@ pub fn setup_fonts(ctx: &egui::Context) {
         "DejaVuSans".to_owned(),
         FontData::from_static(include_bytes!("../assets/fonts/DejaVuSansSansEmoji.ttf")),
     );
-    /*
+
     font_data.insert(
         "OnestBold".to_owned(),
         FontData::from_static(include_bytes!(
@@ -58,18 +296,6 @@ pub fn setup_fonts(ctx: &egui::Context) {
         )),
     );
 
-    font_data.insert(
-        "DejaVuSans".to_owned(),
-        FontData::from_static(include_bytes!("../assets/fonts/DejaVuSansSansEmoji.ttf")),
-    );
-    font_data.insert(
-        "DejaVuSansBold".to_owned(),
-        FontData::from_static(include_bytes!(
-            "../assets/fonts/DejaVuSans-Bold-SansEmoji.ttf"
-        )),
-    );
-    */
-
     font_data.insert(
         "Inconsolata".to_owned(),
         FontData::from_static(include_bytes!("../assets/fonts/Inconsolata-Regular.ttf")).tweak(
@@ -82,50 +308,41 @@ pub fn setup_fonts(ctx: &egui::Context) {
         ),
     );
 
-    font_data.insert(
-        "NotoSansCJK".to_owned(),
-        FontData::from_static(include_bytes!("../assets/fonts/NotoSansCJK-Regular.ttc")),
-    );
-
-    // Some good looking emojis. Use as first priority:
-    font_data.insert(
-        "NotoEmoji".to_owned(),
-        FontData::from_static(include_bytes!("../assets/fonts/NotoEmoji-Regular.ttf")).tweak(
-            FontTweak {
-                scale: 1.1, // make them a touch larger
-                y_offset_factor: 0.0,
-                y_offset: 0.0,
-                baseline_offset_factor: 0.0,
-            },
-        ),
-    );
-
-    let proportional = vec![
-        "Onest".to_owned(),
-        "DejaVuSans".to_owned(),
-        "NotoEmoji".to_owned(),
-        "NotoSansCJK".to_owned(),
-    ];
+    // Onest only covers latin and cyrillic. DejaVu Sans has hebrew, arabic
+    // and the arabic presentation forms that bidi::shape_arabic joins
+    // letters with, so every family falls back to it rather than shipping a
+    // separate font for right-to-left scripts.
+    let proportional = vec!["Onest".to_owned(), "DejaVuSans".to_owned()];
 
     families.insert(egui::FontFamily::Proportional, proportional);
 
-    families.insert(
-        egui::FontFamily::Monospace,
-        vec!["Inconsolata".to_owned(), "NotoEmoji".to_owned()],
-    );
+    families.insert(egui::FontFamily::Monospace, vec!["Inconsolata".to_owned()]);
 
     families.insert(
         egui::FontFamily::Name(NamedFontFamily::Medium.as_str().into()),
-        //egui::FontFamily::Name("bold".into()),
-        vec!["OnestMedium".to_owned(), "NotoEmoji".to_owned()],
+        vec!["OnestMedium".to_owned(), "DejaVuSans".to_owned()],
     );
 
-    debug!("fonts: {:?}", families);
+    families.insert(
+        egui::FontFamily::Name(NamedFontFamily::Bold.as_str().into()),
+        vec!["OnestBold".to_owned(), "DejaVuSansBold".to_owned()],
+    );
 
-    let defs = FontDefinitions {
+    #[allow(unused_mut)]
+    let mut defs = FontDefinitions {
         font_data,
         families,
     };
 
-    ctx.set_fonts(defs);
+    #[cfg(feature = "embedded-fonts")]
+    for font in &OPTIONAL_FONTS {
+        insert_optional_font(&mut defs, font, FontData::from_static(font.data));
+    }
+
+    #[cfg(not(feature = "embedded-fonts"))]
+    FontLoader::new(FontLoader::default_dir()).install(ctx);
+
+    debug!("fonts: {:?}", defs.families);
+
+    set_fonts(ctx, defs);
 }
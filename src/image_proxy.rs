@@ -0,0 +1,89 @@
+/// A resizing service that images can be fetched through, so we download a
+/// thumbnail of the size we need instead of the full original
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageProxy {
+    /// A weserv style proxy like wsrv.nl, which takes the url and size as
+    /// query parameters
+    Weserv { base: String },
+    /// An imgproxy instance with url signing turned off
+    Imgproxy { base: String },
+}
+
+impl ImageProxy {
+    /// The public weserv instance
+    pub fn wsrv() -> Self {
+        ImageProxy::Weserv {
+            base: "https://wsrv.nl".to_owned(),
+        }
+    }
+
+    /// The url to fetch `url` through, scaled down to fit within `size`
+    /// pixels. Images are never scaled up.
+    pub fn rewrite(&self, url: &str, size: u32) -> String {
+        match self {
+            ImageProxy::Weserv { base } => format!(
+                "{}/?url={}&w={}&h={}&fit=inside&we",
+                base.trim_end_matches('/'),
+                percent_encode(url),
+                size,
+                size
+            ),
+            ImageProxy::Imgproxy { base } => format!(
+                "{}/insecure/rs:fit:{}:{}:0/plain/{}",
+                base.trim_end_matches('/'),
+                size,
+                size,
+                percent_encode(url)
+            ),
+        }
+    }
+
+    /// Like [`Self::rewrite`], but asks to keep every frame of animated
+    /// images. imgproxy instances only do this when configured to allow
+    /// more than one animation frame.
+    pub fn rewrite_animated(&self, url: &str, size: u32) -> String {
+        match self {
+            ImageProxy::Weserv { .. } => format!("{}&n=-1", self.rewrite(url, size)),
+            ImageProxy::Imgproxy { .. } => self.rewrite(url, size),
+        }
+    }
+}
+
+/// Escape everything but RFC 3986 unreserved characters, so a url can be
+/// passed inside another one
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_urls() {
+        let url = "https://example.com/a b.jpg?x=1";
+        assert_eq!(
+            ImageProxy::wsrv().rewrite(url, 128),
+            "https://wsrv.nl/?url=https%3A%2F%2Fexample.com%2Fa%20b.jpg%3Fx%3D1&w=128&h=128&fit=inside&we"
+        );
+
+        let imgproxy = ImageProxy::Imgproxy {
+            base: "https://img.example.org/".to_owned(),
+        };
+        assert_eq!(
+            imgproxy.rewrite("https://example.com/a.png", 64),
+            "https://img.example.org/insecure/rs:fit:64:64:0/plain/https%3A%2F%2Fexample.com%2Fa.png"
+        );
+        assert!(ImageProxy::wsrv()
+            .rewrite_animated(url, 128)
+            .ends_with("&fit=inside&we&n=-1"));
+    }
+}
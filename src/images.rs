@@ -1,11 +1,28 @@
 use crate::error::Error;
-use crate::imgcache::ImageCache;
+use crate::exif;
+use crate::image_proxy::ImageProxy;
+use crate::imgcache::{
+    CacheFormat, DominantColors, FetchQueue, FetchQueueHandle, ImageCache, ImageCounters, InFlight,
+};
 use crate::result::Result;
+use base64::Engine;
 use egui::{pos2, Color32, ColorImage, Rect, Sense, SizeHint, TextureHandle};
+use ehttp::streaming::Part;
+use image::codecs::gif::GifDecoder;
+use image::codecs::jpeg::JpegDecoder;
 use image::imageops::FilterType;
+use image::AnimationDecoder;
 use poll_promise::Promise;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::ControlFlow;
 use std::path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::fs;
+use tracing::debug;
 
 //pub type ImageCacheKey = String;
 //pub type ImageCacheValue = Promise<Result<TextureHandle>>;
@@ -102,21 +119,193 @@ pub fn round_image(image: &mut ColorImage) {
     }
 }
 
-fn process_pfp_bitmap(size: u32, image: &mut image::DynamicImage) -> ColorImage {
+/// A deterministic avatar for a pubkey: a mirrored 5x5 grid of cells in a
+/// color derived from the key, for when a profile picture is unavailable.
+pub fn identicon(pubkey: &[u8; 32], size: u32, shape: AvatarShape) -> ColorImage {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
-    // Crop square
-    let smaller = image.width().min(image.height());
+    // 5 cells plus a one cell margin on each side
+    let grid = 7;
+    let size = size as usize;
+    let hue = pubkey[0] as f32 / 255.0;
+    let fg: Color32 = egui::ecolor::Hsva::new(hue, 0.55, 0.85, 1.0).into();
+    let bg = Color32::from_gray(0xEC);
 
-    if image.width() > smaller {
-        let excess = image.width() - smaller;
-        *image = image.crop_imm(excess / 2, 0, image.width() - excess, image.height());
-    } else if image.height() > smaller {
-        let excess = image.height() - smaller;
-        *image = image.crop_imm(0, excess / 2, image.width(), image.height() - excess);
+    let filled = |cell_x: usize, cell_y: usize| -> bool {
+        if cell_x == 0 || cell_y == 0 || cell_x == grid - 1 || cell_y == grid - 1 {
+            return false;
+        }
+        let (col, row) = (cell_x - 1, cell_y - 1);
+        // mirror the right half onto the left
+        let col = col.min(4 - col);
+        pubkey[1 + row * 3 + col] & 1 == 1
+    };
+
+    let mut pixels = Vec::with_capacity(size * size);
+    for y in 0..size {
+        for x in 0..size {
+            let on = filled(x * grid / size, y * grid / size);
+            pixels.push(if on { fg } else { bg });
+        }
+    }
+
+    let mut img = ColorImage {
+        size: [size, size],
+        pixels,
+    };
+    apply_shape(&mut img, shape);
+    img
+}
+
+/// Which filter to use when downscaling images. Faster filters are useful for
+/// the many small avatars in a timeline.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeQuality {
+    Fast,
+    #[default]
+    Balanced,
+    Best,
+}
+
+impl ResizeQuality {
+    pub fn filter(self) -> FilterType {
+        match self {
+            ResizeQuality::Fast => FilterType::Triangle,
+            ResizeQuality::Balanced => FilterType::CatmullRom,
+            ResizeQuality::Best => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How soon a download should start when more are wanted than
+/// [`ImageCache::max_in_flight`] allows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Avatars and other small things there are a lot of
+    Low,
+    #[default]
+    Normal,
+    /// What the user is looking at, like the focused note's media
+    High,
+}
+
+/// Mask out the corners of an image, leaving a rectangle with corners of
+/// `radius` pixels. Edges are antialiased like [`round_image`].
+pub fn round_rect_image(image: &mut ColorImage, radius: f32) {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let width = image.size[0] as f32;
+    let height = image.size[1] as f32;
+    let radius = radius.clamp(0.0, width.min(height) / 2.0);
+    if radius <= 0.0 {
+        return;
     }
-    let image = image.resize(size, size, FilterType::CatmullRom); // DynamicImage
+
+    for (pixnum, pixel) in image.pixels.iter_mut().enumerate() {
+        let x = (pixnum % image.size[0]) as f32 + 0.5;
+        let y = (pixnum / image.size[0]) as f32 + 0.5;
+
+        // the center of the nearest corner arc
+        let cx = x.clamp(radius, width - radius);
+        let cy = y.clamp(radius, height - radius);
+
+        // not in a corner
+        if cx == x || cy == y {
+            continue;
+        }
+
+        let distance = radius - ((x - cx).powi(2) + (y - cy).powi(2)).sqrt();
+        if distance <= 0.0 {
+            *pixel = Color32::TRANSPARENT;
+        } else if distance <= 1.0 {
+            *pixel = Color32::from_rgba_premultiplied(
+                (pixel.r() as f32 * distance) as u8,
+                (pixel.g() as f32 * distance) as u8,
+                (pixel.b() as f32 * distance) as u8,
+                (pixel.a() as f32 * distance) as u8,
+            );
+        }
+    }
+}
+
+/// The outline of a profile picture
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum AvatarShape {
+    #[default]
+    Circle,
+    Square,
+    /// `radius` is a fraction of the avatar's width, from 0.0 (square) to 0.5
+    /// (circle), so the corners look the same at any display size.
+    RoundedRect {
+        radius: f32,
+    },
+}
+
+impl AvatarShape {
+    /// Distinguishes cached copies of the same image in different shapes.
+    /// This is empty for circles so existing cache entries are still valid.
+    pub fn cache_tag(&self) -> String {
+        match self {
+            AvatarShape::Circle => "".to_string(),
+            AvatarShape::Square => "square".to_string(),
+            AvatarShape::RoundedRect { radius } => format!("rounded{}", radius),
+        }
+    }
+}
+
+/// What an image is for, which decides how it is resized and masked
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ImageType {
+    /// A profile picture, cropped square and masked to a shape
+    Profile(AvatarShape),
+    /// An image embedded in a note, scaled down to fit within the requested
+    /// size but otherwise left as it is
+    #[default]
+    Content,
+    /// A profile banner, cropped to [`BANNER_ASPECT_RATIO`] and scaled to
+    /// the requested width
+    Banner,
+}
+
+/// How much wider than tall banners are cropped
+pub const BANNER_ASPECT_RATIO: f32 = 3.0;
+
+impl ImageType {
+    /// Distinguishes cached copies of the same url used in different ways
+    pub fn cache_tag(&self) -> String {
+        match self {
+            ImageType::Profile(shape) => shape.cache_tag(),
+            ImageType::Content => "content".to_string(),
+            ImageType::Banner => "banner".to_string(),
+        }
+    }
+}
+
+/// Mask an image to the given avatar shape
+pub fn apply_shape(image: &mut ColorImage, shape: AvatarShape) {
+    match shape {
+        AvatarShape::Circle => round_image(image),
+        AvatarShape::Square => {}
+        AvatarShape::RoundedRect { radius } => {
+            let radius = radius * image.size[0] as f32;
+            round_rect_image(image, radius);
+        }
+    }
+}
+
+fn process_pfp_bitmap(
+    size: u32,
+    quality: ResizeQuality,
+    shape: AvatarShape,
+    image: &mut image::DynamicImage,
+) -> ColorImage {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    crop_to_aspect(image, 1.0);
+    let image = image.resize(size, size, quality.filter()); // DynamicImage
     let image_buffer = image.into_rgba8(); // RgbaImage (ImageBuffer)
     let mut color_image = ColorImage::from_rgba_unmultiplied(
         [
@@ -125,107 +314,1567 @@ fn process_pfp_bitmap(size: u32, image: &mut image::DynamicImage) -> ColorImage
         ],
         image_buffer.as_flat_samples().as_slice(),
     );
-    round_image(&mut color_image);
+    apply_shape(&mut color_image, shape);
     color_image
 }
 
-fn parse_img_response(response: ehttp::Response, size: u32) -> Result<ColorImage> {
+/// Crop the middle of an image to `aspect_ratio` (width / height)
+fn crop_to_aspect(image: &mut image::DynamicImage, aspect_ratio: f32) {
+    let (width, height) = (image.width(), image.height());
+    let target_width = ((height as f32 * aspect_ratio) as u32).max(1);
+
+    if width > target_width {
+        let excess = width - target_width;
+        *image = image.crop_imm(excess / 2, 0, target_width, height);
+    } else {
+        let target_height = ((width as f32 / aspect_ratio) as u32).max(1);
+        if height > target_height {
+            let excess = height - target_height;
+            *image = image.crop_imm(0, excess / 2, width, target_height);
+        }
+    }
+}
+
+/// Crop a banner to shape, then scale it down to `size` wide
+fn process_banner_bitmap(
+    size: u32,
+    quality: ResizeQuality,
+    image: &mut image::DynamicImage,
+) -> ColorImage {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    crop_to_aspect(image, BANNER_ASPECT_RATIO);
+    if image.width() > size {
+        let height = (size as f32 / BANNER_ASPECT_RATIO) as u32;
+        *image = image.resize_exact(size, height.max(1), quality.filter());
+    }
+
+    let image_buffer = image.to_rgba8();
+    ColorImage::from_rgba_unmultiplied(
+        [
+            image_buffer.width() as usize,
+            image_buffer.height() as usize,
+        ],
+        image_buffer.as_flat_samples().as_slice(),
+    )
+}
+
+/// The part of a texture to draw so it covers a rect without stretching,
+/// cropping whatever sticks out equally from both sides
+pub fn cover_uv(texture_aspect: f32, rect_aspect: f32) -> Rect {
+    if texture_aspect > rect_aspect {
+        let width = rect_aspect / texture_aspect;
+        Rect::from_min_max(
+            pos2((1.0 - width) / 2.0, 0.0),
+            pos2((1.0 + width) / 2.0, 1.0),
+        )
+    } else {
+        let height = texture_aspect / rect_aspect;
+        Rect::from_min_max(
+            pos2(0.0, (1.0 - height) / 2.0),
+            pos2(1.0, (1.0 + height) / 2.0),
+        )
+    }
+}
+
+/// Scale an embedded image down so neither side is larger than `size`,
+/// keeping its aspect ratio. Smaller images are left alone.
+fn process_content_bitmap(
+    size: u32,
+    quality: ResizeQuality,
+    image: &image::DynamicImage,
+) -> ColorImage {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
+    let image = if image.width() > size || image.height() > size {
+        image.resize(size, size, quality.filter())
+    } else {
+        image.clone()
+    };
+    let image_buffer = image.into_rgba8();
+    ColorImage::from_rgba_unmultiplied(
+        [
+            image_buffer.width() as usize,
+            image_buffer.height() as usize,
+        ],
+        image_buffer.as_flat_samples().as_slice(),
+    )
+}
+
+/// The size, in pixels, a content image of `dims` is decoded to when
+/// fetched at `size`, so space can be reserved for it before it arrives
+pub fn fitted_size(dims: [u32; 2], size: u32) -> [u32; 2] {
+    let [width, height] = dims;
+    if width <= size && height <= size {
+        return dims;
+    }
+    let scale = (size as f64 / width as f64).min(size as f64 / height as f64);
+    [
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    ]
+}
+
+/// Parse a NIP-94 `dim` value, eg. "1920x1080"
+pub fn parse_dim(dim: &str) -> Option<[u32; 2]> {
+    let (width, height) = dim.trim().split_once('x')?;
+    let dims = [width.parse().ok()?, height.parse().ok()?];
+    if dims.contains(&0) {
+        None
+    } else {
+        Some(dims)
+    }
+}
+
+fn process_bitmap(
+    size: u32,
+    quality: ResizeQuality,
+    img_type: ImageType,
+    image: &mut image::DynamicImage,
+) -> ColorImage {
+    match img_type {
+        ImageType::Profile(shape) => process_pfp_bitmap(size, quality, shape, image),
+        ImageType::Content => process_content_bitmap(size, quality, image),
+        ImageType::Banner => process_banner_bitmap(size, quality, image),
+    }
+}
+
+/// The frames of an animated image, each paired with how long it is shown
+pub struct AnimatedImage {
+    pub frames: Vec<(ColorImage, Duration)>,
+}
+
+/// The uploaded frames of an [`AnimatedImage`]
+pub struct AnimatedTexture {
+    pub frames: Vec<(TextureHandle, Duration)>,
+}
+
+impl AnimatedTexture {
+    /// The texture to draw this frame. Schedules a repaint for when the
+    /// next frame is due.
+    pub fn current_frame(&self, ctx: &egui::Context) -> &TextureHandle {
+        let delays: Vec<Duration> = self.frames.iter().map(|(_, delay)| *delay).collect();
+        &self.frames[crate::ui::anim::animation_frame(ctx, &delays)].0
+    }
+}
+
+/// Upload every frame, naming their textures after `url`
+fn upload_frames(
+    ctx: &egui::Context,
+    url: &str,
+    frames: Vec<(impl Into<egui::ImageData>, Duration)>,
+) -> AnimatedTexture {
+    AnimatedTexture {
+        frames: frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, (img, delay))| {
+                let name = format!("{}#{}", url, i);
+                (ctx.load_texture(name, img, Default::default()), delay)
+            })
+            .collect(),
+    }
+}
+
+pub enum DecodedImage {
+    Static(ColorImage),
+    Animated(AnimatedImage),
+}
+
+impl DecodedImage {
+    /// Collapse to a single image. Animations use their first frame that
+    /// actually shows something, see [`representative_frame`].
+    pub fn into_static(self) -> ColorImage {
+        match self {
+            DecodedImage::Static(img) => img,
+            DecodedImage::Animated(mut anim) => {
+                let frame = representative_frame(&anim.frames);
+                anim.frames.swap_remove(frame).0
+            }
+        }
+    }
+
+    /// Every frame along with how long it's shown. A static image is a
+    /// single frame.
+    pub fn into_frames(self) -> Vec<(ColorImage, Duration)> {
+        match self {
+            DecodedImage::Static(img) => vec![(img, Duration::ZERO)],
+            DecodedImage::Animated(anim) => anim.frames,
+        }
+    }
+}
+
+/// Whether a frame has nothing worth showing: no opaque pixels, or only a
+/// single flat color like the black a lot of animations fade in from.
+/// Partly transparent pixels, like the edges of a circle mask, are ignored.
+fn is_blank(img: &ColorImage) -> bool {
+    let mut opaque = img.pixels.iter().filter(|p| p.a() == 255);
+    let Some(first) = opaque.next() else {
+        return true;
+    };
+    opaque.all(|p| p == first)
+}
+
+/// The color most of an image is, for painting in its place while it
+/// loads. Opaque pixels are grouped into coarse buckets and the busiest
+/// bucket's average wins, so a picture that's mostly blue with a bit of red
+/// comes out blue rather than purple. Partly transparent pixels, like the
+/// corners a shape mask clears, don't count.
+pub fn dominant_color(image: &ColorImage) -> Color32 {
+    // 4 bits a channel, and the running sums of the pixels in each bucket
+    let mut buckets: HashMap<u16, [u32; 4]> = HashMap::new();
+    for pixel in image.pixels.iter().filter(|p| p.a() == 255) {
+        let (r, g, b) = (pixel.r(), pixel.g(), pixel.b());
+        let bucket = (u16::from(r >> 4) << 8) | (u16::from(g >> 4) << 4) | u16::from(b >> 4);
+        let sums = buckets.entry(bucket).or_default();
+        sums[0] += u32::from(r);
+        sums[1] += u32::from(g);
+        sums[2] += u32::from(b);
+        sums[3] += 1;
+    }
+
+    // ties go to the lowest bucket, so the same image always gets the same color
+    match buckets
+        .into_iter()
+        .max_by_key(|(bucket, sums)| (sums[3], Reverse(*bucket)))
+    {
+        Some((_, [r, g, b, count])) => {
+            Color32::from_rgb((r / count) as u8, (g / count) as u8, (b / count) as u8)
+        }
+        None => Color32::TRANSPARENT,
+    }
+}
+
+/// Remember what color a profile picture mostly is, so it can stand in
+/// for the picture while another copy of it loads
+fn remember_color(colors: &DominantColors, url: &str, img_type: ImageType, img: &ColorImage) {
+    if matches!(img_type, ImageType::Profile(_)) {
+        let color = dominant_color(img);
+        colors.lock().unwrap().insert(url, color);
+    }
+}
+
+/// The frame to show when we only show one: the first that isn't blank,
+/// or the first if they all are
+fn representative_frame(frames: &[(ColorImage, Duration)]) -> usize {
+    frames
+        .iter()
+        .position(|(img, _)| !is_blank(img))
+        .unwrap_or(0)
+}
+
+/// Browsers treat very short gif frame delays as 100ms, so do the same
+fn gif_frame_delay(delay: image::Delay) -> Duration {
+    let delay = Duration::from(delay);
+    if delay < Duration::from_millis(20) {
+        Duration::from_millis(100)
+    } else {
+        delay
+    }
+}
+
+/// Animations stop after this many frames, since each one takes a texture
+const MAX_GIF_FRAMES: usize = 256;
+
+/// Animations also stop once this many pixels have been decoded across all
+/// their frames. Each frame is decoded at full size before it's scaled
+/// down, so a big, long gif could otherwise keep a decode thread busy for
+/// a long time.
+const MAX_GIF_PIXELS: u64 = 64 * 1024 * 1024;
+
+/// Decode a gif, scaling each frame down as soon as it's decoded, so only
+/// one frame is ever held at full size
+fn parse_gif(
+    bytes: &[u8],
+    size: u32,
+    quality: ResizeQuality,
+    img_type: ImageType,
+) -> Result<DecodedImage> {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let decoder = GifDecoder::new(std::io::Cursor::new(bytes))?;
+    let mut frames = vec![];
+    let mut pixels: u64 = 0;
+    for frame in decoder.into_frames() {
+        let frame = match frame {
+            Ok(frame) => frame,
+            // show what we got of a gif that's cut short
+            Err(err) if !frames.is_empty() => {
+                debug!(
+                    "stopped decoding gif after {} frames: {}",
+                    frames.len(),
+                    err
+                );
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let (width, height) = frame.buffer().dimensions();
+        pixels += width as u64 * height as u64;
+        if !frames.is_empty() && (frames.len() == MAX_GIF_FRAMES || pixels > MAX_GIF_PIXELS) {
+            break;
+        }
+
+        let delay = gif_frame_delay(frame.delay());
+        let mut dyn_image = image::DynamicImage::ImageRgba8(frame.into_buffer());
+        frames.push((
+            process_bitmap(size, quality, img_type, &mut dyn_image),
+            delay,
+        ));
+    }
+
+    match frames.len() {
+        0 => Err(Error::Generic("gif has no frames".to_string())),
+        1 => Ok(DecodedImage::Static(frames.swap_remove(0).0)),
+        _ => Ok(DecodedImage::Animated(AnimatedImage { frames })),
+    }
+}
+
+/// Rotate an image decoded from `bytes` upright according to its EXIF
+/// orientation
+fn orient(bytes: &[u8], image: image::DynamicImage) -> image::DynamicImage {
+    match exif::orientation(bytes) {
+        Some(orientation) => exif::apply_orientation(image, orientation),
+        None => image,
+    }
+}
+
+/// Decode an image, rotating it upright according to its EXIF orientation
+fn load_oriented(bytes: &[u8]) -> Result<image::DynamicImage> {
+    Ok(orient(bytes, image::load_from_memory(bytes)?))
+}
+
+/// Whether `bytes` look like a WebP file: a RIFF container of type WEBP.
+/// Some hosts serve these as `application/octet-stream`.
+fn is_webp(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP"
+}
+
+/// How big, in pixels, the quick preview of a large image is
+pub const PREVIEW_SIZE: u32 = 64;
+
+/// Content images smaller than this load fast enough not to need a preview
+const PREVIEW_MIN_BYTES: usize = 256 * 1024;
+
+/// Whether a download is worth decoding a preview of before the real thing.
+/// Animated and vector images are left alone.
+fn wants_preview(content_type: &str, bytes: &[u8]) -> bool {
+    bytes.len() >= PREVIEW_MIN_BYTES
+        && content_type.starts_with("image/")
+        && !content_type.starts_with("image/gif")
+        && !content_type.starts_with("image/svg")
+}
+
+/// Decode a small version of an image as quickly as we can. JPEGs are
+/// downscaled while they are decoded, which skips most of the work.
+fn decode_preview(content_type: &str, bytes: &[u8], size: u32) -> Result<ColorImage> {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let image = if content_type.starts_with("image/jpeg") {
+        let mut decoder = JpegDecoder::new(std::io::Cursor::new(bytes))?;
+        let scale_to = size.min(u16::MAX as u32) as u16;
+        decoder.scale(scale_to, scale_to)?;
+        image::DynamicImage::from_decoder(decoder)?
+    } else {
+        image::load_from_memory(bytes)?
+    };
+
+    let image_buffer = orient(bytes, image).thumbnail(size, size).into_rgba8();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [
+            image_buffer.width() as usize,
+            image_buffer.height() as usize,
+        ],
+        image_buffer.as_flat_samples().as_slice(),
+    ))
+}
+
+/// Reject a download whose sha256 doesn't match what we were told to expect
+fn check_hash(response: ehttp::Response, expected: &[u8; 32]) -> Result<ehttp::Response> {
+    let actual: [u8; 32] = Sha256::digest(&response.bytes).into();
+    if actual == *expected {
+        Ok(response)
+    } else {
+        Err(Error::Generic(format!(
+            "sha256 mismatch for {}: expected {}, got {}",
+            response.url,
+            hex::encode(expected),
+            hex::encode(actual)
+        )))
+    }
+}
+
+pub(crate) fn check_status(response: ehttp::Response) -> Result<ehttp::Response> {
+    if response.ok {
+        Ok(response)
+    } else {
+        Err(Error::Generic(format!(
+            "HTTP {} {} for {}",
+            response.status, response.status_text, response.url
+        )))
+    }
+}
+
+fn parse_img_response(
+    response: ehttp::Response,
+    size: u32,
+    quality: ResizeQuality,
+    img_type: ImageType,
+) -> Result<DecodedImage> {
     let content_type = response.content_type().unwrap_or_default();
+    parse_img_bytes(content_type, &response.bytes, size, quality, img_type)
+}
+
+fn parse_img_bytes(
+    content_type: &str,
+    bytes: &[u8],
+    size: u32,
+    quality: ResizeQuality,
+    img_type: ImageType,
+) -> Result<DecodedImage> {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
 
     if content_type.starts_with("image/svg") {
         #[cfg(feature = "profiling")]
         puffin::profile_scope!("load_svg");
 
-        let mut color_image = egui_extras::image::load_svg_bytes_with_size(
-            &response.bytes,
-            Some(SizeHint::Size(size, size)),
-        )?;
-        round_image(&mut color_image);
-        Ok(color_image)
+        let mut color_image =
+            egui_extras::image::load_svg_bytes_with_size(bytes, Some(SizeHint::Size(size, size)))?;
+        if let ImageType::Profile(shape) = img_type {
+            apply_shape(&mut color_image, shape);
+        }
+        Ok(DecodedImage::Static(color_image))
+    } else if content_type.starts_with("image/gif") {
+        parse_gif(bytes, size, quality, img_type)
+    } else if content_type.starts_with("image/webp") || is_webp(bytes) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_scope!("load_webp");
+        // only the first frame of animated webps, for now
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::WebP)?;
+        let mut dyn_image = orient(bytes, image);
+        Ok(DecodedImage::Static(process_bitmap(
+            size,
+            quality,
+            img_type,
+            &mut dyn_image,
+        )))
     } else if content_type.starts_with("image/") {
         #[cfg(feature = "profiling")]
         puffin::profile_scope!("load_from_memory");
-        let mut dyn_image = image::load_from_memory(&response.bytes)?;
-        Ok(process_pfp_bitmap(size, &mut dyn_image))
+        let mut dyn_image = load_oriented(bytes)?;
+        Ok(DecodedImage::Static(process_bitmap(
+            size,
+            quality,
+            img_type,
+            &mut dyn_image,
+        )))
     } else {
         Err(format!("Expected image, found content-type {:?}", content_type).into())
     }
 }
 
+/// Gives up on a fetch that's no longer wanted, eg. because its note
+/// scrolled away. Clones share the same fetch.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// An image on its way, and a way to stop it. Canceled fetches resolve to
+/// [`Error::Canceled`] and are never written to the disk cache.
+pub struct ImageFetch {
+    pub promise: Promise<Result<TextureHandle>>,
+    pub cancel: CancelToken,
+}
+
+impl ImageFetch {
+    /// A fetch that is local or shared with someone else, so there's
+    /// nothing for us to cancel
+    fn uncancelable(promise: Promise<Result<TextureHandle>>) -> Self {
+        ImageFetch {
+            promise,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+/// The frames of an animated image on their way, and a way to stop them
+pub struct AnimatedFetch {
+    pub promise: Promise<Result<AnimatedTexture>>,
+    pub cancel: CancelToken,
+}
+
+/// What a fetch needs from the [`ImageCache`] that started it, so it can
+/// carry on in the background without it
+#[derive(Clone)]
+struct Fetcher {
+    cache_dir: path::PathBuf,
+    in_flight: InFlight,
+    persistent: Arc<AtomicBool>,
+    used_bytes: Arc<AtomicU64>,
+    max_bytes: u64,
+    counters: Arc<ImageCounters>,
+    colors: DominantColors,
+    queue: FetchQueueHandle,
+    retries: u32,
+    max_download_bytes: u64,
+    proxy: Option<ImageProxy>,
+}
+
+impl Fetcher {
+    fn new(img_cache: &ImageCache) -> Self {
+        Fetcher {
+            cache_dir: img_cache.cache_dir.clone(),
+            in_flight: img_cache.in_flight_handle(),
+            persistent: img_cache.persistent_handle(),
+            used_bytes: img_cache.used_bytes_handle(),
+            max_bytes: img_cache.max_bytes(),
+            counters: img_cache.counters_handle(),
+            colors: img_cache.colors_handle(),
+            queue: img_cache.fetch_queue_handle(),
+            retries: img_cache.fetch_retries(),
+            max_download_bytes: img_cache.max_download_bytes(),
+            proxy: img_cache.proxy().cloned(),
+        }
+    }
+
+    /// Wait for a download slot, then fetch `url`, from `proxied` first if
+    /// given, retrying failures. The slot is freed as soon as the download is
+    /// done, and `on_done` runs on a decode thread, since checking and decoding
+    /// the download is the slow part.
+    fn download(
+        &self,
+        url: &str,
+        proxied: Option<String>,
+        priority: Priority,
+        cancel: CancelToken,
+        on_done: impl FnOnce(Result<ehttp::Response>) + Send + 'static,
+    ) {
+        let slot = self.queue.clone();
+        let retries = self.retries;
+        let max_download_bytes = self.max_download_bytes;
+        let on_done = move |response: Result<ehttp::Response>| {
+            FetchQueue::finish(&slot);
+            spawn_decode(Box::new(move || on_done(response)));
+        };
+
+        let original = url.to_owned();
+        let start = move || {
+            // it scrolled away while waiting its turn
+            if cancel.is_canceled() {
+                on_done(Err(Error::Canceled));
+                return;
+            }
+
+            let Some(proxied) = proxied else {
+                fetch_with_retries(
+                    original,
+                    retries,
+                    INITIAL_RETRY_BACKOFF,
+                    max_download_bytes,
+                    cancel,
+                    Box::new(on_done),
+                );
+                return;
+            };
+
+            let fallback_cancel = cancel.clone();
+            fetch_limited(
+                &proxied,
+                max_download_bytes,
+                cancel,
+                Box::new(move |response| {
+                    let response = response.and_then(check_status).and_then(|resp| {
+                        if resp
+                            .content_type()
+                            .unwrap_or_default()
+                            .starts_with("image/")
+                        {
+                            Ok(resp)
+                        } else {
+                            Err(Error::Generic(format!(
+                                "proxy didn't return an image for {}",
+                                resp.url
+                            )))
+                        }
+                    });
+
+                    match response {
+                        Ok(resp) => on_done(Ok(resp)),
+                        Err(Error::Canceled) => on_done(Err(Error::Canceled)),
+                        Err(err) => {
+                            debug!(
+                                "image proxy failed for {}, fetching it directly: {}",
+                                original, err
+                            );
+                            fetch_with_retries(
+                                original,
+                                retries,
+                                INITIAL_RETRY_BACKOFF,
+                                max_download_bytes,
+                                fallback_cancel,
+                                Box::new(on_done),
+                            );
+                        }
+                    }
+                }),
+            );
+        };
+        FetchQueue::submit(&self.queue, priority, Box::new(start));
+    }
+}
+
+/// Load an image from the disk cache. A file that can't be read back, eg.
+/// because it was cut short, is deleted and the image downloaded again.
 fn fetch_img_from_disk(
+    img_cache: &ImageCache,
     ctx: &egui::Context,
     url: &str,
-    path: &path::Path,
-) -> Promise<Result<TextureHandle>> {
+    options: FetchOptions,
+    key: String,
+) -> ImageFetch {
+    let fetcher = Fetcher::new(img_cache);
     let ctx = ctx.clone();
     let url = url.to_owned();
-    let path = path.to_owned();
-    Promise::spawn_async(async move {
-        let data = fs::read(path).await?;
-        let image_buffer = image::load_from_memory(&data)?;
-
-        // TODO: remove unwrap here
-        let flat_samples = image_buffer.as_flat_samples_u8().unwrap();
-        let img = ColorImage::from_rgba_unmultiplied(
-            [
-                image_buffer.width() as usize,
-                image_buffer.height() as usize,
-            ],
-            flat_samples.as_slice(),
-        );
+    let (sender, promise) = Promise::new();
+    let cancel = CancelToken::default();
+    let fallback_cancel = cancel.clone();
+    tokio::spawn(async move {
+        let path = fetcher.cache_dir.join(&key);
+        match load_img_from_disk(&path, &fetcher.counters).await {
+            Ok(img) => {
+                remember_color(&fetcher.colors, &url, options.img_type, &img);
+                let res = Ok(ctx.load_texture(&url, img, Default::default()));
+                ImageCache::finish_in_flight(&fetcher.in_flight, &key, &res);
+                sender.send(res);
+                ctx.request_repaint();
+            }
+            Err(err) => {
+                debug!(
+                    "fetching {} again, its cached copy is unreadable: {}",
+                    url, err
+                );
+                if let Err(err) = fs::remove_file(&path).await {
+                    debug!("could not remove {:?} from image cache: {}", path, err);
+                }
+                fetch_from_net(fetcher, &ctx, &url, options, None, sender, fallback_cancel);
+            }
+        }
+    });
+
+    ImageFetch { promise, cancel }
+}
 
-        Ok(ctx.load_texture(&url, img, Default::default()))
-    })
+async fn load_img_from_disk(path: &path::Path, counters: &ImageCounters) -> Result<ColorImage> {
+    let data = fs::read(path).await?;
+    if let Err(err) = ImageCache::touch(path) {
+        debug!("could not bump image cache mtime for {:?}: {}", path, err);
+    }
+    let started = Instant::now();
+    let img = ImageCache::read(&data);
+    counters.decoded(started.elapsed());
+    img
 }
 
+/// Split a `data:<mime>;base64,<payload>` uri into its mime type and
+/// decoded payload
+fn parse_data_uri(uri: &str) -> Result<(&str, Vec<u8>)> {
+    let rest = uri
+        .strip_prefix("data:")
+        .ok_or_else(|| Error::Generic("not a data uri".to_string()))?;
+    let (header, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| Error::Generic("data uri is missing its payload".to_string()))?;
+    let mime = header
+        .strip_suffix(";base64")
+        .ok_or_else(|| Error::Generic("only base64 data uris are supported".to_string()))?;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload.trim())
+        .map_err(|err| Error::Generic(format!("invalid base64 in data uri: {}", err)))?;
+    Ok((mime, bytes))
+}
+
+/// Decode an inline `data:` image. These are never written to the disk
+/// cache since they already live in the note or profile.
+fn load_data_uri(
+    ctx: &egui::Context,
+    colors: &DominantColors,
+    uri: &str,
+    size: u32,
+    quality: ResizeQuality,
+    img_type: ImageType,
+) -> Result<TextureHandle> {
+    let (mime, bytes) = parse_data_uri(uri)?;
+    let img = parse_img_bytes(mime, &bytes, size, quality, img_type)?.into_static();
+    remember_color(colors, uri, img_type, &img);
+    Ok(ctx.load_texture(uri, img, Default::default()))
+}
+
+/// What to fetch an image as, and how to check and decode a download of
+/// it, for [`fetch_img`]. Fields that aren't given use their defaults:
+/// `FetchOptions { size, img_type, ..Default::default() }`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FetchOptions {
+    /// How many pixels across to decode the image at
+    pub size: u32,
+    pub quality: ResizeQuality,
+    pub img_type: ImageType,
+    /// The sha256 of the original file, eg. from a NIP-94 `x` tag
+    pub expected_hash: Option<[u8; 32]>,
+    pub priority: Priority,
+}
+
+/// Fetch an image, first from the disk cache and then from the network.
+/// Concurrent requests for the same image share a single fetch. `data:`
+/// uris are decoded immediately.
+///
+/// What gets cached is the decoded image at `options.size`, so SVGs are
+/// only rasterized once per size and later loads skip the SVG renderer.
+///
+/// The `quality` only applies when decoding a fresh download; images
+/// already in the disk cache are returned as they were written. Likewise
+/// the `expected_hash` is checked against fresh downloads before they are
+/// cached.
+///
+/// Downloads wait for a free slot, in `priority` order.
 pub fn fetch_img(
     img_cache: &ImageCache,
     ctx: &egui::Context,
     url: &str,
-    size: u32,
-) -> Promise<Result<TextureHandle>> {
-    let key = ImageCache::key(url);
-    let path = img_cache.cache_dir.join(key);
+    options: FetchOptions,
+) -> ImageFetch {
+    let FetchOptions {
+        size,
+        quality,
+        img_type,
+        ..
+    } = options;
+    if url.starts_with("data:") {
+        let started = Instant::now();
+        let img = load_data_uri(
+            ctx,
+            &img_cache.colors_handle(),
+            url,
+            size,
+            quality,
+            img_type,
+        );
+        img_cache.counters_handle().decoded(started.elapsed());
+        return ImageFetch::uncancelable(Promise::from_ready(img));
+    }
+
+    let key = ImageCache::key(url, size, img_type);
+    if let Some(promise) = img_cache.join_in_flight(&key) {
+        img_cache.counters_handle().hit();
+        return ImageFetch::uncancelable(promise);
+    }
 
-    if path.exists() {
-        fetch_img_from_disk(ctx, url, &path)
+    if ImageCache::is_cached(&img_cache.cache_dir.join(&key)) {
+        img_cache.counters_handle().hit();
+        fetch_img_from_disk(img_cache, ctx, url, options, key)
     } else {
-        fetch_img_from_net(&img_cache.cache_dir, ctx, url, size)
+        fetch_img_from_net(img_cache, ctx, url, options, None)
+    }
+}
+
+/// Like [`fetch_img`], but for large downloads a small, blurry preview is
+/// decoded and sent first, so there's something to show while the full
+/// image decodes. The preview is `None` when there won't be one, eg. when
+/// the image comes from the disk cache, and resolves to an error when the
+/// download turns out not to need one.
+///
+/// The preview can only be decoded once the whole download has arrived,
+/// since none of our decoders work on partial data.
+pub fn fetch_img_progressive(
+    img_cache: &ImageCache,
+    ctx: &egui::Context,
+    url: &str,
+    options: FetchOptions,
+) -> (Option<Promise<Result<TextureHandle>>>, ImageFetch) {
+    if url.starts_with("data:") {
+        return (None, fetch_img(img_cache, ctx, url, options));
+    }
+
+    let key = ImageCache::key(url, options.size, options.img_type);
+    if let Some(promise) = img_cache.join_in_flight(&key) {
+        img_cache.counters_handle().hit();
+        return (None, ImageFetch::uncancelable(promise));
     }
 
-    // TODO: fetch image from local cache
+    if ImageCache::is_cached(&img_cache.cache_dir.join(&key)) {
+        img_cache.counters_handle().hit();
+        return (None, fetch_img_from_disk(img_cache, ctx, url, options, key));
+    }
+
+    let (preview_sender, preview) = Promise::new();
+    let full = fetch_img_from_net(img_cache, ctx, url, options, Some(preview_sender));
+    (Some(preview), full)
+}
+
+/// Only network errors and server errors are worth retrying. Anything else
+/// (404s, oversized or bad content) will fail the same way next time.
+fn is_retryable(response: &Result<ehttp::Response>) -> bool {
+    match response {
+        Err(Error::Generic(_)) => true,
+        Err(_) => false,
+        Ok(resp) => resp.status >= 500,
+    }
+}
+
+pub(crate) type OnFetched = Box<dyn FnOnce(Result<ehttp::Response>) + Send>;
+
+/// Fetch a url, giving up as soon as the body is known to be larger than
+/// `max_bytes`: either from its `Content-Length` or once that much has
+/// actually been received. The download also stops at the next chunk after
+/// `cancel` is canceled.
+pub(crate) fn fetch_limited(url: &str, max_bytes: u64, cancel: CancelToken, on_done: OnFetched) {
+    struct State {
+        response: Option<ehttp::streaming::PartialResponse>,
+        body: Vec<u8>,
+        on_done: Option<OnFetched>,
+    }
+
+    let state = Mutex::new(State {
+        response: None,
+        body: vec![],
+        on_done: Some(on_done),
+    });
+
+    let request = ehttp::Request::get(url);
+    ehttp::streaming::fetch(request, move |part| {
+        let mut state = state.lock().unwrap();
+        let done = match part {
+            _ if cancel.is_canceled() => Some(Err(Error::Canceled)),
+            Err(err) => Some(Err(Error::Generic(err))),
+            Ok(Part::Response(response)) => {
+                let content_length = response
+                    .headers
+                    .get("content-length")
+                    .and_then(|len| len.parse::<u64>().ok());
+                if content_length.is_some_and(|len| len > max_bytes) {
+                    Some(Err(Error::TooLarge { max_bytes }))
+                } else {
+                    state.response = Some(response);
+                    None
+                }
+            }
+            // an empty chunk marks the end of the body
+            Ok(Part::Chunk(chunk)) if chunk.is_empty() => match state.response.take() {
+                Some(response) => Some(Ok(ehttp::Response {
+                    url: response.url,
+                    ok: response.ok,
+                    status: response.status,
+                    status_text: response.status_text,
+                    headers: response.headers,
+                    bytes: std::mem::take(&mut state.body),
+                })),
+                None => Some(Err(Error::Generic("body without response".to_string()))),
+            },
+            Ok(Part::Chunk(chunk)) => {
+                if (state.body.len() + chunk.len()) as u64 > max_bytes {
+                    Some(Err(Error::TooLarge { max_bytes }))
+                } else {
+                    state.body.extend_from_slice(&chunk);
+                    None
+                }
+            }
+        };
+
+        match done {
+            None => ControlFlow::Continue(()),
+            Some(res) => {
+                if let Some(on_done) = state.on_done.take() {
+                    on_done(res);
+                }
+                ControlFlow::Break(())
+            }
+        }
+    });
+}
+
+/// The delay before the first retry. Each subsequent retry doubles it.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Fetch a url, retrying up to `retries` times with exponential backoff
+fn fetch_with_retries(
+    url: String,
+    retries: u32,
+    backoff: Duration,
+    max_bytes: u64,
+    cancel: CancelToken,
+    on_done: OnFetched,
+) {
+    let cloned_url = url.clone();
+    fetch_limited(
+        &cloned_url,
+        max_bytes,
+        cancel.clone(),
+        Box::new(move |response| {
+            if retries == 0 || !is_retryable(&response) {
+                on_done(response);
+                return;
+            }
+
+            debug!("retrying {} in {:?} ({} left)", url, backoff, retries);
+            std::thread::spawn(move || {
+                std::thread::sleep(backoff);
+                if cancel.is_canceled() {
+                    on_done(Err(Error::Canceled));
+                    return;
+                }
+                fetch_with_retries(url, retries - 1, backoff * 2, max_bytes, cancel, on_done);
+            });
+        }),
+    );
+}
+
+type TextureSender = poll_promise::Sender<Result<TextureHandle>>;
+
+type DecodeJob = Box<dyn FnOnce() + Send>;
+
+/// How many threads decode downloaded images
+fn decode_workers() -> usize {
+    std::thread::available_parallelism().map_or(2, |n| n.get().clamp(1, 4))
+}
+
+/// Run `job` on one of the threads that decode downloads, which start the
+/// first time there's something to decode. Downloads finish on whatever
+/// thread the http backend calls back on, which may be the ui thread, so
+/// decoding there would hold up repaints.
+fn spawn_decode(job: DecodeJob) {
+    static DECODER: OnceLock<Mutex<mpsc::Sender<DecodeJob>>> = OnceLock::new();
+
+    let decoder = DECODER.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<DecodeJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..decode_workers() {
+            let receiver = receiver.clone();
+            let worker = std::thread::Builder::new()
+                .name(format!("image-decode-{}", i))
+                .spawn(move || loop {
+                    let job = receiver
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                });
+            if let Err(err) = worker {
+                debug!("could not start image decode thread: {}", err);
+            }
+        }
+        Mutex::new(sender)
+    });
+
+    let sent = decoder
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .send(job);
+    // no workers to take it, so decode it here rather than not at all
+    if let Err(mpsc::SendError(job)) = sent {
+        job();
+    }
+}
+
+/// Send a quick preview of a fresh download, if it's big enough to want one
+fn send_preview(ctx: &egui::Context, url: &str, response: &ehttp::Response, sender: TextureSender) {
+    let content_type = response.content_type().unwrap_or_default();
+    let preview = if wants_preview(content_type, &response.bytes) {
+        decode_preview(content_type, &response.bytes, PREVIEW_SIZE)
+            .map(|img| ctx.load_texture(format!("{}#preview", url), img, Default::default()))
+    } else {
+        Err(Error::Generic(format!(
+            "{} is small enough without a preview",
+            url
+        )))
+    };
+
+    sender.send(preview);
+    ctx.request_repaint();
 }
 
 fn fetch_img_from_net(
-    cache_path: &path::Path,
+    img_cache: &ImageCache,
     ctx: &egui::Context,
     url: &str,
-    size: u32,
-) -> Promise<Result<TextureHandle>> {
+    options: FetchOptions,
+    preview: Option<TextureSender>,
+) -> ImageFetch {
     let (sender, promise) = Promise::new();
-    let request = ehttp::Request::get(url);
+    let cancel = CancelToken::default();
+    let fetcher = Fetcher::new(img_cache);
+    fetch_from_net(fetcher, ctx, url, options, preview, sender, cancel.clone());
+    ImageFetch { promise, cancel }
+}
+
+/// Download an image, decode it and write it to the disk cache, sending
+/// the texture to `sender` and to anyone waiting on the same fetch
+fn fetch_from_net(
+    fetcher: Fetcher,
+    ctx: &egui::Context,
+    url: &str,
+    options: FetchOptions,
+    preview: Option<TextureSender>,
+    sender: TextureSender,
+    cancel: CancelToken,
+) {
+    let FetchOptions {
+        size,
+        quality,
+        img_type,
+        expected_hash,
+        priority,
+    } = options;
     let ctx = ctx.clone();
     let cloned_url = url.to_owned();
-    let cache_path = cache_path.to_owned();
-    ehttp::fetch(request, move |response| {
+    let key = ImageCache::key(url, size, img_type);
+    let Fetcher {
+        cache_dir,
+        in_flight,
+        persistent,
+        used_bytes,
+        max_bytes,
+        counters,
+        colors,
+        ..
+    } = fetcher.clone();
+    counters.miss();
+    let canceled = cancel.clone();
+    let on_done = move |response: Result<ehttp::Response>| {
+        if let Ok(resp) = &response {
+            counters.fetched(resp.bytes.len());
+        }
+        let response = response
+            .and_then(check_status)
+            .and_then(|resp| match expected_hash {
+                Some(hash) => check_hash(resp, &hash),
+                None => Ok(resp),
+            });
+
+        if let Some(preview) = preview {
+            match &response {
+                Ok(resp) => send_preview(&ctx, &cloned_url, resp, preview),
+                Err(err) => preview.send(Err(Error::Generic(err.to_string()))),
+            }
+        }
+
         let handle = response
-            .map_err(Error::Generic)
-            .and_then(|resp| parse_img_response(resp, size))
+            .and_then(|resp| {
+                let started = Instant::now();
+                let img = parse_img_response(resp, size, quality, img_type);
+                counters.decoded(started.elapsed());
+                img
+            })
             .map(|img| {
+                // the texture and the disk write share the one copy of the pixels
+                let img = Arc::new(img.into_static());
+                remember_color(&colors, &cloned_url, img_type, &img);
                 let texture_handle = ctx.load_texture(&cloned_url, img.clone(), Default::default());
+                if canceled.is_canceled() {
+                    // nobody wants it anymore, so don't spend disk on it
+                    return texture_handle;
+                }
 
-                // write to disk
-                std::thread::spawn(move || ImageCache::write(&cache_path, &cloned_url, img));
+                ImageCache::spawn_write(
+                    cache_dir,
+                    persistent,
+                    used_bytes,
+                    max_bytes,
+                    key.clone(),
+                    img,
+                    CacheFormat::for_type(img_type),
+                );
 
                 texture_handle
             });
 
+        ImageCache::finish_in_flight(&in_flight, &key, &handle);
         sender.send(handle); // send the results back to the UI thread.
         ctx.request_repaint();
+    };
+
+    // a proxied image won't match the original's hash
+    let proxied = fetcher
+        .proxy
+        .as_ref()
+        .filter(|_| expected_hash.is_none())
+        .map(|proxy| proxy.rewrite(url, size));
+    fetcher.download(url, proxied, priority, cancel, on_done);
+}
+
+/// Fetch an image, keeping every frame if it is animated. Static images
+/// resolve to a single frame. Only gifs animate for now; other formats use
+/// their first frame. Downloads go through the same queue, proxy and
+/// retries as [`fetch_img`], and are disk cached as they arrived, since the
+/// decoded frames would take far more space.
+///
+/// While a download runs, [`fetch_img`]s of the same image at the same size
+/// wait for it instead of downloading it again, and get its still frame.
+pub fn fetch_animated_img(
+    img_cache: &ImageCache,
+    ctx: &egui::Context,
+    url: &str,
+    options: FetchOptions,
+) -> AnimatedFetch {
+    let FetchOptions {
+        size,
+        quality,
+        img_type,
+        ..
+    } = options;
+    if url.starts_with("data:") {
+        let started = Instant::now();
+        let anim = parse_data_uri(url)
+            .and_then(|(mime, bytes)| parse_img_bytes(mime, &bytes, size, quality, img_type))
+            .map(|img| upload_frames(ctx, url, img.into_frames()));
+        img_cache.counters_handle().decoded(started.elapsed());
+        return AnimatedFetch {
+            promise: Promise::from_ready(anim),
+            cancel: CancelToken::default(),
+        };
+    }
+
+    let fetcher = Fetcher::new(img_cache);
+    let (sender, promise) = Promise::new();
+    let cancel = CancelToken::default();
+    let source_key = ImageCache::source_key(url);
+    if !ImageCache::is_cached_source(&img_cache.cache_dir.join(&source_key)) {
+        // lead the still's fetch too, unless it's already cached or coming
+        let still_key = ImageCache::key(url, size, img_type);
+        let leads_still = !ImageCache::is_cached(&img_cache.cache_dir.join(&still_key))
+            && img_cache.join_in_flight(&still_key).is_none();
+        let still_key = leads_still.then_some(still_key);
+        fetch_animated_from_net(
+            fetcher,
+            ctx,
+            url,
+            options,
+            still_key,
+            sender,
+            cancel.clone(),
+        );
+        return AnimatedFetch { promise, cancel };
+    }
+
+    fetcher.counters.hit();
+    let ctx = ctx.clone();
+    let url = url.to_owned();
+    let fallback_cancel = cancel.clone();
+    tokio::spawn(async move {
+        let path = fetcher.cache_dir.join(&source_key);
+        let data = fs::read(&path).await;
+        if let Err(err) = ImageCache::touch(&path) {
+            debug!("could not bump image cache mtime for {:?}: {}", path, err);
+        }
+
+        spawn_decode(Box::new(move || {
+            let started = Instant::now();
+            let img = data.map_err(Error::from).and_then(|data| {
+                let (content_type, bytes) = ImageCache::read_source(&data)?;
+                parse_img_bytes(content_type, bytes, size, quality, img_type)
+            });
+            fetcher.counters.decoded(started.elapsed());
+
+            match img {
+                Ok(img) => {
+                    sender.send(Ok(upload_frames(&ctx, &url, img.into_frames())));
+                    ctx.request_repaint();
+                }
+                Err(err) => {
+                    debug!(
+                        "fetching {} again, its cached copy is unreadable: {}",
+                        url, err
+                    );
+                    if let Err(err) = std::fs::remove_file(&path) {
+                        debug!("could not remove {:?} from image cache: {}", path, err);
+                    }
+                    fetch_animated_from_net(
+                        fetcher,
+                        &ctx,
+                        &url,
+                        options,
+                        None,
+                        sender,
+                        fallback_cancel,
+                    );
+                }
+            }
+        }));
     });
 
-    promise
+    AnimatedFetch { promise, cancel }
+}
+
+/// Download an animated image and keep it in the disk cache. If we lead
+/// the fetch of its still under `still_key`, that gets resolved and cached
+/// too.
+fn fetch_animated_from_net(
+    fetcher: Fetcher,
+    ctx: &egui::Context,
+    url: &str,
+    options: FetchOptions,
+    still_key: Option<String>,
+    sender: poll_promise::Sender<Result<AnimatedTexture>>,
+    cancel: CancelToken,
+) {
+    let FetchOptions {
+        size,
+        quality,
+        img_type,
+        priority,
+        ..
+    } = options;
+    let ctx = ctx.clone();
+    let cloned_url = url.to_owned();
+    let Fetcher {
+        cache_dir,
+        in_flight,
+        persistent,
+        used_bytes,
+        max_bytes,
+        counters,
+        colors,
+        ..
+    } = fetcher.clone();
+    counters.miss();
+    let canceled = cancel.clone();
+    let on_done = move |response: Result<ehttp::Response>| {
+        let url = cloned_url;
+        if let Ok(resp) = &response {
+            counters.fetched(resp.bytes.len());
+        }
+
+        let decoded = response.and_then(check_status).and_then(|resp| {
+            let started = Instant::now();
+            let content_type = resp.content_type().unwrap_or_default().to_owned();
+            let img = parse_img_bytes(&content_type, &resp.bytes, size, quality, img_type);
+            counters.decoded(started.elapsed());
+            Ok((img?, content_type, resp.bytes))
+        });
+
+        let res = decoded.map(|(img, content_type, bytes)| {
+            let frames = img.into_frames();
+            let still = representative_frame(&frames);
+            // the still's texture and disk write share its frame's pixels
+            let frames: Vec<_> = frames
+                .into_iter()
+                .map(|(img, delay)| (Arc::new(img), delay))
+                .collect();
+            let still_img = frames[still].0.clone();
+            remember_color(&colors, &url, img_type, &still_img);
+            let anim = upload_frames(&ctx, &url, frames);
+            if !canceled.is_canceled() {
+                ImageCache::spawn_write_source(
+                    cache_dir.clone(),
+                    persistent.clone(),
+                    used_bytes.clone(),
+                    max_bytes,
+                    ImageCache::source_key(&url),
+                    content_type,
+                    bytes,
+                );
+            }
+            (anim, still, still_img)
+        });
+
+        if let Some(still_key) = still_key {
+            let still = match &res {
+                Ok((anim, still, still_img)) => {
+                    if !canceled.is_canceled() {
+                        ImageCache::spawn_write(
+                            cache_dir,
+                            persistent,
+                            used_bytes,
+                            max_bytes,
+                            still_key.clone(),
+                            still_img.clone(),
+                            CacheFormat::for_type(img_type),
+                        );
+                    }
+                    Ok(anim.frames[*still].0.clone())
+                }
+                Err(Error::Canceled) => Err(Error::Canceled),
+                Err(err) => Err(Error::Generic(err.to_string())),
+            };
+            ImageCache::finish_in_flight(&in_flight, &still_key, &still);
+        }
+
+        sender.send(res.map(|(anim, ..)| anim));
+        ctx.request_repaint();
+    };
+
+    let proxied = fetcher
+        .proxy
+        .as_ref()
+        .map(|proxy| proxy.rewrite_animated(url, size));
+    fetcher.download(url, proxied, priority, cancel, on_done);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_off_the_calling_thread() {
+        let (sender, receiver) = mpsc::channel();
+        spawn_decode(Box::new(move || {
+            sender.send(std::thread::current().id()).unwrap();
+        }));
+        let worker = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(worker, std::thread::current().id());
+    }
+
+    #[test]
+    fn identicon_is_deterministic() {
+        let pk = [0x5au8; 32];
+        let a = identicon(&pk, 32, AvatarShape::Circle);
+        let b = identicon(&pk, 32, AvatarShape::Circle);
+        assert_eq!(a.size, [32, 32]);
+        assert_eq!(a.pixels, b.pixels);
+    }
+
+    #[test]
+    fn skips_blank_leading_frames() {
+        let frame = |pixels: Vec<Color32>| {
+            (
+                ColorImage {
+                    size: [2, 2],
+                    pixels,
+                },
+                Duration::from_millis(100),
+            )
+        };
+        let clear = frame(vec![Color32::TRANSPARENT; 4]);
+        let black = frame(vec![Color32::BLACK; 4]);
+        let picture = frame(vec![
+            Color32::RED,
+            Color32::BLUE,
+            Color32::TRANSPARENT,
+            Color32::RED,
+        ]);
+
+        let frames = vec![clear.clone(), black.clone(), picture.clone()];
+        assert_eq!(representative_frame(&frames), 2);
+        assert_eq!(representative_frame(&[clear, black]), 0);
+        assert_eq!(representative_frame(&[picture]), 0);
+    }
+
+    #[test]
+    fn identicon_differs_by_key() {
+        let mut other = [0x5au8; 32];
+        other[0] = 0x10;
+        other[1] = 0x11;
+        assert_ne!(
+            identicon(&[0x5au8; 32], 32, AvatarShape::Circle).pixels,
+            identicon(&other, 32, AvatarShape::Circle).pixels
+        );
+    }
+
+    #[test]
+    fn parses_data_uri() {
+        let (mime, bytes) = parse_data_uri("data:image/png;base64,aGVsbG8=").expect("valid uri");
+        assert_eq!(mime, "image/png");
+        assert_eq!(bytes, b"hello");
+
+        assert!(parse_data_uri("data:image/svg+xml,<svg/>").is_err());
+        assert!(parse_data_uri("data:image/png;base64").is_err());
+    }
+
+    #[test]
+    fn dominant_color_is_the_busiest_bucket() {
+        let solid = ColorImage::new([4, 4], Color32::from_rgb(30, 60, 90));
+        assert_eq!(dominant_color(&solid), Color32::from_rgb(30, 60, 90));
+
+        // mostly blue with a little red is blue, not purple
+        let mut mixed = ColorImage::new([4, 4], Color32::from_rgb(0, 0, 200));
+        mixed.pixels[..5].fill(Color32::from_rgb(220, 0, 0));
+        assert_eq!(dominant_color(&mixed), Color32::from_rgb(0, 0, 200));
+
+        // the corners a circle mask clears don't count
+        let mut round = ColorImage::new([32, 32], Color32::from_rgb(10, 200, 10));
+        round_image(&mut round);
+        assert_eq!(dominant_color(&round), Color32::from_rgb(10, 200, 10));
+
+        let clear = ColorImage::new([4, 4], Color32::TRANSPARENT);
+        assert_eq!(dominant_color(&clear), Color32::TRANSPARENT);
+    }
+
+    #[test]
+    fn round_rect_masks_corners() {
+        let mut img = ColorImage::new([32, 32], Color32::WHITE);
+        round_rect_image(&mut img, 8.0);
+        assert_eq!(img.pixels[0], Color32::TRANSPARENT);
+        assert_eq!(img.pixels[31], Color32::TRANSPARENT);
+        assert_eq!(img.pixels[16 * 32 + 16], Color32::WHITE);
+        // edges between the corners are untouched
+        assert_eq!(img.pixels[16], Color32::WHITE);
+    }
+
+    #[test]
+    fn banners_are_cropped_wide() {
+        let mut square = image::DynamicImage::new_rgba8(900, 900);
+        let banner = process_banner_bitmap(600, ResizeQuality::default(), &mut square);
+        assert_eq!(banner.size, [600, 200]);
+
+        // small banners are cropped but never scaled up
+        let mut small = image::DynamicImage::new_rgba8(300, 50);
+        let banner = process_banner_bitmap(600, ResizeQuality::default(), &mut small);
+        assert_eq!(banner.size, [150, 50]);
+    }
+
+    #[test]
+    fn previews_are_small() {
+        let mut png = vec![];
+        image::DynamicImage::new_rgba8(400, 200)
+            .write_to(
+                &mut std::io::Cursor::new(&mut png),
+                image::ImageOutputFormat::Png,
+            )
+            .unwrap();
+
+        let preview = decode_preview("image/png", &png, PREVIEW_SIZE).unwrap();
+        assert_eq!(preview.size, [64, 32]);
+
+        assert!(!wants_preview("image/png", &png));
+        let big = vec![0; PREVIEW_MIN_BYTES];
+        assert!(wants_preview("image/jpeg", &big));
+        assert!(!wants_preview("image/gif", &big));
+    }
+
+    /// 1x1 webps, lossy and lossless
+    const LOSSY_WEBP: &str =
+        "data:image/webp;base64,UklGRiIAAABXRUJQVlA4IBYAAAAwAQCdASoBAAEADsD+JaQAA3AAAAAA";
+    const LOSSLESS_WEBP: &str =
+        "data:image/webp;base64,UklGRhoAAABXRUJQVlA4TA0AAAAvAAAAEAcQERGIiP4HAA==";
+
+    #[test]
+    fn decodes_webp() {
+        for uri in [LOSSY_WEBP, LOSSLESS_WEBP] {
+            let (mime, bytes) = parse_data_uri(uri).unwrap();
+            assert!(is_webp(&bytes));
+
+            let embed = parse_img_bytes(mime, &bytes, 64, ResizeQuality::Fast, ImageType::Content)
+                .unwrap()
+                .into_static();
+            assert_eq!(embed.size, [1, 1]);
+
+            let pfp = ImageType::Profile(AvatarShape::Square);
+            let avatar = parse_img_bytes(mime, &bytes, 8, ResizeQuality::Fast, pfp)
+                .unwrap()
+                .into_static();
+            assert_eq!(avatar.size, [8, 8]);
+
+            // sniffed when the server doesn't say what it is
+            assert!(parse_img_bytes(
+                "application/octet-stream",
+                &bytes,
+                8,
+                ResizeQuality::Fast,
+                pfp
+            )
+            .is_ok());
+        }
+    }
+
+    #[test]
+    fn long_gifs_stop_at_the_frame_limit() {
+        let mut gif = vec![];
+        {
+            let mut encoder = image::codecs::gif::GifEncoder::new(&mut gif);
+            let frames = (0..MAX_GIF_FRAMES + 10).map(|i| {
+                let pixel = image::Rgba([i as u8, 0, 0, 255]);
+                image::Frame::new(image::RgbaImage::from_pixel(2, 2, pixel))
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+
+        match parse_gif(&gif, 2, ResizeQuality::Fast, ImageType::Content).unwrap() {
+            DecodedImage::Animated(anim) => assert_eq!(anim.frames.len(), MAX_GIF_FRAMES),
+            DecodedImage::Static(_) => panic!("expected an animation"),
+        }
+    }
+
+    #[test]
+    fn svgs_are_cached_rasterized() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" fill="red"/></svg>"#;
+        let img = parse_img_bytes(
+            "image/svg+xml",
+            svg,
+            32,
+            ResizeQuality::Fast,
+            ImageType::Content,
+        )
+        .unwrap()
+        .into_static();
+        assert_eq!(img.size, [32, 32]);
+
+        let dir = std::env::temp_dir().join(format!("notedeck-svg-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = ImageCache::key("https://example.com/a.svg", 32, ImageType::Content);
+        ImageCache::write(&dir, &key, &img, CacheFormat::Png).unwrap();
+
+        // what comes back from disk is a plain bitmap
+        let cached = ImageCache::read(&std::fs::read(dir.join(&key)).unwrap()).unwrap();
+        assert_eq!(cached.size, [32, 32]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn disk_copies_decode_identically() {
+        let pixels: Vec<Color32> = (0..64u8)
+            .map(|i| Color32::from_rgb(i * 4, 255 - i * 4, i.wrapping_mul(37)))
+            .collect();
+        let img = Arc::new(ColorImage {
+            size: [8, 8],
+            pixels,
+        });
+        // the same buffer a texture would be made from
+        let for_texture = img.clone();
+
+        let dir = std::env::temp_dir().join(format!("notedeck-write-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key = ImageCache::key("https://example.com/a.png", 8, ImageType::Content);
+        ImageCache::write(&dir, &key, &img, CacheFormat::Png).unwrap();
+
+        let cached = ImageCache::read(&std::fs::read(dir.join(&key)).unwrap()).unwrap();
+        assert_eq!(cached, *for_texture);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_dims() {
+        assert_eq!(parse_dim("1920x1080"), Some([1920, 1080]));
+        assert_eq!(parse_dim(" 10x20 "), Some([10, 20]));
+        assert_eq!(parse_dim("0x20"), None);
+        assert_eq!(parse_dim("1920"), None);
+
+        assert_eq!(fitted_size([1920, 1080], 800), [800, 450]);
+        assert_eq!(fitted_size([300, 1200], 800), [200, 800]);
+        assert_eq!(fitted_size([300, 200], 800), [300, 200]);
+    }
+
+    #[test]
+    fn cover_uv_crops_overflow() {
+        let uv = cover_uv(2.0, 1.0);
+        assert_eq!(uv, Rect::from_min_max(pos2(0.25, 0.0), pos2(0.75, 1.0)));
+        let uv = cover_uv(1.0, 2.0);
+        assert_eq!(uv, Rect::from_min_max(pos2(0.0, 0.25), pos2(1.0, 0.75)));
+    }
 }
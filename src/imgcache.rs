@@ -1,55 +1,1011 @@
-use crate::Result;
+use crate::image_proxy::ImageProxy;
+use crate::images::{AnimatedFetch, AnimatedTexture, CancelToken, ImageFetch, ImageType, Priority};
+use crate::{Error, Result};
 use egui::TextureHandle;
-use poll_promise::Promise;
+use image::ImageEncoder;
+use poll_promise::{Promise, Sender};
 
-use egui::ColorImage;
+use egui::{Color32, ColorImage};
 
-use std::collections::HashMap;
-use std::fs::File;
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use std::path;
+use tracing::{debug, error};
 
 pub type ImageCacheValue = Promise<Result<TextureHandle>>;
 pub type ImageCacheMap = HashMap<String, ImageCacheValue>;
 
+/// Requests waiting on a fetch that is already in flight, keyed by [`ImageCache::key`]
+type InFlightMap = HashMap<String, Vec<Sender<Result<TextureHandle>>>>;
+pub(crate) type InFlight = Arc<Mutex<InFlightMap>>;
+
+pub(crate) type FetchQueueHandle = Arc<Mutex<FetchQueue>>;
+
+pub(crate) type DominantColors = Arc<Mutex<ColorMap>>;
+
+/// The most profile picture colors we remember. A color is 4 bytes and a
+/// url, so this is well under a megabyte.
+const MAX_DOMINANT_COLORS: usize = 4096;
+
+/// What color each profile picture mostly is, by url, see
+/// [`crate::images::dominant_color`]. Once it's full, the colors that
+/// were added first are forgotten first.
+#[derive(Debug, Default)]
+pub(crate) struct ColorMap {
+    colors: HashMap<String, Color32>,
+    /// The urls in `colors`, oldest first
+    order: VecDeque<String>,
+}
+
+impl ColorMap {
+    pub(crate) fn insert(&mut self, url: &str, color: Color32) {
+        if let Some(existing) = self.colors.get_mut(url) {
+            *existing = color;
+            return;
+        }
+
+        if self.order.len() >= MAX_DOMINANT_COLORS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.colors.remove(&oldest);
+            }
+        }
+        self.colors.insert(url.to_owned(), color);
+        self.order.push_back(url.to_owned());
+    }
+
+    fn get(&self, url: &str) -> Option<Color32> {
+        self.colors.get(url).copied()
+    }
+
+    fn clear(&mut self) {
+        self.colors.clear();
+        self.order.clear();
+    }
+}
+
+/// Network fetches waiting for one of a few download slots, so a screen
+/// full of avatars doesn't hold up the image someone is looking at. Only
+/// downloads wait here; the disk cache is read straight away.
+pub(crate) struct FetchQueue {
+    max_in_flight: usize,
+    running: usize,
+    waiting: Vec<QueuedFetch>,
+    /// How many fetches have been queued, so ones with the same priority
+    /// start in the order they were asked for
+    queued: u64,
+}
+
+struct QueuedFetch {
+    priority: Priority,
+    order: u64,
+    start: Box<dyn FnOnce() + Send>,
+}
+
+impl FetchQueue {
+    fn new(max_in_flight: usize) -> Self {
+        FetchQueue {
+            max_in_flight,
+            running: 0,
+            waiting: vec![],
+            queued: 0,
+        }
+    }
+
+    /// Run `start` now if there's a free slot, otherwise once it's the most
+    /// important fetch waiting. Whatever `start` begins must call
+    /// [`FetchQueue::finish`] when it's done, to free the slot.
+    pub(crate) fn submit(
+        queue: &FetchQueueHandle,
+        priority: Priority,
+        start: Box<dyn FnOnce() + Send>,
+    ) {
+        let mut locked = queue.lock().unwrap();
+        if locked.running < locked.max_in_flight {
+            locked.running += 1;
+            drop(locked);
+            start();
+            return;
+        }
+
+        let order = locked.queued;
+        locked.queued += 1;
+        locked.waiting.push(QueuedFetch {
+            priority,
+            order,
+            start,
+        });
+    }
+
+    /// Free a slot, starting the next fetch waiting for one
+    pub(crate) fn finish(queue: &FetchQueueHandle) {
+        let next = {
+            let mut locked = queue.lock().unwrap();
+            locked.running = locked.running.saturating_sub(1);
+            let next = locked.next();
+            if next.is_some() {
+                locked.running += 1;
+            }
+            next
+        };
+
+        if let Some(next) = next {
+            (next.start)();
+        }
+    }
+
+    /// The highest priority fetch that's been waiting longest, if there's
+    /// a slot for it
+    fn next(&mut self) -> Option<QueuedFetch> {
+        if self.running >= self.max_in_flight {
+            return None;
+        }
+        let (index, _) = self
+            .waiting
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, fetch)| (fetch.priority, Reverse(fetch.order)))?;
+        Some(self.waiting.swap_remove(index))
+    }
+}
+
+/// What the image cache has done this session, for a debug overlay. Take
+/// two and subtract them to see what happened in between, eg. a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImageStats {
+    /// Images decoded, from downloads, the disk cache or `data:` uris
+    pub decodes: u64,
+    /// How long decoding took, added up across every thread
+    pub decode_time: Duration,
+    /// Bytes of images downloaded
+    pub bytes_fetched: u64,
+    /// Fetches served by the disk cache or by joining one already running
+    pub hits: u64,
+    /// Fetches that had to download the image
+    pub misses: u64,
+}
+
+impl ImageStats {
+    /// The share of fetches that didn't need a download, from 0 to 1
+    pub fn hit_rate(&self) -> f32 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            fetches => self.hits as f32 / fetches as f32,
+        }
+    }
+}
+
+/// The counters behind [`ImageStats`], shared with the threads fetching
+/// and decoding images
+#[derive(Debug, Default)]
+pub(crate) struct ImageCounters {
+    decodes: AtomicU64,
+    decode_nanos: AtomicU64,
+    bytes_fetched: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ImageCounters {
+    pub(crate) fn decoded(&self, took: Duration) {
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        self.decode_nanos
+            .fetch_add(took.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn fetched(&self, bytes: usize) {
+        self.bytes_fetched
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> ImageStats {
+        ImageStats {
+            decodes: self.decodes.load(Ordering::Relaxed),
+            decode_time: Duration::from_nanos(self.decode_nanos.load(Ordering::Relaxed)),
+            bytes_fetched: self.bytes_fetched.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
 pub struct ImageCache {
     pub cache_dir: path::PathBuf,
+    max_bytes: u64,
+    used_bytes: Arc<AtomicU64>,
+    /// Cleared when writing to the disk cache fails, after which images
+    /// are only kept in memory
+    persistent: Arc<AtomicBool>,
+    in_flight: InFlight,
+    fetch_queue: FetchQueueHandle,
+    fetch_retries: u32,
+    max_download_bytes: u64,
     url_imgs: ImageCacheMap,
+    /// The frame each texture was last drawn in, for evicting the least
+    /// recently drawn ones from memory
+    last_drawn: HashMap<String, u64>,
+    frame: u64,
+    max_textures: usize,
+    /// How to stop the fetches we started and are still waiting on
+    cancels: HashMap<String, CancelToken>,
+    proxy: Option<ImageProxy>,
+    /// Every frame of the animated images being drawn. These hold a texture
+    /// per frame, so they're only kept while they're on screen.
+    animated: HashMap<String, AnimatedFetch>,
+    counters: Arc<ImageCounters>,
+    colors: DominantColors,
 }
 
+/// What every image stored as raw pixels in the disk cache starts with.
+/// The last byte is the format's version, so files from older versions
+/// don't match.
+const CACHE_MAGIC: [u8; 4] = *b"NDI\x01";
+
+/// What images stored as pngs in the disk cache start with
+const PNG_CACHE_MAGIC: [u8; 4] = *b"NDP\x01";
+
+/// The magic, then the width and height as little endian u32s
+const CACHE_HEADER_LEN: usize = 12;
+
+/// What downloads kept as they arrived, eg. animated gifs, start with. The
+/// content type's length as a little endian u32 and the content type come
+/// next, then the downloaded bytes.
+const SOURCE_CACHE_MAGIC: [u8; 4] = *b"NDS\x01";
+
+/// How an image's pixels are kept in the disk cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheFormat {
+    /// The pixels as they are, which load without running a decoder.
+    /// Avatars are small enough for this to cost little space.
+    Raw,
+    /// The pixels compressed losslessly as a png. Embeds and banners can
+    /// take several megabytes raw, which would crowd everything else out of
+    /// the cache.
+    Png,
+}
+
+impl CacheFormat {
+    pub fn for_type(img_type: ImageType) -> Self {
+        match img_type {
+            ImageType::Profile(_) => CacheFormat::Raw,
+            ImageType::Content | ImageType::Banner => CacheFormat::Png,
+        }
+    }
+
+    fn magic(self) -> [u8; 4] {
+        match self {
+            CacheFormat::Raw => CACHE_MAGIC,
+            CacheFormat::Png => PNG_CACHE_MAGIC,
+        }
+    }
+
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        [CacheFormat::Raw, CacheFormat::Png]
+            .into_iter()
+            .find(|format| format.magic() == magic)
+    }
+}
+
+/// How a write changed the size of the disk cache
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheWrite {
+    /// The size of the file that was written
+    pub written: u64,
+    /// The size of the file it replaced, if its key was already cached
+    pub replaced: u64,
+}
+
+/// A fetch that hasn't been drawn for this many frames has most likely
+/// scrolled away, so we stop downloading it
+const STALE_FETCH_FRAMES: u64 = 30;
+
+/// Animated images that haven't been drawn for this many frames are dropped
+const STALE_ANIMATED_FRAMES: u64 = 120;
+
 impl ImageCache {
-    pub fn new(cache_dir: path::PathBuf) -> Self {
-        Self {
+    pub fn new(cache_dir: path::PathBuf, max_bytes: u64) -> Self {
+        let cache = Self {
             cache_dir,
+            max_bytes,
+            used_bytes: Arc::new(AtomicU64::new(0)),
+            persistent: Arc::new(AtomicBool::new(true)),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            fetch_queue: Arc::new(Mutex::new(FetchQueue::new(Self::default_max_in_flight()))),
+            fetch_retries: Self::default_fetch_retries(),
+            max_download_bytes: Self::default_max_download_bytes(),
             url_imgs: HashMap::new(),
-        }
+            last_drawn: HashMap::new(),
+            frame: 0,
+            max_textures: Self::default_max_textures(),
+            cancels: HashMap::new(),
+            proxy: None,
+            animated: HashMap::new(),
+            counters: Arc::new(ImageCounters::default()),
+            colors: Arc::new(Mutex::new(ColorMap::default())),
+        };
+
+        // figure out how much is already on disk, evicting if we're over
+        cache.prune();
+
+        cache
+    }
+
+    /// A cache of its own for `namespace`, eg. an account, in a directory
+    /// under `base_dir` that's created if it's missing. Fails if the
+    /// namespace is empty.
+    pub fn namespaced(
+        base_dir: impl AsRef<path::Path>,
+        namespace: &str,
+        max_bytes: u64,
+    ) -> Result<Self> {
+        let cache_dir = base_dir.as_ref().join(namespace_dir(namespace)?);
+        fs::create_dir_all(&cache_dir)?;
+        Ok(Self::new(cache_dir, max_bytes))
+    }
+
+    /// Where the platform keeps caches, eg. `$XDG_CACHE_HOME/notedeck/img`
+    /// on Linux, if it has such a place
+    pub fn default_dir() -> Option<path::PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("notedeck").join("img"))
     }
 
     pub fn rel_datadir() -> &'static str {
         "cache/img"
     }
 
-    pub fn write(cache_dir: &path::Path, url: &str, data: ColorImage) -> Result<()> {
-        let file_path = cache_dir.join(Self::key(url));
-        let file = File::options()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(file_path)?;
-        let encoder = image::codecs::webp::WebPEncoder::new_lossless(file);
+    /// 256 MiB of cached images on disk
+    pub fn default_max_bytes() -> u64 {
+        256 * 1024 * 1024
+    }
+
+    pub fn default_fetch_retries() -> u32 {
+        3
+    }
+
+    /// How many times a failed network fetch is retried before giving up
+    pub fn fetch_retries(&self) -> u32 {
+        self.fetch_retries
+    }
+
+    /// Set to 0 to disable retries, eg. in previews and tests
+    pub fn set_fetch_retries(&mut self, retries: u32) {
+        self.fetch_retries = retries;
+    }
+
+    pub fn default_max_in_flight() -> usize {
+        6
+    }
+
+    /// How many images are downloaded at once. More wait their turn, most
+    /// important first; see [`Priority`].
+    pub fn max_in_flight(&self) -> usize {
+        self.fetch_queue.lock().unwrap().max_in_flight
+    }
+
+    /// Lowering this doesn't stop downloads that already started
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize) {
+        self.fetch_queue.lock().unwrap().max_in_flight = max_in_flight.max(1);
+    }
+
+    /// 20 MB, far more than any reasonable avatar
+    pub fn default_max_download_bytes() -> u64 {
+        20 * 1024 * 1024
+    }
+
+    /// Downloads larger than this are aborted and fail with
+    /// [`Error::TooLarge`]
+    pub fn max_download_bytes(&self) -> u64 {
+        self.max_download_bytes
+    }
+
+    pub fn set_max_download_bytes(&mut self, max_bytes: u64) {
+        self.max_download_bytes = max_bytes;
+    }
+
+    /// Enough for a few screens of avatars and embeds
+    pub fn default_max_textures() -> usize {
+        512
+    }
+
+    /// How many loaded textures are kept in memory, and on the GPU, before
+    /// the least recently drawn are dropped. They're reloaded from the disk
+    /// cache if they're needed again.
+    pub fn max_textures(&self) -> usize {
+        self.max_textures
+    }
+
+    pub fn set_max_textures(&mut self, max_textures: usize) {
+        self.max_textures = max_textures;
+    }
+
+    /// Where images are fetched through, if anywhere. Cache keys always use
+    /// the original url.
+    pub fn proxy(&self) -> Option<&ImageProxy> {
+        self.proxy.as_ref()
+    }
+
+    /// Fetch images through a resizing proxy, falling back to the original
+    /// url when the proxy fails
+    pub fn set_proxy(&mut self, proxy: Option<ImageProxy>) {
+        self.proxy = proxy;
+    }
+
+    /// Whether downloaded images are still being written to the disk
+    /// cache. This turns off for the rest of the session the first time a
+    /// write fails, eg. because the disk is full or read only, and images
+    /// are then only cached in memory.
+    pub fn is_persistent(&self) -> bool {
+        self.persistent.load(Ordering::Relaxed)
+    }
+
+    /// Write a downloaded image to the disk cache on a background thread,
+    /// unless the disk cache has stopped working
+    pub(crate) fn spawn_write(
+        cache_dir: path::PathBuf,
+        persistent: Arc<AtomicBool>,
+        used_bytes: Arc<AtomicU64>,
+        max_bytes: u64,
+        key: String,
+        data: Arc<ColorImage>,
+        format: CacheFormat,
+    ) {
+        Self::spawn_cache_write(cache_dir, persistent, used_bytes, max_bytes, move |dir| {
+            Self::write(dir, &key, &data, format)
+        });
+    }
+
+    /// Like [`Self::spawn_write`], for a download that's cached as it
+    /// arrived, see [`Self::write_source`]
+    pub(crate) fn spawn_write_source(
+        cache_dir: path::PathBuf,
+        persistent: Arc<AtomicBool>,
+        used_bytes: Arc<AtomicU64>,
+        max_bytes: u64,
+        key: String,
+        content_type: String,
+        bytes: Vec<u8>,
+    ) {
+        Self::spawn_cache_write(cache_dir, persistent, used_bytes, max_bytes, move |dir| {
+            Self::write_source(dir, &key, &content_type, &bytes)
+        });
+    }
+
+    fn spawn_cache_write(
+        cache_dir: path::PathBuf,
+        persistent: Arc<AtomicBool>,
+        used_bytes: Arc<AtomicU64>,
+        max_bytes: u64,
+        write: impl FnOnce(&path::Path) -> Result<CacheWrite> + Send + 'static,
+    ) {
+        if !persistent.load(Ordering::Relaxed) {
+            return;
+        }
+
+        std::thread::spawn(move || match write(&cache_dir) {
+            Ok(written) => Self::on_write(&cache_dir, &used_bytes, max_bytes, written),
+            Err(err) => {
+                if Self::write_failed(&persistent) {
+                    error!(
+                        "can't write to the image cache in {:?}, only caching images in memory: {}",
+                        cache_dir, err
+                    );
+                }
+            }
+        });
+    }
+
+    /// Stop writing to the disk cache. Returns whether this is the first
+    /// failure, which is the only one worth logging.
+    fn write_failed(persistent: &AtomicBool) -> bool {
+        persistent.swap(false, Ordering::Relaxed)
+    }
+
+    pub(crate) fn persistent_handle(&self) -> Arc<AtomicBool> {
+        self.persistent.clone()
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// The last known size of the on-disk cache
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Evict least-recently-accessed files until the on-disk cache fits
+    /// within `max_bytes`. This runs on a background thread.
+    pub fn prune(&self) {
+        Self::spawn_evict(
+            self.cache_dir.clone(),
+            self.max_bytes,
+            self.used_bytes.clone(),
+        );
+    }
+
+    /// Account for a newly written file, less the one it replaced, evicting
+    /// old entries on a background thread if this write put us over budget.
+    pub fn on_write(
+        cache_dir: &path::Path,
+        used_bytes: &Arc<AtomicU64>,
+        max_bytes: u64,
+        write: CacheWrite,
+    ) {
+        let apply = |used: u64| used.saturating_sub(write.replaced) + write.written;
+        let used = used_bytes
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(apply(used))
+            })
+            .map_or_else(apply, apply);
+        if used > max_bytes {
+            Self::spawn_evict(cache_dir.to_owned(), max_bytes, used_bytes.clone());
+        }
+    }
+
+    fn spawn_evict(cache_dir: path::PathBuf, max_bytes: u64, used_bytes: Arc<AtomicU64>) {
+        std::thread::spawn(move || match Self::evict(&cache_dir, max_bytes) {
+            Ok(used) => used_bytes.store(used, Ordering::Relaxed),
+            Err(err) => error!("error pruning image cache: {}", err),
+        });
+    }
+
+    /// Remove the oldest files by mtime until the directory is under
+    /// `max_bytes`. Returns the number of bytes remaining.
+    fn evict(cache_dir: &path::Path, max_bytes: u64) -> Result<u64> {
+        let mut entries: Vec<(SystemTime, u64, path::PathBuf)> = vec![];
+        let mut total: u64 = 0;
+
+        for entry in fs::read_dir(cache_dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if !meta.is_file() {
+                continue;
+            }
+
+            let mtime = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            total += meta.len();
+            entries.push((mtime, meta.len(), entry.path()));
+        }
+
+        if total <= max_bytes {
+            return Ok(total);
+        }
+
+        // oldest first
+        entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        for (_, len, path) in entries {
+            if total <= max_bytes {
+                break;
+            }
+
+            if let Err(err) = fs::remove_file(&path) {
+                error!("could not evict {:?} from image cache: {}", path, err);
+                continue;
+            }
+
+            debug!("evicted {:?} from image cache", path);
+            total -= len;
+        }
+
+        Ok(total)
+    }
+
+    /// The total size of the files currently in the disk cache
+    pub fn size_on_disk(&self) -> Result<u64> {
+        let mut total = 0;
+        for entry in fs::read_dir(&self.cache_dir)? {
+            let meta = match entry.and_then(|entry| entry.metadata()) {
+                Ok(meta) => meta,
+                // removed by an eviction while we were looking
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if meta.is_file() {
+                total += meta.len();
+            }
+        }
+
+        self.used_bytes.store(total, Ordering::Relaxed);
+        Ok(total)
+    }
+
+    /// Remove every cached image, both in memory and on disk. The cache
+    /// directory itself is left in place so running fetches can still write
+    /// to it when they finish.
+    pub fn clear(&mut self) -> Result<()> {
+        self.url_imgs.clear();
+        self.last_drawn.clear();
+        self.colors.lock().unwrap().clear();
+        for (_, cancel) in self.cancels.drain() {
+            cancel.cancel();
+        }
+        for (_, fetch) in self.animated.drain() {
+            fetch.cancel.cancel();
+        }
 
-        encoder.encode(
-            data.as_raw(),
-            data.size[0] as u32,
-            data.size[1] as u32,
-            image::ColorType::Rgba8,
-        )?;
+        let entries = match fs::read_dir(&self.cache_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
 
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        self.used_bytes.store(0, Ordering::Relaxed);
         Ok(())
     }
 
-    pub fn key(url: &str) -> String {
-        base32::encode(base32::Alphabet::Crockford, url.as_bytes())
+    /// Bump the mtime of a cached file so it counts as recently accessed
+    pub fn touch(path: &path::Path) -> Result<()> {
+        File::options()
+            .append(true)
+            .open(path)?
+            .set_modified(SystemTime::now())?;
+        Ok(())
+    }
+
+    /// Write an image to the disk cache under `key`, returning how much it
+    /// wrote and replaced. Images are stored in `format` after a small header.
+    /// They're written to a temporary file that's then renamed into place,
+    /// so a crash or a full disk never leaves half an image under `key`.
+    pub fn write(
+        cache_dir: &path::Path,
+        key: &str,
+        data: &ColorImage,
+        format: CacheFormat,
+    ) -> Result<CacheWrite> {
+        Self::write_atomically(cache_dir, key, |path| Self::write_file(path, data, format))
+    }
+
+    /// Write a download to the disk cache under `key` as it arrived, along
+    /// with its content type, so it can be decoded again later. Animated
+    /// images are kept like this since their frames take far more space
+    /// decoded than the file they came from.
+    pub fn write_source(
+        cache_dir: &path::Path,
+        key: &str,
+        content_type: &str,
+        bytes: &[u8],
+    ) -> Result<CacheWrite> {
+        Self::write_atomically(cache_dir, key, |path| {
+            let mut writer = io::BufWriter::new(File::create(path)?);
+            writer.write_all(&SOURCE_CACHE_MAGIC)?;
+            writer.write_all(&(content_type.len() as u32).to_le_bytes())?;
+            writer.write_all(content_type.as_bytes())?;
+            writer.write_all(bytes)?;
+            writer.flush()?;
+            Ok(())
+        })
+    }
+
+    /// Write to a temporary file with `write`, then rename it to `key`,
+    /// replacing whatever was cached under it
+    fn write_atomically(
+        cache_dir: &path::Path,
+        key: &str,
+        write: impl FnOnce(&path::Path) -> Result<()>,
+    ) -> Result<CacheWrite> {
+        let file_path = cache_dir.join(key);
+        let tmp_path = cache_dir.join(format!("{}.tmp", key));
+        if let Err(err) = write(&tmp_path) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(err);
+        }
+
+        // eg. an image cached in an older format, or one that two fetches
+        // wrote at once
+        let replaced = fs::metadata(&file_path).map_or(0, |meta| meta.len());
+        match fs::rename(&tmp_path, &file_path) {
+            Ok(()) => Ok(CacheWrite {
+                written: fs::metadata(file_path)?.len(),
+                replaced,
+            }),
+            // the cache was cleared while we were writing
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(CacheWrite::default()),
+            Err(err) => {
+                let _ = fs::remove_file(&tmp_path);
+                Err(err.into())
+            }
+        }
+    }
+
+    fn write_file(path: &path::Path, data: &ColorImage, format: CacheFormat) -> Result<()> {
+        let file = File::create(path)?;
+
+        let mut header = Vec::with_capacity(CACHE_HEADER_LEN);
+        header.extend_from_slice(&format.magic());
+        header.extend_from_slice(&(data.size[0] as u32).to_le_bytes());
+        header.extend_from_slice(&(data.size[1] as u32).to_le_bytes());
+
+        let mut writer = io::BufWriter::new(file);
+        writer.write_all(&header)?;
+        match format {
+            CacheFormat::Raw => writer.write_all(data.as_raw())?,
+            // the premultiplied pixels as they are, since we're the only
+            // ones reading them back
+            CacheFormat::Png => image::codecs::png::PngEncoder::new(&mut writer).write_image(
+                data.as_raw(),
+                data.size[0] as u32,
+                data.size[1] as u32,
+                image::ColorType::Rgba8,
+            )?,
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Whether `path` is an image we wrote in a current format. Anything
+    /// else, eg. from an older version, is fetched again and overwritten.
+    pub fn is_cached(path: &path::Path) -> bool {
+        let mut magic = [0; CACHE_MAGIC.len()];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok()
+            && CacheFormat::from_magic(&magic).is_some()
+    }
+
+    /// Whether `path` is a download written by [`ImageCache::write_source`]
+    pub fn is_cached_source(path: &path::Path) -> bool {
+        let mut magic = [0; SOURCE_CACHE_MAGIC.len()];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok()
+            && magic == SOURCE_CACHE_MAGIC
+    }
+
+    /// Read back the content type and bytes of a download written by
+    /// [`ImageCache::write_source`]
+    pub fn read_source(bytes: &[u8]) -> Result<(&str, &[u8])> {
+        let not_ours = || Error::Generic("not a download from our disk cache".to_string());
+        let rest = bytes
+            .strip_prefix(&SOURCE_CACHE_MAGIC[..])
+            .ok_or_else(not_ours)?;
+        let (len, rest) = rest.split_first_chunk::<4>().ok_or_else(not_ours)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            return Err(not_ours());
+        }
+        let (content_type, body) = rest.split_at(len);
+        let content_type = std::str::from_utf8(content_type).map_err(|_| not_ours())?;
+        Ok((content_type, body))
+    }
+
+    /// Read back an image written by [`ImageCache::write`]
+    pub fn read(bytes: &[u8]) -> Result<ColorImage> {
+        let (header, format) = bytes
+            .get(..CACHE_HEADER_LEN)
+            .and_then(|header| Some((header, CacheFormat::from_magic(&header[..4])?)))
+            .ok_or_else(|| Error::Generic("not an image from our disk cache".to_string()))?;
+        let dimension = |at: usize| {
+            let bytes: [u8; 4] = header[at..at + 4].try_into().unwrap();
+            u32::from_le_bytes(bytes) as usize
+        };
+        let size = [dimension(4), dimension(8)];
+
+        let body = &bytes[CACHE_HEADER_LEN..];
+        let decoded;
+        let pixels = match format {
+            CacheFormat::Raw => body,
+            CacheFormat::Png => {
+                decoded = image::load_from_memory_with_format(body, image::ImageFormat::Png)?
+                    .into_rgba8()
+                    .into_raw();
+                &decoded[..]
+            }
+        };
+        let expected = size[0].checked_mul(size[1]).and_then(|n| n.checked_mul(4));
+        if expected != Some(pixels.len()) {
+            return Err(Error::Generic(format!(
+                "cached {}x{} image has {} bytes of pixels",
+                size[0],
+                size[1],
+                pixels.len()
+            )));
+        }
+
+        let pixels = pixels
+            .chunks_exact(4)
+            .map(|p| Color32::from_rgba_premultiplied(p[0], p[1], p[2], p[3]))
+            .collect();
+        Ok(ColorImage { size, pixels })
+    }
+
+    /// The on-disk key for an image. The requested size and image type are
+    /// part of the key so that the same url rendered at different sizes, in
+    /// different shapes or as a note embed is cached separately.
+    pub fn key(url: &str, size: u32, img_type: ImageType) -> String {
+        let tag = img_type.cache_tag();
+        let encoded = base32::encode(base32::Alphabet::Crockford, url.as_bytes());
+        if tag.is_empty() {
+            format!("{}_{}", size, encoded)
+        } else {
+            format!("{}_{}_{}", size, tag, encoded)
+        }
+    }
+
+    /// The on-disk key for a download kept as it arrived. Unlike
+    /// [`Self::key`] this doesn't depend on the size or shape, since it's
+    /// decoded again for each of them.
+    pub fn source_key(url: &str) -> String {
+        let encoded = base32::encode(base32::Alphabet::Crockford, url.as_bytes());
+        format!("src_{}", encoded)
+    }
+
+    /// Whether any image we've asked for hasn't finished loading
+    pub fn has_pending(&self) -> bool {
+        self.url_imgs
+            .values()
+            .any(|promise| promise.ready().is_none())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.url_imgs.contains_key(key)
+    }
+
+    /// Insert an image that's already loaded, or can't be canceled
+    pub fn insert(&mut self, key: String, promise: ImageCacheValue) {
+        self.last_drawn.insert(key.clone(), self.frame);
+        self.url_imgs.insert(key, promise);
+    }
+
+    /// Insert a fetch that is stopped if it isn't drawn for a while before
+    /// it finishes
+    pub fn insert_fetch(&mut self, key: String, fetch: ImageFetch) {
+        self.cancels.insert(key.clone(), fetch.cancel);
+        self.insert(key, fetch.promise);
+    }
+
+    /// Forget an image, stopping its fetch if it's still going
+    pub fn remove(&mut self, key: &str) {
+        self.last_drawn.remove(key);
+        self.url_imgs.remove(key);
+        if let Some(cancel) = self.cancels.remove(key) {
+            cancel.cancel();
+        }
+    }
+
+    /// The image for `key`, if it has finished loading. This counts as
+    /// drawing it, so ask for textures through here rather than holding on
+    /// to their handles.
+    pub fn ready(&mut self, key: &str) -> Option<&Result<TextureHandle>> {
+        let promise = self.url_imgs.get(key)?;
+        if matches!(promise.ready(), Some(Err(Error::Canceled))) {
+            // canceled, maybe by whoever we were sharing the fetch with, so
+            // forget it and let it be fetched again
+            self.remove(key);
+            return None;
+        }
+
+        let promise = &self.url_imgs[key];
+        if let Some(frame) = self.last_drawn.get_mut(key) {
+            *frame = self.frame;
+        } else {
+            self.last_drawn.insert(key.to_owned(), self.frame);
+        }
+        promise.ready()
+    }
+
+    pub fn contains_animated(&self, key: &str) -> bool {
+        self.animated.contains_key(key)
+    }
+
+    /// Insert an animated fetch that, like [`Self::insert_fetch`], is
+    /// stopped if it isn't drawn for a while before it finishes
+    pub fn insert_animated(&mut self, key: String, fetch: AnimatedFetch) {
+        self.last_drawn.insert(key.clone(), self.frame);
+        self.animated.insert(key, fetch);
+    }
+
+    /// The frames of an animated image, if it has finished loading. Like
+    /// [`Self::ready`], this counts as drawing it.
+    pub fn ready_animated(&mut self, key: &str) -> Option<&Result<AnimatedTexture>> {
+        let fetch = self.animated.get(key)?;
+        self.last_drawn.insert(key.to_owned(), self.frame);
+        fetch.promise.ready()
+    }
+
+    /// Drop the least recently drawn textures past [`Self::max_textures`],
+    /// so egui can free them. Anything drawn this frame is kept. Fetches
+    /// that haven't been drawn in a while are canceled. Call this once at
+    /// the end of every frame.
+    pub fn evict_textures(&mut self) {
+        let pending: Vec<(u64, &str)> = self
+            .cancels
+            .keys()
+            .filter(|key| {
+                self.url_imgs
+                    .get(*key)
+                    .is_some_and(|promise| promise.ready().is_none())
+            })
+            .map(|key| {
+                let drawn = self.last_drawn.get(key).copied().unwrap_or(0);
+                (drawn, key.as_str())
+            })
+            .collect();
+        let stale: Vec<String> = stale_fetches(pending, self.frame)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        if !stale.is_empty() {
+            debug!("canceling {} image fetches that scrolled away", stale.len());
+        }
+        for key in stale {
+            self.remove(&key);
+        }
+
+        // finished fetches have nothing left to cancel
+        let url_imgs = &self.url_imgs;
+        self.cancels.retain(|key, _| {
+            url_imgs
+                .get(key)
+                .is_some_and(|promise| promise.ready().is_none())
+        });
+
+        let loaded: Vec<(u64, &str)> = self
+            .url_imgs
+            .iter()
+            .filter(|(_, promise)| matches!(promise.ready(), Some(Ok(_))))
+            .map(|(key, _)| {
+                let drawn = self.last_drawn.get(key).copied().unwrap_or(0);
+                (drawn, key.as_str())
+            })
+            .collect();
+
+        let victims: Vec<String> = lru_victims(loaded, self.max_textures, self.frame)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        if !victims.is_empty() {
+            debug!("evicting {} textures from memory", victims.len());
+        }
+        for key in victims {
+            self.remove(&key);
+        }
+
+        let (frame, last_drawn) = (self.frame, &mut self.last_drawn);
+        self.animated.retain(|key, fetch| {
+            let drawn = last_drawn.get(key).copied().unwrap_or(0);
+            let pending = fetch.promise.ready().is_none();
+            let stale_after = if pending {
+                STALE_FETCH_FRAMES
+            } else {
+                STALE_ANIMATED_FRAMES
+            };
+            let keep = frame.saturating_sub(drawn) <= stale_after;
+            if !keep {
+                if pending {
+                    fetch.cancel.cancel();
+                }
+                last_drawn.remove(key);
+            }
+            keep
+        });
+
+        self.frame += 1;
     }
 
     pub fn map(&self) -> &ImageCacheMap {
@@ -59,4 +1015,321 @@ impl ImageCache {
     pub fn map_mut(&mut self) -> &mut ImageCacheMap {
         &mut self.url_imgs
     }
+
+    pub(crate) fn used_bytes_handle(&self) -> Arc<AtomicU64> {
+        self.used_bytes.clone()
+    }
+
+    pub(crate) fn in_flight_handle(&self) -> InFlight {
+        self.in_flight.clone()
+    }
+
+    pub(crate) fn fetch_queue_handle(&self) -> FetchQueueHandle {
+        self.fetch_queue.clone()
+    }
+
+    pub fn stats(&self) -> ImageStats {
+        self.counters.stats()
+    }
+
+    pub(crate) fn counters_handle(&self) -> Arc<ImageCounters> {
+        self.counters.clone()
+    }
+
+    pub(crate) fn colors_handle(&self) -> DominantColors {
+        self.colors.clone()
+    }
+
+    /// What color the profile picture at `url` mostly is, once any copy of
+    /// it has been decoded this session. Colors outlive the textures they
+    /// came from, so there's something to show while an evicted picture is
+    /// read back in, or while it loads at another size. Only the most
+    /// recent few thousand are kept, and [`Self::clear`] forgets them.
+    pub fn dominant_color(&self, url: &str) -> Option<Color32> {
+        self.colors.lock().unwrap().get(url)
+    }
+
+    /// If a fetch for this key is already running, return a promise that
+    /// resolves along with it. Otherwise mark a new fetch as in flight and
+    /// return None, in which case the caller is responsible for the fetch
+    /// and must call [`ImageCache::finish_in_flight`] when it completes.
+    pub(crate) fn join_in_flight(&self, key: &str) -> Option<Promise<Result<TextureHandle>>> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        if let Some(waiters) = in_flight.get_mut(key) {
+            let (sender, promise) = Promise::new();
+            waiters.push(sender);
+            Some(promise)
+        } else {
+            in_flight.insert(key.to_owned(), vec![]);
+            None
+        }
+    }
+
+    /// Resolve everyone waiting on an in-flight fetch and clear its entry
+    pub(crate) fn finish_in_flight(in_flight: &InFlight, key: &str, res: &Result<TextureHandle>) {
+        let waiters = in_flight.lock().unwrap().remove(key).unwrap_or_default();
+
+        for waiter in waiters {
+            let shared = match res {
+                Ok(texture) => Ok(texture.clone()),
+                Err(Error::Canceled) => Err(Error::Canceled),
+                Err(err) => Err(Error::Generic(err.to_string())),
+            };
+            waiter.send(shared);
+        }
+    }
+}
+
+/// The directory name for a cache namespace. Every byte that isn't a
+/// lowercase letter, a digit, `-` or `_` is percent-encoded, so a namespace
+/// can't reach outside the base directory, and different namespaces never
+/// share a directory, even on filesystems that ignore case.
+fn namespace_dir(namespace: &str) -> Result<String> {
+    if namespace.is_empty() {
+        return Err(Error::Generic("image cache namespace is empty".to_owned()));
+    }
+
+    let mut dir = String::with_capacity(namespace.len());
+    for byte in namespace.bytes() {
+        match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => dir.push(byte as char),
+            _ => dir.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    Ok(dir)
+}
+
+/// Which of `loaded`, each paired with the frame it was last drawn in, to
+/// evict to get down to `max`, oldest first. Nothing drawn in `frame` is
+/// evicted, even if that leaves us over.
+fn lru_victims(mut loaded: Vec<(u64, &str)>, max: usize, frame: u64) -> Vec<&str> {
+    if loaded.len() <= max {
+        return vec![];
+    }
+
+    let excess = loaded.len() - max;
+    loaded.sort_unstable();
+    loaded
+        .into_iter()
+        .take_while(|(drawn, _)| *drawn < frame)
+        .take(excess)
+        .map(|(_, key)| key)
+        .collect()
+}
+
+/// Which of the `pending` fetches, each paired with the frame it was last
+/// drawn in, have gone undrawn long enough to cancel
+fn stale_fetches(pending: Vec<(u64, &str)>, frame: u64) -> Vec<&str> {
+    pending
+        .into_iter()
+        .filter(|(drawn, _)| drawn + STALE_FETCH_FRAMES < frame)
+        .map(|(_, key)| key)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_drawn() {
+        let loaded = vec![(5, "c"), (1, "a"), (3, "b"), (9, "d")];
+        assert_eq!(lru_victims(loaded.clone(), 2, 9), vec!["a", "b"]);
+        assert!(lru_victims(loaded.clone(), 4, 9).is_empty());
+        // never evict what's on screen
+        assert_eq!(lru_victims(loaded, 1, 5), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn counts_what_the_cache_did() {
+        let counters = ImageCounters::default();
+        assert_eq!(counters.stats().hit_rate(), 0.0);
+
+        counters.decoded(Duration::from_millis(3));
+        counters.decoded(Duration::from_millis(5));
+        counters.fetched(1024);
+        counters.hit();
+        counters.hit();
+        counters.hit();
+        counters.miss();
+
+        let stats = counters.stats();
+        assert_eq!(stats.decodes, 2);
+        assert_eq!(stats.decode_time, Duration::from_millis(8));
+        assert_eq!(stats.bytes_fetched, 1024);
+        assert_eq!(stats.hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let img = ColorImage {
+            size: [3, 2],
+            pixels: (0..6u8)
+                .map(|i| Color32::from_rgba_premultiplied(i, i * 2, i * 3, 200))
+                .collect(),
+        };
+        let dir = std::env::temp_dir().join(format!("notedeck-raw-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = ImageCache::key("https://example.com/a.png", 3, ImageType::Content);
+        let raw = ImageCache::write(&dir, &key, &img, CacheFormat::Raw).unwrap();
+
+        let raw_len = (CACHE_HEADER_LEN + 6 * 4) as u64;
+        assert_eq!(
+            raw,
+            CacheWrite {
+                written: raw_len,
+                replaced: 0
+            }
+        );
+        assert!(ImageCache::is_cached(&dir.join(&key)));
+        assert_eq!(
+            ImageCache::read(&fs::read(dir.join(&key)).unwrap()).unwrap(),
+            img
+        );
+
+        // pngs come back pixel for pixel, and nothing is left half written
+        let png = ImageCache::write(&dir, &key, &img, CacheFormat::Png).unwrap();
+        assert_eq!(png.replaced, raw_len);
+
+        // only the difference counts against the budget
+        let used_bytes = Arc::new(AtomicU64::new(raw_len));
+        ImageCache::on_write(&dir, &used_bytes, u64::MAX, png);
+        assert_eq!(used_bytes.load(Ordering::Relaxed), png.written);
+        assert!(ImageCache::is_cached(&dir.join(&key)));
+        assert_eq!(
+            ImageCache::read(&fs::read(dir.join(&key)).unwrap()).unwrap(),
+            img
+        );
+        assert!(!dir.join(format!("{}.tmp", key)).exists());
+
+        // eg. a webp from before the cache stored raw pixels
+        fs::write(dir.join("old"), b"RIFF\x00\x00\x00\x00WEBPVP8L").unwrap();
+        assert!(!ImageCache::is_cached(&dir.join("old")));
+        assert!(!ImageCache::is_cached(&dir.join("missing")));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let truncated = [&CACHE_MAGIC[..], &[3, 0, 0, 0, 2, 0, 0, 0, 1, 2][..]].concat();
+        assert!(ImageCache::read(&truncated).is_err());
+    }
+
+    #[test]
+    fn reads_back_downloads_as_they_arrived() {
+        let dir = std::env::temp_dir().join(format!("notedeck-source-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = ImageCache::source_key("https://example.com/a.gif");
+        ImageCache::write_source(&dir, &key, "image/gif", b"GIF89a...").unwrap();
+
+        let data = fs::read(dir.join(&key)).unwrap();
+        assert!(ImageCache::is_cached_source(&dir.join(&key)));
+        assert!(!ImageCache::is_cached(&dir.join(&key)));
+        assert_eq!(
+            ImageCache::read_source(&data).unwrap(),
+            ("image/gif", &b"GIF89a..."[..])
+        );
+        assert!(ImageCache::read_source(&data[..10]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn namespaces_stay_inside_the_base_dir() {
+        let dir = |namespace: &str| namespace_dir(namespace).unwrap();
+        assert_eq!(dir("alice"), "alice");
+        assert_eq!(dir("npub1abc-2_x"), "npub1abc-2_x");
+        assert_eq!(dir("../../etc"), "%2E%2E%2F%2E%2E%2Fetc");
+        assert_eq!(dir("a/b\\c"), "a%2Fb%5Cc");
+        assert!(namespace_dir("").is_err());
+
+        // namespaces that differ get directories that do
+        let names = ["a.b", "a/b", "a_b", "A_b", "a%2Eb", " a_b"];
+        let dirs: std::collections::HashSet<String> = names.iter().map(|name| dir(*name)).collect();
+        assert_eq!(dirs.len(), names.len());
+
+        let base = std::env::temp_dir().join(format!("notedeck-ns-test-{}", std::process::id()));
+        let cache = ImageCache::namespaced(&base, "bob", ImageCache::default_max_bytes()).unwrap();
+        assert_eq!(cache.cache_dir, base.join("bob"));
+        assert!(cache.cache_dir.is_dir());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn only_the_first_write_failure_counts() {
+        let persistent = AtomicBool::new(true);
+        assert!(ImageCache::write_failed(&persistent));
+        assert!(!ImageCache::write_failed(&persistent));
+        assert!(!persistent.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn queues_fetches_by_priority() {
+        let queue = Arc::new(Mutex::new(FetchQueue::new(1)));
+        let started = Arc::new(Mutex::new(vec![]));
+        let submit = |name: &'static str, priority| {
+            let started = started.clone();
+            let start = Box::new(move || started.lock().unwrap().push(name));
+            FetchQueue::submit(&queue, priority, start);
+        };
+
+        submit("first", Priority::Low);
+        submit("avatar", Priority::Low);
+        submit("embed", Priority::Normal);
+        submit("focused", Priority::High);
+        submit("another embed", Priority::Normal);
+        assert_eq!(*started.lock().unwrap(), vec!["first"]);
+
+        for _ in 0..4 {
+            FetchQueue::finish(&queue);
+        }
+        assert_eq!(
+            *started.lock().unwrap(),
+            vec!["first", "focused", "embed", "another embed", "avatar"]
+        );
+        FetchQueue::finish(&queue);
+        assert_eq!(queue.lock().unwrap().running, 0);
+    }
+
+    #[test]
+    fn forgets_the_oldest_colors_first() {
+        let mut colors = ColorMap::default();
+        for i in 0..MAX_DOMINANT_COLORS + 2 {
+            colors.insert(&format!("https://example.com/{}.png", i), Color32::RED);
+        }
+        assert_eq!(colors.colors.len(), MAX_DOMINANT_COLORS);
+        assert_eq!(colors.get("https://example.com/1.png"), None);
+        assert_eq!(colors.get("https://example.com/2.png"), Some(Color32::RED));
+
+        // updating a color doesn't make room for another
+        colors.insert("https://example.com/2.png", Color32::BLUE);
+        assert_eq!(colors.get("https://example.com/2.png"), Some(Color32::BLUE));
+        assert_eq!(colors.order.len(), MAX_DOMINANT_COLORS);
+
+        colors.clear();
+        assert_eq!(colors.get("https://example.com/2.png"), None);
+    }
+
+    #[test]
+    fn clearing_stops_animations_loading() {
+        let dir = std::env::temp_dir().join(format!("notedeck-clear-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut cache = ImageCache::new(dir.clone(), 1024);
+        let cancel = CancelToken::default();
+        let (_sender, promise) = Promise::new();
+        let fetch = AnimatedFetch {
+            promise,
+            cancel: cancel.clone(),
+        };
+        cache.insert_animated("animated:a".to_owned(), fetch);
+
+        cache.clear().unwrap();
+        assert!(!cache.contains_animated("animated:a"));
+        assert!(cancel.is_canceled());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cancels_fetches_that_scrolled_away() {
+        let frame = 100;
+        let pending = vec![(frame, "visible"), (frame - STALE_FETCH_FRAMES - 1, "gone")];
+        assert_eq!(stale_fetches(pending, frame), vec!["gone"]);
+    }
 }
@@ -1,26 +1,31 @@
 mod app;
 //mod camera;
 mod error;
+mod exif;
 //mod note;
 //mod block;
 mod abbrev;
 pub mod account_login_view;
 pub mod app_creation;
 mod app_style;
+mod bidi;
+mod blurhash;
 mod colors;
 mod filter;
-mod fonts;
+pub mod fonts;
 mod frame_history;
-mod images;
-mod imgcache;
+pub mod image_proxy;
+pub mod images;
+pub mod imgcache;
 mod key_parsing;
+pub mod lightning;
+pub mod link_preview;
 pub mod login_manager;
 mod notecache;
 mod profile;
 pub mod relay_pool_manager;
 mod result;
 mod test_data;
-mod time;
 mod timecache;
 mod timeline;
 pub mod ui;
@@ -31,7 +36,7 @@ mod test_utils;
 
 pub use app::Damus;
 pub use error::Error;
-pub use profile::DisplayName;
+pub use profile::{DisplayName, Nip05Status};
 
 #[cfg(target_os = "android")]
 use winit::platform::android::EventLoopBuilderExtAndroid;
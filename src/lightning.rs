@@ -0,0 +1,265 @@
+/// What we could read out of a BOLT11 invoice
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invoice {
+    /// How much it's for, if it says
+    pub msat: Option<u64>,
+    pub description: Option<String>,
+}
+
+const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The tagged field holding an invoice's description
+const DESCRIPTION_FIELD: u8 = 13;
+
+/// An invoice's data starts with a 35 bit timestamp...
+const TIMESTAMP_LEN: usize = 7;
+
+/// ...and ends with a 520 bit signature, before the checksum
+const SIGNATURE_LEN: usize = 104;
+
+const CHECKSUM_LEN: usize = 6;
+
+/// Drop the "lightning:" scheme that often comes in front of invoices and
+/// LNURLs
+pub fn strip_lightning_scheme(s: &str) -> &str {
+    match s.get(..10) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("lightning:") => &s[10..],
+        _ => s,
+    }
+}
+
+/// Whether `s` looks like a BOLT11 invoice, without checking that it is one
+pub fn is_invoice(s: &str) -> bool {
+    let s = strip_lightning_scheme(s);
+    ["lnbc", "lntb", "lntbs", "lnbcrt"].iter().any(|prefix| {
+        s.get(..prefix.len())
+            .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+            && s[prefix.len()..].contains('1')
+    })
+}
+
+/// Whether `s` looks like a bech32 encoded LNURL
+pub fn is_lnurl(s: &str) -> bool {
+    let s = strip_lightning_scheme(s);
+    s.get(..6).is_some_and(|p| p.eq_ignore_ascii_case("lnurl1"))
+}
+
+/// The amount and description in a BOLT11 invoice, or `None` if it isn't
+/// a valid one. The signature isn't checked; we only show what it says.
+pub fn decode_invoice(s: &str) -> Option<Invoice> {
+    let (hrp, data) = decode_bech32(strip_lightning_scheme(s))?;
+    let msat = invoice_amount(&hrp)?;
+
+    if data.len() < TIMESTAMP_LEN + SIGNATURE_LEN {
+        return None;
+    }
+    let mut fields = &data[TIMESTAMP_LEN..data.len() - SIGNATURE_LEN];
+
+    let mut description = None;
+    while fields.len() >= 3 {
+        let kind = fields[0];
+        let len = (fields[1] as usize) << 5 | fields[2] as usize;
+        let value = fields.get(3..3 + len)?;
+        if kind == DESCRIPTION_FIELD {
+            description = String::from_utf8(to_bytes(value)).ok();
+        }
+        fields = &fields[3 + len..];
+    }
+
+    Some(Invoice { msat, description })
+}
+
+/// The url an LNURL encodes
+pub fn decode_lnurl(s: &str) -> Option<String> {
+    let (hrp, data) = decode_bech32(strip_lightning_scheme(s))?;
+    if hrp != "lnurl" {
+        return None;
+    }
+    String::from_utf8(to_bytes(&data)).ok()
+}
+
+/// The amount in an invoice's human readable part, like "lnbc2500u".
+/// `Some(None)` means it doesn't give one.
+fn invoice_amount(hrp: &str) -> Option<Option<u64>> {
+    let currency = hrp.strip_prefix("ln")?;
+    let amount = currency.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    if amount.len() == currency.len() {
+        return None;
+    }
+    if amount.is_empty() {
+        return Some(None);
+    }
+
+    let (digits, multiplier) = match amount.as_bytes()[amount.len() - 1] {
+        b'0'..=b'9' => (amount, None),
+        m => (&amount[..amount.len() - 1], Some(m)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+
+    // amounts are in bitcoin, times the multiplier
+    let msat = match multiplier {
+        None => n.checked_mul(100_000_000_000),
+        Some(b'm') => n.checked_mul(100_000_000),
+        Some(b'u') => n.checked_mul(100_000),
+        Some(b'n') => n.checked_mul(100),
+        Some(b'p') if n.is_multiple_of(10) => Some(n / 10),
+        _ => None,
+    }?;
+    Some(Some(msat))
+}
+
+/// Split a bech32 string into its lowercased human readable part and its
+/// 5 bit data, without the checksum. Unlike BIP-173 there's no length
+/// limit, since invoices are usually longer than 90 characters.
+fn decode_bech32(s: &str) -> Option<(String, Vec<u8>)> {
+    // mixed case isn't allowed
+    if s.chars().any(|c| c.is_ascii_lowercase()) && s.chars().any(|c| c.is_ascii_uppercase()) {
+        return None;
+    }
+    let s = s.to_ascii_lowercase();
+
+    let sep = s.rfind('1')?;
+    let (hrp, data) = (&s[..sep], &s[sep + 1..]);
+    if hrp.is_empty() || data.len() < CHECKSUM_LEN {
+        return None;
+    }
+
+    let data: Vec<u8> = data
+        .chars()
+        .map(|c| CHARSET.find(c).map(|i| i as u8))
+        .collect::<Option<_>>()?;
+    if polymod(hrp_expand(hrp).chain(data.iter().copied())) != 1 {
+        return None;
+    }
+
+    let len = data.len() - CHECKSUM_LEN;
+    Some((hrp.to_owned(), data[..len].to_vec()))
+}
+
+fn hrp_expand(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|b| b >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|b| b & 31))
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for value in values {
+        let top = chk >> 25;
+        chk = (chk & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// Regroup 5 bit values into bytes, dropping the padding at the end
+fn to_bytes(data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(data.len() * 5 / 8);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for value in data {
+        acc = acc << 5 | *value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_groups(bytes: &[u8]) -> Vec<u8> {
+        let mut groups = vec![];
+        let mut acc: u32 = 0;
+        let mut bits = 0;
+        for byte in bytes {
+            acc = acc << 8 | *byte as u32;
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                groups.push((acc >> bits) as u8 & 31);
+            }
+        }
+        if bits > 0 {
+            groups.push((acc << (5 - bits)) as u8 & 31);
+        }
+        groups
+    }
+
+    fn encode(hrp: &str, data: &[u8]) -> String {
+        let values = hrp_expand(hrp).chain(data.iter().copied()).chain([0; 6]);
+        let chk = polymod(values) ^ 1;
+        let checksum = (0..6).map(|i| (chk >> (5 * (5 - i))) as u8 & 31);
+        let chars = data.iter().copied().chain(checksum);
+        let data: String = chars
+            .map(|v| CHARSET.as_bytes()[v as usize] as char)
+            .collect();
+        format!("{}1{}", hrp, data)
+    }
+
+    fn invoice(hrp: &str, description: &str) -> String {
+        let description = to_groups(description.as_bytes());
+        let mut data = vec![0; TIMESTAMP_LEN];
+        data.push(DESCRIPTION_FIELD);
+        data.push((description.len() >> 5) as u8);
+        data.push((description.len() & 31) as u8);
+        data.extend(description);
+        data.extend([0; SIGNATURE_LEN]);
+        encode(hrp, &data)
+    }
+
+    #[test]
+    fn decodes_invoice_amounts_and_descriptions() {
+        let s = invoice("lnbc2500u", "1 cup coffee");
+        assert!(is_invoice(&s));
+        assert_eq!(
+            decode_invoice(&s),
+            Some(Invoice {
+                msat: Some(250_000_000),
+                description: Some("1 cup coffee".to_owned()),
+            })
+        );
+
+        let s = format!("lightning:{}", invoice("lnbc", "donation").to_uppercase());
+        assert_eq!(decode_invoice(&s).unwrap().msat, None);
+
+        assert_eq!(invoice_amount("lnbc10n"), Some(Some(1_000)));
+        assert_eq!(invoice_amount("lnbc15p"), None);
+        assert_eq!(invoice_amount("lnbc1x"), None);
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        let mut s = invoice("lnbc1m", "coffee");
+        let last = if s.ends_with('q') { 'p' } else { 'q' };
+        s.pop();
+        s.push(last);
+        assert_eq!(decode_invoice(&s), None);
+        assert!(is_invoice(&s));
+    }
+
+    #[test]
+    fn decodes_lnurls() {
+        let s = encode("lnurl", &to_groups(b"https://example.com/lnurlp/bob"));
+        assert!(is_lnurl(&s));
+        assert_eq!(
+            decode_lnurl(&s).as_deref(),
+            Some("https://example.com/lnurlp/bob")
+        );
+        assert!(!is_lnurl("lnurlp"));
+    }
+}
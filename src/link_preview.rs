@@ -0,0 +1,226 @@
+use crate::images::{check_status, fetch_limited, CancelToken};
+use crate::{Error, Result};
+use poll_promise::Promise;
+use std::collections::HashMap;
+
+/// We only need the `<head>` of a page, so don't buffer huge ones
+const MAX_HTML_BYTES: u64 = 512 * 1024;
+
+/// What a page says about itself in its OpenGraph tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkCard {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// An absolute url for the page's preview image
+    pub image: Option<String>,
+}
+
+impl LinkCard {
+    /// Whether the page gave us anything worth showing beyond its domain
+    pub fn has_metadata(&self) -> bool {
+        self.title.is_some() || self.description.is_some() || self.image.is_some()
+    }
+
+    pub fn domain(&self) -> &str {
+        domain(&self.url)
+    }
+}
+
+/// Link cards we've fetched, or are fetching, keyed by url
+#[derive(Default)]
+pub struct LinkPreviews {
+    cards: HashMap<String, Promise<Result<LinkCard>>>,
+}
+
+impl LinkPreviews {
+    /// The card for `url`, starting to fetch it the first time it's asked for
+    pub fn get(&mut self, ctx: &egui::Context, url: &str) -> Option<&Result<LinkCard>> {
+        self.cards
+            .entry(url.to_owned())
+            .or_insert_with(|| link_preview(ctx, url))
+            .ready()
+    }
+}
+
+/// Fetch a page and pull its OpenGraph metadata out
+pub fn link_preview(ctx: &egui::Context, url: &str) -> Promise<Result<LinkCard>> {
+    let (sender, promise) = Promise::new();
+    let ctx = ctx.clone();
+    let page_url = url.to_owned();
+
+    fetch_limited(
+        url,
+        MAX_HTML_BYTES,
+        CancelToken::default(),
+        Box::new(move |response| {
+            let card = response.and_then(check_status).and_then(|resp| {
+                let content_type = resp.content_type().unwrap_or_default();
+                if !content_type.starts_with("text/html") {
+                    return Err(Error::Generic(format!(
+                        "can't preview {} of type {}",
+                        page_url, content_type
+                    )));
+                }
+                Ok(parse_link_card(
+                    &page_url,
+                    &String::from_utf8_lossy(&resp.bytes),
+                ))
+            });
+
+            sender.send(card);
+            ctx.request_repaint();
+        }),
+    );
+
+    promise
+}
+
+/// Read the `og:title`, `og:description` and `og:image` meta tags out of a
+/// page. The first of each wins.
+pub fn parse_link_card(url: &str, html: &str) -> LinkCard {
+    let mut card = LinkCard {
+        url: url.to_owned(),
+        ..Default::default()
+    };
+
+    // lowercasing ascii leaves byte offsets alone, so we can search one and
+    // slice the other
+    let lower = html.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find("<meta") {
+        let start = from + i;
+        let end = lower[start..]
+            .find('>')
+            .map_or(lower.len(), |end| start + end);
+        let tag = &html[start..end];
+        from = end;
+
+        let key = attr(tag, "property").or_else(|| attr(tag, "name"));
+        let (Some(key), Some(content)) = (key, attr(tag, "content")) else {
+            continue;
+        };
+        let content = decode_entities(content.trim());
+        if content.is_empty() {
+            continue;
+        }
+
+        let field = match key.to_ascii_lowercase().as_str() {
+            "og:title" => &mut card.title,
+            "og:description" => &mut card.description,
+            "og:image" => &mut card.image,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = Some(content);
+        }
+    }
+
+    card.image = card.image.map(|image| resolve_url(url, &image));
+    card
+}
+
+/// The value of attribute `name` in the html tag `tag`
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let start = from + i;
+        from = start + name.len();
+
+        let rest = lower[from..].trim_start();
+        if !lower[..start].ends_with(|c: char| c.is_ascii_whitespace()) || !rest.starts_with('=') {
+            continue;
+        }
+
+        let value = tag[tag.len() - rest.len() + 1..].trim_start();
+        return match value.chars().next()? {
+            quote @ ('"' | '\'') => value[1..].split(quote).next(),
+            _ => value.split(|c: char| c.is_ascii_whitespace()).next(),
+        };
+    }
+
+    None
+}
+
+/// Decode the few entities that actually show up in meta tags
+fn decode_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// The scheme and host of a url, eg. `https://damus.io`
+fn origin(url: &str) -> &str {
+    let host_start = url.find("://").map_or(0, |i| i + 3);
+    match url[host_start..].find(['/', '?', '#']) {
+        Some(i) => &url[..host_start + i],
+        None => url,
+    }
+}
+
+/// The host of a url, without any `www.`
+pub fn domain(url: &str) -> &str {
+    let origin = origin(url);
+    let host = origin.find("://").map_or(origin, |i| &origin[i + 3..]);
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+/// Make a link found on the page at `base` absolute
+fn resolve_url(base: &str, href: &str) -> String {
+    if href.starts_with("http://") || href.starts_with("https://") {
+        href.to_owned()
+    } else if let Some(rest) = href.strip_prefix("//") {
+        let scheme = base.split("://").next().unwrap_or("https");
+        format!("{}://{}", scheme, rest)
+    } else if href.starts_with('/') {
+        format!("{}{}", origin(base), href)
+    } else {
+        let origin = origin(base);
+        let path = base[origin.len()..].split(['?', '#']).next().unwrap_or("");
+        let dir = path.rfind('/').map_or("", |i| &path[..i]);
+        format!("{}{}/{}", origin, dir, href)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_opengraph_tags() {
+        let html = r#"<html><head>
+            <META property="og:title" content="Damus &amp; Notedeck">
+            <meta content='A nostr client' property='og:description' />
+            <meta property="og:image" content="/banner.png">
+            <meta property="og:title" content="ignored">
+            </head></html>"#;
+        let card = parse_link_card("https://damus.io/notedeck", html);
+        assert_eq!(card.title.as_deref(), Some("Damus & Notedeck"));
+        assert_eq!(card.description.as_deref(), Some("A nostr client"));
+        assert_eq!(card.image.as_deref(), Some("https://damus.io/banner.png"));
+        assert!(card.has_metadata());
+    }
+
+    #[test]
+    fn falls_back_to_domain() {
+        let card = parse_link_card("https://www.example.com/a?b", "<title>hi</title>");
+        assert!(!card.has_metadata());
+        assert_eq!(card.domain(), "example.com");
+    }
+
+    #[test]
+    fn resolves_relative_urls() {
+        let base = "https://example.com/blog/post?x=1";
+        assert_eq!(
+            resolve_url(base, "//cdn.com/a.png"),
+            "https://cdn.com/a.png"
+        );
+        assert_eq!(resolve_url(base, "a.png"), "https://example.com/blog/a.png");
+        assert_eq!(resolve_url(base, "https://b.com/c"), "https://b.com/c");
+    }
+}
@@ -1,10 +1,10 @@
-use crate::time::time_ago_since;
 use crate::timecache::TimeCached;
+use crate::ui::time::{unix_now, RelativeTime};
 use nostrdb::{Note, NoteReply, NoteReplyBuf};
 use std::time::Duration;
 
 pub struct NoteCache {
-    reltime: TimeCached<String>,
+    reltime: TimeCached<RelativeTime>,
     pub reply: NoteReplyBuf,
     pub bar_open: bool,
 }
@@ -14,7 +14,7 @@ impl NoteCache {
         let created_at = note.created_at();
         let reltime = TimeCached::new(
             Duration::from_secs(1),
-            Box::new(move || time_ago_since(created_at)),
+            Box::new(move || RelativeTime::new(created_at, unix_now())),
         );
         let reply = NoteReply::new(note.tags()).to_owned();
         let bar_open = false;
@@ -25,7 +25,9 @@ impl NoteCache {
         }
     }
 
-    pub fn reltime_str(&mut self) -> &str {
-        return self.reltime.get();
+    /// How long ago the note was written, which is formatted in the
+    /// language of the ui when it's drawn
+    pub fn reltime(&mut self) -> RelativeTime {
+        *self.reltime.get()
     }
 }
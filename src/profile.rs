@@ -16,16 +16,43 @@ impl<'a> DisplayName<'a> {
             Self::Both { username, .. } => username,
         }
     }
+
+    /// The name to show first, the display name if there is one
+    pub fn display_name(&self) -> &'a str {
+        match self {
+            Self::One(n) => n,
+            Self::Both { display_name, .. } => display_name,
+        }
+    }
+}
+
+/// Where we are with verifying a profile's NIP-05 identifier. The actual
+/// lookup happens elsewhere; this is just what to display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip05Status {
+    /// Not checked yet, or still being checked
+    Unverified,
+    /// Verified, with the full `name@domain` identifier
+    Verified(String),
+    /// The identifier didn't resolve to this profile's pubkey
+    Failed,
 }
 
-fn is_empty(s: &str) -> bool {
-    s.chars().all(|c| c.is_whitespace())
+/// A name trimmed of surrounding whitespace, or None if nothing is left or
+/// it contains control characters that would mangle the layout
+pub fn usable_name(name: &str) -> Option<&str> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().any(char::is_control) {
+        None
+    } else {
+        Some(name)
+    }
 }
 
 pub fn get_profile_name<'a>(record: &'a ProfileRecord) -> Option<DisplayName<'a>> {
     let profile = record.record().profile()?;
-    let display_name = profile.display_name().filter(|n| !is_empty(n));
-    let name = profile.name().filter(|n| !is_empty(n));
+    let display_name = profile.display_name().and_then(usable_name);
+    let name = profile.name().and_then(usable_name);
 
     match (display_name, name) {
         (None, None) => None,
@@ -37,3 +64,31 @@ pub fn get_profile_name<'a>(record: &'a ProfileRecord) -> Option<DisplayName<'a>
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unusable_names() {
+        assert_eq!(usable_name("  jb55 "), Some("jb55"));
+        assert_eq!(usable_name(" \t\n"), None);
+        assert_eq!(usable_name("evil\u{0007}name"), None);
+        assert_eq!(usable_name(""), None);
+    }
+
+    #[test]
+    fn mock_profile_names() {
+        use crate::ui::preview::mock::{mock_profile, mock_profile_variant, MockVariant};
+
+        let profile = mock_profile();
+        assert_eq!(
+            profile.display_name.as_deref().and_then(usable_name),
+            Some("Will")
+        );
+
+        let profile = mock_profile_variant(MockVariant::NoDisplayName);
+        assert_eq!(profile.display_name.as_deref().and_then(usable_name), None);
+        assert_eq!(profile.name.as_deref().and_then(usable_name), Some("jb55"));
+    }
+}
@@ -1,5 +1,6 @@
 use enostr::RelayPool;
 pub use enostr::RelayStatus;
+use std::time::Instant;
 
 /// The interface to a RelayPool for UI components.
 /// Represents all user-facing operations that can be performed for a user's relays
@@ -10,6 +11,8 @@ pub struct RelayPoolManager<'a> {
 pub struct RelayInfo<'a> {
     pub relay_url: &'a str,
     pub status: &'a RelayStatus,
+    /// When we last heard from the relay, if ever
+    pub last_message: Option<Instant>,
 }
 
 impl<'a> RelayPoolManager<'a> {
@@ -24,6 +27,7 @@ impl<'a> RelayPoolManager<'a> {
             .map(|relay| RelayInfo {
                 relay_url: &relay.relay.url,
                 status: &relay.relay.status,
+                last_message: relay.last_message,
             })
             .collect()
     }
@@ -35,13 +39,32 @@ impl<'a> RelayPoolManager<'a> {
         }
     }
 
+    pub fn remove_relay_url(&mut self, relay_url: &str) {
+        self.pool
+            .relays
+            .retain(|relay| relay.relay.url != relay_url);
+    }
+
+    /// Move the relay at `from` in get_relay_infos so it's at `to`
+    pub fn move_relay(&mut self, from: usize, to: usize) {
+        let relays = &mut self.pool.relays;
+        if from < relays.len() && to < relays.len() {
+            let relay = relays.remove(from);
+            relays.insert(to, relay);
+        }
+    }
+
     /// removes all specified relay indicies shown in get_relay_infos
     pub fn remove_relays(&mut self, mut indices: Vec<usize>) {
         indices.sort_unstable_by(|a, b| b.cmp(a));
         indices.iter().for_each(|index| self.remove_relay(*index));
     }
 
+    /// Adding a relay that's already in the pool does nothing
     pub fn add_relay(&mut self, ctx: &egui::Context, relay_url: String) {
+        if self.pool.has(&relay_url) {
+            return;
+        }
         let _ = self.pool.add_url(relay_url, create_wakeup(ctx));
     }
 }
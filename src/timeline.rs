@@ -1,12 +1,10 @@
 use crate::{ui, Damus};
 use egui::containers::scroll_area::ScrollBarVisibility;
-use egui_virtual_list::VirtualList;
 use enostr::Filter;
 use nostrdb::{NoteKey, Subscription, Transaction};
 use std::cmp::Ordering;
-use std::sync::{Arc, Mutex};
 
-use log::warn;
+use log::{debug, warn};
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub struct NoteRef {
@@ -36,73 +34,101 @@ pub struct Timeline {
 
     /// Our nostrdb subscription
     pub subscription: Option<Subscription>,
-
-    /// State for our virtual list egui widget
-    pub list: Arc<Mutex<VirtualList>>,
 }
 
 impl Timeline {
     pub fn new(filter: Vec<Filter>) -> Self {
         let notes: Vec<NoteRef> = Vec::with_capacity(1000);
         let subscription: Option<Subscription> = None;
-        let list = Arc::new(Mutex::new(VirtualList::new()));
 
         Timeline {
             filter,
             notes,
             subscription,
-            list,
         }
     }
 }
 
+/// How many placeholder notes fill an empty timeline while it loads
+const LOADING_SKELETONS: usize = 8;
+
+/// How tall we guess a note is before it's been laid out
+const ESTIMATED_NOTE_HEIGHT: f32 = 120.0;
+
 pub fn timeline_view(ui: &mut egui::Ui, app: &mut Damus, timeline: usize) {
     //padding(4.0, ui, |ui| ui.heading("Notifications"));
-    /*
-    let font_id = egui::TextStyle::Body.resolve(ui.style());
-    let row_height = ui.fonts(|f| f.row_height(&font_id)) + ui.spacing().item_spacing.y;
-    */
 
     egui::ScrollArea::vertical()
         .scroll_bar_visibility(ScrollBarVisibility::AlwaysVisible)
         //.auto_shrink([false; 2])
-        /*
         .show_viewport(ui, |ui, viewport| {
-            render_notes_in_viewport(ui, app, viewport, row_height, font_id);
-        });
-        */
-        .show(ui, |ui| {
             let len = app.timelines[timeline].notes.len();
-            let list = app.timelines[timeline].list.clone();
-            list.lock()
-                .unwrap()
-                .ui_custom_layout(ui, len, |ui, start_index| {
+            if len == 0 {
+                // nothing from the relays yet
+                for _ in 0..LOADING_SKELETONS {
+                    ui.add(ui::skeleton::NoteSkeleton::new());
+                    ui.add(egui::Separator::default().spacing(0.0));
+                }
+                return;
+            }
+
+            let focus_id = ui.id().with(("feed_focus", timeline));
+            let mut focus = ui::focus::FeedFocus::load(ui.ctx(), focus_id);
+            focus.handle_keys(ui.ctx(), len);
+
+            let keys: Vec<u64> = app.timelines[timeline]
+                .notes
+                .iter()
+                .map(|note| note.key.as_u64())
+                .collect();
+            ui::VirtualList::new(ui.id().with(("timeline", timeline))).show(
+                ui,
+                viewport,
+                &keys,
+                |_| ESTIMATED_NOTE_HEIGHT,
+                |ui, index, _| {
                     ui.spacing_mut().item_spacing.y = 0.0;
                     ui.spacing_mut().item_spacing.x = 4.0;
 
-                    let note_key = app.timelines[timeline].notes[start_index].key;
+                    let note_key = app.timelines[timeline].notes[index].key;
 
                     let txn = if let Ok(txn) = Transaction::new(&app.ndb) {
                         txn
                     } else {
                         warn!("failed to create transaction for {:?}", note_key);
-                        return 0;
+                        return;
                     };
 
                     let note = if let Ok(note) = app.ndb.get_note_by_key(&txn, note_key) {
                         note
                     } else {
                         warn!("failed to query note {:?}", note_key);
-                        return 0;
+                        return;
                     };
 
                     let textmode = app.textmode;
-                    let note_ui = ui::Note::new(app, &note).note_previews(!textmode);
-                    ui.add(note_ui);
+                    let resp = ui::Note::new(app, &note)
+                        .note_previews(!textmode)
+                        .show_media(!textmode)
+                        .focused(focus.is_selected(index))
+                        .show(ui);
+                    // only notes the list lays out can scroll to themselves,
+                    // but moving one at a time keeps the next one close by
+                    if focus.take_scroll(index) {
+                        resp.response.scroll_to_me(None);
+                    }
+                    if let Some(hashtag) = resp.clicked_hashtag {
+                        // TODO: open a hashtag timeline
+                        debug!("clicked hashtag #{}", hashtag);
+                    }
+                    if resp.action != ui::NoteAction::None {
+                        // TODO: publish reactions, open the reply composer
+                        debug!("note action {:?} on {:?}", resp.action, note_key);
+                    }
                     ui.add(egui::Separator::default().spacing(0.0));
-
-                    1
-                });
+                },
+            );
+            focus.store(ui.ctx(), focus_id);
         });
 }
 
@@ -1,19 +1,283 @@
+use std::time::Duration;
+
+/// egui's own default for `Style::animation_time`
+const EGUI_ANIMATION_TIME: f32 = 1.0 / 12.0;
+
+/// How everything animated should move, for the whole app. The helpers in
+/// this module all go through these, as do egui's own animations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimSettings {
+    /// Snap animations straight to where they end up
+    pub reduced_motion: bool,
+    /// Multiplies how fast animations run, eg. 2.0 for twice as fast
+    pub speed: f32,
+}
+
+impl Default for AnimSettings {
+    fn default() -> Self {
+        AnimSettings {
+            reduced_motion: false,
+            speed: 1.0,
+        }
+    }
+}
+
+impl AnimSettings {
+    fn id() -> egui::Id {
+        egui::Id::new("anim_settings")
+    }
+
+    pub fn get(ctx: &egui::Context) -> Self {
+        ctx.data(|d| d.get_temp(Self::id())).unwrap_or_default()
+    }
+
+    pub fn set(self, ctx: &egui::Context) {
+        ctx.data_mut(|d| d.insert_temp(Self::id(), self));
+        ctx.style_mut(|style| style.animation_time = self.duration(EGUI_ANIMATION_TIME));
+    }
+
+    /// How long an animation that normally takes `time` seconds should take
+    pub fn duration(&self, time: f32) -> f32 {
+        if self.reduced_motion {
+            0.0
+        } else {
+            time / self.speed.max(f32::EPSILON)
+        }
+    }
+}
+
+/// How an animation's progress is shaped over its run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// Starts slow
+    EaseIn,
+    /// Ends slow
+    EaseOut,
+    /// Starts and ends slow
+    EaseInOut,
+    /// Overshoots a little and settles, like a spring
+    Spring,
+}
+
+/// Shape linear progress `t`, from 0 to 1, by `curve`. Every curve starts
+/// at 0 and ends at 1, though a spring goes past 1 on the way.
+pub fn ease(curve: Curve, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    match curve {
+        Curve::Linear => t,
+        Curve::EaseIn => t * t * t,
+        Curve::EaseOut => 1.0 - (1.0 - t).powi(3),
+        Curve::EaseInOut => {
+            if t < 0.5 {
+                4.0 * t * t * t
+            } else {
+                1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+            }
+        }
+        // a damped cosine, with the frequency picked so it's exactly at
+        // rest at t = 1
+        Curve::Spring => 1.0 - (-6.0 * t).exp() * (t * 4.5 * std::f32::consts::PI).cos(),
+    }
+}
+
 pub fn hover_expand(
     ui: &mut egui::Ui,
     id: egui::Id,
     size: f32,
     expand_size: f32,
     anim_speed: f32,
+) -> (egui::Rect, f32) {
+    hover_expand_with_curve(ui, id, size, expand_size, anim_speed, Curve::Linear)
+}
+
+/// [`hover_expand`], growing along `curve`
+pub fn hover_expand_with_curve(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    size: f32,
+    expand_size: f32,
+    anim_speed: f32,
+    curve: Curve,
 ) -> (egui::Rect, f32) {
     // Allocate space for the profile picture with a fixed size
     let default_size = size + expand_size;
     let (rect, response) =
         ui.allocate_exact_size(egui::vec2(default_size, default_size), egui::Sense::hover());
 
+    let anim_speed = AnimSettings::get(ui.ctx()).duration(anim_speed);
     let val = ui
         .ctx()
         .animate_bool_with_time(id, response.hovered(), anim_speed);
 
-    let size = size + val * expand_size;
+    let size = size + ease(curve, val) * expand_size;
     (rect, size)
 }
+
+/// How long to cross-fade from a placeholder to a freshly loaded image
+pub const FADE_IN_TIME: f32 = 0.15;
+
+/// Animate from 0 to 1 once `loaded` becomes true. If it was already loaded the
+/// first time we saw it, this is 1 immediately.
+pub fn fade_in(ctx: &egui::Context, id: egui::Id, loaded: bool) -> f32 {
+    fade_in_with_curve(ctx, id, loaded, Curve::Linear)
+}
+
+/// [`fade_in`], along `curve`
+pub fn fade_in_with_curve(ctx: &egui::Context, id: egui::Id, loaded: bool, curve: Curve) -> f32 {
+    let time = AnimSettings::get(ctx).duration(FADE_IN_TIME);
+    ease(curve, ctx.animate_bool_with_time(id, loaded, time))
+}
+
+/// How long a brief confirmation, like "Copied!", stays up, in seconds
+pub const TOAST_TIME: f64 = 1.5;
+
+/// How opaque a confirmation shown at `shown_at` should be now, fading out
+/// over the second half of [`TOAST_TIME`]. `None` once it's gone. Repaints
+/// are requested while it's still showing.
+pub fn toast_opacity(ctx: &egui::Context, shown_at: f64) -> Option<f32> {
+    let reduced_motion = AnimSettings::get(ctx).reduced_motion;
+    let opacity = toast_fade(ctx.input(|i| i.time) - shown_at, reduced_motion)?;
+    ctx.request_repaint();
+    Some(opacity)
+}
+
+/// With reduced motion the toast stays solid until it disappears
+fn toast_fade(elapsed: f64, reduced_motion: bool) -> Option<f32> {
+    if !(0.0..TOAST_TIME).contains(&elapsed) {
+        return None;
+    }
+    if reduced_motion {
+        return Some(1.0);
+    }
+    let fade_start = TOAST_TIME / 2.0;
+    Some((1.0 - (elapsed - fade_start).max(0.0) / fade_start) as f32)
+}
+
+/// How far through a loop of `period` seconds we are, from 0 to 1. Repaints
+/// are requested to keep it moving. `None` with reduced motion, when
+/// whatever is looping should hold still instead.
+pub fn loop_phase(ctx: &egui::Context, period: f32) -> Option<f32> {
+    let settings = AnimSettings::get(ctx);
+    if settings.reduced_motion {
+        return None;
+    }
+
+    let period = settings.duration(period) as f64;
+    ctx.request_repaint();
+    Some(((ctx.input(|i| i.time) % period) / period) as f32)
+}
+
+/// Which frame of a looping animation should be shown right now, given each
+/// frame's delay. A repaint is requested for when the next frame is due.
+/// With reduced motion, it stays on the first frame.
+pub fn animation_frame(ctx: &egui::Context, delays: &[Duration]) -> usize {
+    if AnimSettings::get(ctx).reduced_motion {
+        return 0;
+    }
+
+    let elapsed = Duration::from_secs_f64(ctx.input(|i| i.time).max(0.0));
+    let (frame, remaining) = frame_at(delays, elapsed);
+
+    if delays.len() > 1 {
+        ctx.request_repaint_after(remaining);
+    }
+
+    frame
+}
+
+/// The frame index at `elapsed` into a looping animation, and how long until
+/// the following frame.
+fn frame_at(delays: &[Duration], elapsed: Duration) -> (usize, Duration) {
+    let total: Duration = delays.iter().sum();
+    if total.is_zero() {
+        return (0, Duration::ZERO);
+    }
+
+    let mut t = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+    for (i, delay) in delays.iter().enumerate() {
+        if t < *delay {
+            return (i, *delay - t);
+        }
+        t -= *delay;
+    }
+
+    (0, Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_at_loops() {
+        let delays = [Duration::from_millis(100), Duration::from_millis(50)];
+
+        assert_eq!(
+            frame_at(&delays, Duration::from_millis(0)),
+            (0, Duration::from_millis(100))
+        );
+        assert_eq!(
+            frame_at(&delays, Duration::from_millis(120)),
+            (1, Duration::from_millis(30))
+        );
+        assert_eq!(
+            frame_at(&delays, Duration::from_millis(160)),
+            (0, Duration::from_millis(90))
+        );
+    }
+
+    #[test]
+    fn toasts_fade_out() {
+        assert_eq!(toast_fade(0.0, false), Some(1.0));
+        assert_eq!(toast_fade(TOAST_TIME / 2.0, false), Some(1.0));
+        assert_eq!(toast_fade(TOAST_TIME * 0.75, false), Some(0.5));
+        assert_eq!(toast_fade(TOAST_TIME, false), None);
+        assert_eq!(toast_fade(-1.0, false), None);
+
+        assert_eq!(toast_fade(TOAST_TIME * 0.75, true), Some(1.0));
+        assert_eq!(toast_fade(TOAST_TIME, true), None);
+    }
+
+    #[test]
+    fn curves_start_and_end_in_place() {
+        for curve in [
+            Curve::Linear,
+            Curve::EaseIn,
+            Curve::EaseOut,
+            Curve::EaseInOut,
+            Curve::Spring,
+        ] {
+            assert_eq!(ease(curve, 0.0), 0.0, "{:?}", curve);
+            assert!((ease(curve, 1.0) - 1.0).abs() < 1e-5, "{:?}", curve);
+        }
+
+        assert_eq!(ease(Curve::Linear, 0.25), 0.25);
+        assert!(ease(Curve::EaseIn, 0.25) < 0.25);
+        assert!(ease(Curve::EaseOut, 0.25) > 0.25);
+        assert_eq!(ease(Curve::EaseInOut, 0.5), 0.5);
+        assert!((1..10).any(|i| ease(Curve::Spring, i as f32 / 10.0) > 1.0));
+    }
+
+    #[test]
+    fn settings_scale_durations() {
+        let settings = AnimSettings {
+            reduced_motion: false,
+            speed: 2.0,
+        };
+        assert_eq!(settings.duration(0.5), 0.25);
+        assert_eq!(AnimSettings::default().duration(0.5), 0.5);
+
+        let reduced = AnimSettings {
+            reduced_motion: true,
+            ..Default::default()
+        };
+        assert_eq!(reduced.duration(0.5), 0.0);
+    }
+
+    #[test]
+    fn frame_at_no_delays() {
+        assert_eq!(frame_at(&[], Duration::from_secs(1)), (0, Duration::ZERO));
+    }
+}
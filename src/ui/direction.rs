@@ -0,0 +1,118 @@
+use crate::ui::{layout_mode, LayoutMode};
+use egui::{Align, Layout};
+
+/// Which way the user's language reads, and so which way rows of widgets
+/// run. Views ask this instead of assuming left to right, so the whole
+/// layout can be mirrored with one switch.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LayoutDirection {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+/// Languages written right to left, by ISO 639 code
+const RTL_LANGUAGES: [&str; 10] = ["ar", "arc", "dv", "fa", "he", "ku", "ps", "sd", "ur", "yi"];
+
+impl LayoutDirection {
+    fn id() -> egui::Id {
+        egui::Id::new("layout_direction")
+    }
+
+    /// The direction of a locale like "he_IL.UTF-8" or "ar-EG"
+    pub fn from_locale(locale: &str) -> Self {
+        let language = locale
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if RTL_LANGUAGES.contains(&language.as_str()) {
+            LayoutDirection::RightToLeft
+        } else {
+            LayoutDirection::LeftToRight
+        }
+    }
+
+    /// The direction of the locale we were started with
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .map_or_else(Self::default, |locale| Self::from_locale(&locale))
+    }
+
+    /// The configured direction, whatever the layout
+    pub fn configured(ctx: &egui::Context) -> Self {
+        ctx.data(|d| d.get_temp(Self::id())).unwrap_or_default()
+    }
+
+    /// The direction to lay things out in. Only the mobile layout mirrors
+    /// for now; the desktop columns still run left to right.
+    pub fn get(ctx: &egui::Context) -> Self {
+        match layout_mode(ctx) {
+            LayoutMode::Mobile => Self::configured(ctx),
+            _ => LayoutDirection::LeftToRight,
+        }
+    }
+
+    pub fn set(self, ctx: &egui::Context) {
+        ctx.data_mut(|d| d.insert_temp(Self::id(), self));
+    }
+
+    pub fn is_rtl(self) -> bool {
+        self == LayoutDirection::RightToLeft
+    }
+
+    /// A row starting from the edge text starts from
+    pub fn row(self, align: Align) -> Layout {
+        match self {
+            LayoutDirection::LeftToRight => Layout::left_to_right(align),
+            LayoutDirection::RightToLeft => Layout::right_to_left(align),
+        }
+    }
+
+    /// A row starting from the far edge, for things like trailing buttons
+    pub fn row_end(self, align: Align) -> Layout {
+        match self {
+            LayoutDirection::LeftToRight => Layout::right_to_left(align),
+            LayoutDirection::RightToLeft => Layout::left_to_right(align),
+        }
+    }
+
+    /// A column hugging the edge text starts from
+    pub fn column(self) -> Layout {
+        match self {
+            LayoutDirection::LeftToRight => Layout::top_down(Align::Min),
+            LayoutDirection::RightToLeft => Layout::top_down(Align::Max),
+        }
+    }
+}
+
+/// Like [`egui::Ui::horizontal`], but running in the layout direction
+pub fn horizontal<R>(
+    ui: &mut egui::Ui,
+    add_contents: impl FnOnce(&mut egui::Ui) -> R,
+) -> egui::InnerResponse<R> {
+    match LayoutDirection::get(ui.ctx()) {
+        LayoutDirection::LeftToRight => ui.horizontal(add_contents),
+        LayoutDirection::RightToLeft => {
+            let size = egui::vec2(ui.available_width(), ui.spacing().interact_size.y);
+            ui.allocate_ui_with_layout(size, Layout::right_to_left(Align::Center), add_contents)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_direction_from_locales() {
+        assert!(LayoutDirection::from_locale("he_IL.UTF-8").is_rtl());
+        assert!(LayoutDirection::from_locale("ar-EG").is_rtl());
+        assert!(LayoutDirection::from_locale("FA").is_rtl());
+        assert!(!LayoutDirection::from_locale("en_US.UTF-8").is_rtl());
+        assert!(!LayoutDirection::from_locale("C").is_rtl());
+        assert!(!LayoutDirection::from_locale("").is_rtl());
+    }
+}
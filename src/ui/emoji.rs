@@ -0,0 +1,174 @@
+use crate::images::{self, FetchOptions, ImageType};
+use crate::imgcache::ImageCache;
+use crate::ui::note::contents::tag_str;
+use egui::{vec2, TextStyle};
+use nostrdb::{Ndb, NoteKey, ProfileRecord, Transaction};
+use std::collections::HashMap;
+
+/// NIP-30 custom emoji: image urls that `:shortcode:`s in some text stand
+/// for. Shortcodes nobody defined stay as text.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CustomEmojis {
+    urls: HashMap<String, String>,
+}
+
+/// A piece of text split around its custom emoji
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmojiSpan<'a> {
+    Text(&'a str),
+    Emoji { shortcode: &'a str, url: &'a str },
+}
+
+/// Shortcodes are letters, numbers and underscores
+fn is_shortcode(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl CustomEmojis {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The emoji a note defines in its `emoji` tags
+    pub fn from_note(note: &nostrdb::Note) -> Self {
+        note.tags()
+            .iter()
+            .filter(|tag| tag_str(tag, 0) == Some("emoji"))
+            .filter_map(|tag| Some((tag_str(&tag, 1)?.to_owned(), tag_str(&tag, 2)?.to_owned())))
+            .collect()
+    }
+
+    /// The emoji someone's profile defines for their name, in the tags of
+    /// the metadata note it came from
+    pub fn from_profile(ndb: &Ndb, txn: &Transaction, profile: &ProfileRecord) -> Self {
+        let note_key = NoteKey::new(profile.record().note_key());
+        match ndb.get_note_by_key(txn, note_key) {
+            Ok(note) => Self::from_note(&note),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn insert(&mut self, shortcode: impl Into<String>, url: impl Into<String>) {
+        let shortcode = shortcode.into();
+        if is_shortcode(&shortcode) {
+            self.urls.insert(shortcode, url.into());
+        }
+    }
+
+    pub fn get(&self, shortcode: &str) -> Option<&str> {
+        self.urls.get(shortcode).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.urls.is_empty()
+    }
+
+    /// Split `text` into runs of plain text and the emoji between them
+    pub fn spans<'a>(&'a self, text: &'a str) -> Vec<EmojiSpan<'a>> {
+        let mut spans = vec![];
+        let mut start = 0;
+        let mut from = 0;
+        while !self.is_empty() {
+            let Some(open) = text[from..].find(':').map(|i| from + i) else {
+                break;
+            };
+            let Some(close) = text[open + 1..].find(':').map(|i| open + 1 + i) else {
+                break;
+            };
+
+            let shortcode = &text[open + 1..close];
+            match self.get(shortcode) {
+                Some(url) => {
+                    if open > start {
+                        spans.push(EmojiSpan::Text(&text[start..open]));
+                    }
+                    spans.push(EmojiSpan::Emoji { shortcode, url });
+                    start = close + 1;
+                    from = close + 1;
+                }
+                // the closing colon could be the start of the next one
+                None => from = close,
+            }
+        }
+
+        if start < text.len() {
+            spans.push(EmojiSpan::Text(&text[start..]));
+        }
+        spans
+    }
+}
+
+impl FromIterator<(String, String)> for CustomEmojis {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        let mut emojis = CustomEmojis::new();
+        for (shortcode, url) in iter {
+            emojis.insert(shortcode, url);
+        }
+        emojis
+    }
+}
+
+/// A custom emoji as tall as a line of body text, so it sits on the
+/// baseline of the text around it. It's shown as its shortcode until it
+/// loads, or if it can't be.
+pub fn emoji_image(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    shortcode: &str,
+    url: &str,
+) -> egui::Response {
+    let height = ui.text_style_height(&TextStyle::Body);
+    let img_size = (height * ui.ctx().pixels_per_point()).ceil() as u32;
+    let key = ImageCache::key(url, img_size, ImageType::Content);
+    if !img_cache.contains(&key) {
+        let options = FetchOptions {
+            size: img_size,
+            ..Default::default()
+        };
+        let fetch = images::fetch_img(img_cache, ui.ctx(), url, options);
+        img_cache.insert_fetch(key.clone(), fetch);
+    }
+
+    let response = match img_cache.ready(&key) {
+        Some(Ok(texture)) => {
+            let size = texture.size_vec2();
+            let width = height * size.x / size.y.max(1.0);
+            ui.add(egui::Image::new(texture).fit_to_exact_size(vec2(width, height)))
+        }
+        _ => ui.label(format!(":{}:", shortcode)),
+    };
+    response.on_hover_text(format!(":{}:", shortcode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_known_shortcodes_out() {
+        let emojis: CustomEmojis = [("soapbox".to_owned(), "https://e.x/s.png".to_owned())]
+            .into_iter()
+            .collect();
+        let soapbox = EmojiSpan::Emoji {
+            shortcode: "soapbox",
+            url: "https://e.x/s.png",
+        };
+
+        assert_eq!(
+            emojis.spans("hi :soapbox: there"),
+            vec![EmojiSpan::Text("hi "), soapbox, EmojiSpan::Text(" there")]
+        );
+        assert_eq!(
+            emojis.spans("at 12:30 :soapbox::soapbox:"),
+            vec![EmojiSpan::Text("at 12:30 "), soapbox, soapbox]
+        );
+        assert_eq!(
+            emojis.spans(":unknown: stays"),
+            vec![EmojiSpan::Text(":unknown: stays")]
+        );
+        assert_eq!(
+            CustomEmojis::new().spans(":soapbox:"),
+            vec![EmojiSpan::Text(":soapbox:")]
+        );
+    }
+}
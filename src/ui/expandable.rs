@@ -0,0 +1,128 @@
+use crate::ui::i18n::tr;
+use crate::ui::text::floor_grapheme_boundary;
+use egui::text::LayoutJob;
+use egui::{Galley, Sense};
+use std::sync::Arc;
+
+/// What happened to an [`expandable_text`]
+pub struct ExpandableResponse {
+    pub response: egui::Response,
+    /// The byte offset into the text that was clicked, if any
+    pub clicked_at: Option<usize>,
+}
+
+/// Lay out `job` clamped to `max_rows`, ending in an ellipsis with a "more"
+/// link when it doesn't fit. Whether it has been expanded is remembered in
+/// egui memory under `id`.
+pub fn expandable_text(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    mut job: LayoutJob,
+    max_rows: usize,
+) -> ExpandableResponse {
+    let expanded: bool = ui.data(|d| d.get_temp(id)).unwrap_or(false);
+
+    job.wrap.max_width = ui.available_width();
+    if !expanded {
+        job.wrap.max_rows = max_rows;
+        job.wrap.overflow_character = Some('…');
+    }
+
+    let galley = ui.fonts(|f| f.layout_job(job));
+    let elided = galley.elided;
+    let galley = elide_on_grapheme(ui, galley);
+    let (rect, response) = ui.allocate_exact_size(galley.size(), Sense::click());
+
+    let clicked_at = response
+        .interact_pointer_pos()
+        .filter(|_| response.clicked())
+        .and_then(|pos| {
+            let index = galley.cursor_from_pos(pos - rect.min).ccursor.index;
+            galley.job.text.char_indices().nth(index).map(|(i, _)| i)
+        });
+
+    ui.painter()
+        .galley(rect.min, galley, ui.visuals().text_color());
+
+    if elided {
+        if ui.link(tr!(ui.ctx(), "more")).clicked() {
+            ui.data_mut(|d| d.insert_temp(id, true));
+        }
+    } else if expanded && ui.link(tr!(ui.ctx(), "less")).clicked() {
+        ui.data_mut(|d| d.insert_temp(id, false));
+    }
+
+    ExpandableResponse {
+        response,
+        clicked_at,
+    }
+}
+
+/// egui elides by character, which can leave half an emoji sequence or a
+/// letter without its accents before the ellipsis. Cut back to the last
+/// whole grapheme and lay out again when that happens.
+fn elide_on_grapheme(ui: &egui::Ui, galley: Arc<Galley>) -> Arc<Galley> {
+    if !galley.elided {
+        return galley;
+    }
+
+    // the overflow character takes the place of what was cut
+    let shown: usize = galley
+        .rows
+        .iter()
+        .map(|row| row.char_count_including_newline())
+        .sum::<usize>()
+        .saturating_sub(1);
+    let text = &galley.job.text;
+    let end = text
+        .char_indices()
+        .nth(shown)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let grapheme_end = floor_grapheme_boundary(text, end);
+    if grapheme_end == end {
+        return galley;
+    }
+
+    let mut job = (*galley.job).clone();
+    truncate_job(&mut job, grapheme_end);
+    let format = job
+        .sections
+        .last()
+        .map(|section| section.format.clone())
+        .unwrap_or_default();
+    job.append("…", 0.0, format);
+    ui.fonts(|f| f.layout_job(job))
+}
+
+/// Drop everything in `job` from byte `end` on
+fn truncate_job(job: &mut LayoutJob, end: usize) {
+    job.text.truncate(end);
+    job.sections
+        .retain(|section| section.byte_range.start < end);
+    if let Some(last) = job.sections.last_mut() {
+        last.byte_range.end = last.byte_range.end.min(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::TextFormat;
+
+    #[test]
+    fn truncates_jobs_mid_section() {
+        let mut job = LayoutJob::default();
+        job.append("hello ", 0.0, TextFormat::default());
+        job.append("world", 0.0, TextFormat::default());
+
+        truncate_job(&mut job, 8);
+        assert_eq!(job.text, "hello wo");
+        assert_eq!(job.sections.len(), 2);
+        assert_eq!(job.sections[1].byte_range, 6..8);
+
+        truncate_job(&mut job, 3);
+        assert_eq!(job.sections.len(), 1);
+        assert_eq!(job.sections[0].byte_range, 0..3);
+    }
+}
@@ -0,0 +1,49 @@
+/// A short, locale-neutral rendering of a count, eg. "999", "1.5k" or
+/// "3.4M". Values are truncated to one decimal place, never rounded up, so
+/// 1999 is "1.9k" rather than "2k", and a count never shows as more than
+/// it is. From 100 of a unit up the decimal is dropped to keep it short.
+/// Use this anywhere a count of things is shown so they all read alike.
+pub fn compact_number(n: u64) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "k")];
+
+    for (size, suffix) in UNITS {
+        if n >= size {
+            let whole = n / size;
+            let tenths = (n % size) / (size / 10);
+            return if tenths == 0 || whole >= 100 {
+                format!("{}{}", whole, suffix)
+            } else {
+                format!("{}.{}{}", whole, tenths, suffix)
+            };
+        }
+    }
+
+    n.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compacts_counts() {
+        assert_eq!(compact_number(0), "0");
+        assert_eq!(compact_number(999), "999");
+        assert_eq!(compact_number(1_000), "1k");
+        assert_eq!(compact_number(1_250), "1.2k");
+        assert_eq!(compact_number(1_500), "1.5k");
+        assert_eq!(compact_number(2_400_000), "2.4M");
+    }
+
+    #[test]
+    fn truncates_at_the_boundaries() {
+        assert_eq!(compact_number(1_999), "1.9k");
+        assert_eq!(compact_number(1_050), "1k");
+        assert_eq!(compact_number(99_999), "99.9k");
+        assert_eq!(compact_number(100_500), "100k");
+        assert_eq!(compact_number(999_999), "999k");
+        assert_eq!(compact_number(1_000_000), "1M");
+        assert_eq!(compact_number(1_000_000_000), "1B");
+        assert_eq!(compact_number(u64::MAX), "18446744073B");
+    }
+}
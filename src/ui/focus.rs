@@ -0,0 +1,181 @@
+use crate::ui::note::{NoteAction, DEFAULT_REACTIONS};
+use egui::{EventFilter, Key};
+
+/// A shortcut for acting on the selected note
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteKeyAction {
+    Reply,
+    React,
+    Repost,
+    Open,
+}
+
+const SHORTCUTS: [(Key, NoteKeyAction); 5] = [
+    (Key::R, NoteKeyAction::Reply),
+    (Key::L, NoteKeyAction::React),
+    (Key::T, NoteKeyAction::Repost),
+    (Key::O, NoteKeyAction::Open),
+    (Key::Enter, NoteKeyAction::Open),
+];
+
+impl NoteKeyAction {
+    /// The shortcut pressed this frame, if any. Shortcuts with modifiers
+    /// held are left for the app.
+    pub fn from_input(input: &egui::InputState) -> Option<Self> {
+        if !input.modifiers.is_none() {
+            return None;
+        }
+        SHORTCUTS
+            .iter()
+            .find(|(key, _)| input.key_pressed(*key))
+            .map(|(_, action)| *action)
+    }
+
+    /// What the note does for this shortcut. Reacting uses the first of the
+    /// [`DEFAULT_REACTIONS`].
+    pub fn note_action(self) -> NoteAction {
+        match self {
+            NoteKeyAction::Reply => NoteAction::Reply,
+            NoteKeyAction::React => NoteAction::React(DEFAULT_REACTIONS[0].to_owned()),
+            NoteKeyAction::Repost => NoteAction::Repost,
+            NoteKeyAction::Open => NoteAction::Open,
+        }
+    }
+}
+
+/// Where we remember which note has focus, so feeds can tell it apart from
+/// a text field that has it
+fn focused_note_key() -> egui::Id {
+    egui::Id::new("focused_note")
+}
+
+/// Give the note `id` keyboard focus, unless something else like a text
+/// field has it, and return the shortcut pressed for it this frame
+pub fn focus_note(ui: &egui::Ui, id: egui::Id) -> Option<NoteKeyAction> {
+    let previous = ui.data(|d| d.get_temp::<egui::Id>(focused_note_key()));
+    let focused = ui.memory_mut(|m| {
+        if m.focused().is_none() || m.focused() == previous {
+            m.request_focus(id);
+            // the feed uses the arrow keys, so egui mustn't move focus with them
+            m.set_focus_lock_filter(
+                id,
+                EventFilter {
+                    vertical_arrows: true,
+                    horizontal_arrows: true,
+                    ..Default::default()
+                },
+            );
+        }
+        m.has_focus(id)
+    });
+    if !focused {
+        return None;
+    }
+
+    ui.data_mut(|d| d.insert_temp(focused_note_key(), id));
+    ui.input(NoteKeyAction::from_input)
+}
+
+/// Which note in a feed is selected with the keyboard. j and k or the
+/// arrow keys move the selection and escape clears it. It's kept in egui's
+/// memory under an id per feed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FeedFocus {
+    pub selected: Option<usize>,
+    /// Whether the selected note still needs scrolling into view
+    scroll: bool,
+}
+
+impl FeedFocus {
+    pub fn load(ctx: &egui::Context, id: egui::Id) -> Self {
+        ctx.data(|d| d.get_temp(id)).unwrap_or_default()
+    }
+
+    pub fn store(self, ctx: &egui::Context, id: egui::Id) {
+        ctx.data_mut(|d| d.insert_temp(id, self));
+    }
+
+    /// Move the selection for this frame's key presses, in a feed of `len`
+    /// notes. Nothing moves while something other than a note, like a text
+    /// field, has keyboard focus.
+    pub fn handle_keys(&mut self, ctx: &egui::Context, len: usize) {
+        let note = ctx.data(|d| d.get_temp::<egui::Id>(focused_note_key()));
+        if ctx.memory(|m| m.focused().is_some_and(|id| Some(id) != note)) {
+            return;
+        }
+
+        ctx.input(|i| {
+            if !i.modifiers.is_none() {
+                return;
+            }
+            if i.key_pressed(Key::J) || i.key_pressed(Key::ArrowDown) {
+                self.move_by(1, len);
+            }
+            if i.key_pressed(Key::K) || i.key_pressed(Key::ArrowUp) {
+                self.move_by(-1, len);
+            }
+            if i.key_pressed(Key::Escape) {
+                self.selected = None;
+            }
+        });
+        if self.selected.is_none() {
+            ctx.data_mut(|d| d.remove::<egui::Id>(focused_note_key()));
+        }
+    }
+
+    /// Move the selection `delta` notes along, stopping at either end. With
+    /// nothing selected, moving selects the first note.
+    fn move_by(&mut self, delta: isize, len: usize) {
+        if len == 0 {
+            self.selected = None;
+            return;
+        }
+        let next = match self.selected {
+            Some(index) => index.saturating_add_signed(delta).min(len - 1),
+            None => 0,
+        };
+        self.scroll |= self.selected != Some(next);
+        self.selected = Some(next);
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected == Some(index)
+    }
+
+    /// Whether the note at `index` should be scrolled into view, which the
+    /// selected one needs once after the selection moves
+    pub fn take_scroll(&mut self, index: usize) -> bool {
+        let scroll = self.scroll && self.is_selected(index);
+        if scroll {
+            self.scroll = false;
+        }
+        scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selection_stays_in_the_feed() {
+        let mut focus = FeedFocus::default();
+        focus.move_by(-1, 3);
+        assert_eq!(focus.selected, Some(0));
+
+        focus.move_by(1, 3);
+        focus.move_by(1, 3);
+        assert!(focus.take_scroll(2));
+        focus.move_by(1, 3);
+        assert_eq!(focus.selected, Some(2));
+        assert!(!focus.take_scroll(2), "didn't move on the last press");
+
+        focus.move_by(-1, 3);
+        assert!(!focus.take_scroll(2));
+        assert!(focus.take_scroll(1));
+        assert!(!focus.take_scroll(1));
+
+        focus.move_by(1, 0);
+        assert_eq!(focus.selected, None);
+    }
+}
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// Translations of the text widgets show, keyed by their English text.
+/// Anything a table doesn't have is shown in English, so it only needs
+/// the strings it translates. The `{}`s in a key are filled in, in the
+/// same order, wherever its translation has them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Strings {
+    table: HashMap<String, String>,
+}
+
+impl Strings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, english: impl Into<String>, translated: impl Into<String>) {
+        self.table.insert(english.into(), translated.into());
+    }
+
+    pub fn with(mut self, english: impl Into<String>, translated: impl Into<String>) -> Self {
+        self.insert(english, translated);
+        self
+    }
+
+    /// The translation of `english`, or `english` itself without one
+    pub fn get<'a>(&'a self, english: &'a str) -> &'a str {
+        self.table
+            .get(english)
+            .map(String::as_str)
+            .unwrap_or(english)
+    }
+
+    fn id() -> egui::Id {
+        egui::Id::new("i18n_strings")
+    }
+
+    /// Show every widget's text in `ctx` from `strings`, or in English
+    /// again when it's `None`
+    pub fn install(ctx: &egui::Context, strings: Option<Strings>) {
+        ctx.data_mut(|d| match strings {
+            Some(strings) => d.insert_temp(Self::id(), Arc::new(strings)),
+            None => d.remove::<Arc<Strings>>(Self::id()),
+        });
+    }
+
+    /// The strings installed in `ctx`, which are empty until a host
+    /// installs some
+    pub fn get_for(ctx: &egui::Context) -> Arc<Strings> {
+        ctx.data(|d| d.get_temp::<Arc<Strings>>(Self::id()))
+            .unwrap_or_default()
+    }
+}
+
+impl FromIterator<(String, String)> for Strings {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Strings {
+            table: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Fill the `{}`s in `template` with `args`, in order. Any left over once
+/// the args run out stay as they are.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some(at) = rest.find("{}") {
+        filled.push_str(&rest[..at]);
+        match args.next() {
+            Some(arg) => filled.push_str(&arg.to_string()),
+            None => filled.push_str("{}"),
+        }
+        rest = &rest[at + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// `english` in the language of the strings installed in `ctx`. This runs
+/// for most text every frame, so it looks the strings up in place, and
+/// only text that has a translation is copied.
+pub fn translate<'a>(ctx: &egui::Context, english: &'a str) -> Cow<'a, str> {
+    ctx.data_mut(|d| {
+        match d
+            .get_temp_mut_or_default::<Arc<Strings>>(Strings::id())
+            .table
+            .get(english)
+        {
+            Some(translated) => Cow::Owned(translated.clone()),
+            None => Cow::Borrowed(english),
+        }
+    })
+}
+
+/// Translate a widget's text with the [`Strings`] installed in a context,
+/// filling in any `{}`s with the arguments after it:
+/// `tr!(ui.ctx(), "Up to {} subscriptions", subs)`
+macro_rules! tr {
+    ($ctx:expr, $english:expr) => {
+        $crate::ui::i18n::translate($ctx, $english)
+    };
+    ($ctx:expr, $english:expr, $($arg:expr),+ $(,)?) => {
+        $crate::ui::i18n::fill(
+            &$crate::ui::i18n::translate($ctx, $english),
+            &[$(&$arg as &dyn std::fmt::Display),+],
+        )
+    };
+}
+pub(crate) use tr;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_english() {
+        let strings = Strings::new().with("Show more", "Mehr anzeigen");
+        assert_eq!(strings.get("Show more"), "Mehr anzeigen");
+        assert_eq!(strings.get("Copied!"), "Copied!");
+    }
+
+    #[test]
+    fn fills_placeholders_in_order() {
+        assert_eq!(fill("{} of {} relays", &[&3, &12]), "3 of 12 relays");
+        assert_eq!(fill("{} de {} relés", &[&3, &12]), "3 de 12 relés");
+        assert_eq!(fill("Pay {} sats", &[]), "Pay {} sats");
+        assert_eq!(fill("no placeholders", &[&1]), "no placeholders");
+    }
+
+    #[test]
+    fn installs_per_context() {
+        let ctx = egui::Context::default();
+        assert!(matches!(tr!(&ctx, "Zap"), Cow::Borrowed("Zap")));
+
+        Strings::install(
+            &ctx,
+            Some(Strings::new().with("Up to {} notes", "Hasta {} notas")),
+        );
+        assert_eq!(tr!(&ctx, "Up to {} notes", 5), "Hasta 5 notas");
+
+        Strings::install(&ctx, None);
+        assert_eq!(tr!(&ctx, "Up to {} notes", 5), "Up to 5 notes");
+    }
+}
@@ -0,0 +1,68 @@
+use crate::abbrev::abbreviate_middle;
+use crate::ui::nip19::{self, Nip19Entity};
+
+/// How many characters of a shortened identifier we keep after its "npub1"
+pub const PREFIX_LEN: usize = 4;
+
+/// How many characters of a shortened identifier we keep from its end
+pub const SUFFIX_LEN: usize = 4;
+
+/// Shorten a bech32 entity for display, keeping its human readable part
+/// and `prefix_len` and `suffix_len` characters either side of an ellipsis,
+/// eg. "npub1abcd…wxyz". A leading "nostr:" is dropped.
+pub fn shorten_bech32(s: &str, prefix_len: usize, suffix_len: usize) -> String {
+    let s = s.strip_prefix("nostr:").unwrap_or(s);
+
+    // bech32 data never contains a '1', so the last one is the separator
+    let data_start = match s.rfind('1') {
+        Some(sep) if s[..sep].bytes().all(|b| b.is_ascii_lowercase()) => sep + 1,
+        _ => 0,
+    };
+    let (hrp, data) = s.split_at(data_start);
+    format!("{}{}", hrp, abbreviate_middle(data, prefix_len, suffix_len))
+}
+
+/// Someone's npub shortened with the default lengths, or their hex pubkey
+/// if it can't be encoded
+pub fn short_npub(pk: &[u8; 32]) -> String {
+    let npub = nip19::npub(pk).unwrap_or_else(|_| hex::encode(pk));
+    shorten_bech32(&npub, PREFIX_LEN, SUFFIX_LEN)
+}
+
+/// A note's id as a shortened `note1`, or as hex if it can't be encoded
+pub fn short_note_id(id: &[u8; 32]) -> String {
+    let note = nip19::encode(&Nip19Entity::Note(*id)).unwrap_or_else(|_| hex::encode(id));
+    shorten_bech32(&note, PREFIX_LEN, SUFFIX_LEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NPUB: &str = "npub1xtscya34g58tk0z605fvr788k263gsu6cy9x0mhnm87echrgufzsevkk5s";
+
+    #[test]
+    fn keeps_the_hrp_and_both_ends() {
+        assert_eq!(shorten_bech32(NPUB, 4, 4), "npub1xtsc…kk5s");
+        assert_eq!(shorten_bech32(NPUB, 6, 2), "npub1xtscya…5s");
+        assert_eq!(
+            shorten_bech32(&format!("nostr:{}", NPUB), 4, 4),
+            shorten_bech32(NPUB, 4, 4)
+        );
+    }
+
+    #[test]
+    fn shortens_note_ids() {
+        let id = hex::decode("d4a3c5f6d2a1b0c9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(short_note_id(&id), "note16j3u…xpre");
+    }
+
+    #[test]
+    fn leaves_short_and_hex_ids_alone() {
+        assert_eq!(shorten_bech32("npub1abcdefgh", 4, 4), "npub1abcdefgh");
+        assert_eq!(shorten_bech32("0123456789abcdef", 4, 4), "0123…cdef");
+    }
+}
@@ -0,0 +1,388 @@
+use crate::images::{self, FetchOptions, ImageType, Priority};
+use crate::imgcache::ImageCache;
+use crate::ui::i18n::tr;
+use crate::ui::note::contents::{cached_texture, MediaTexture};
+use crate::ui::note::gallery::fit_within;
+use crate::ui::{self, View};
+use egui::{vec2, Align2, Color32, FontId, Image, Key, Order, Rect, RichText, Sense, Vec2};
+
+/// The largest an image is decoded at for the lightbox. Bigger originals
+/// are scaled down to it, which keeps their texture within what GPUs take.
+const FULL_SIZE: u32 = 4096;
+
+const MAX_ZOOM: f32 = 8.0;
+
+/// How far a swipe has to go to move to the next image, in points
+const SWIPE_DISTANCE: f32 = 60.0;
+
+/// A full screen viewer for a note's images. Arrow keys, the buttons at the
+/// sides or swiping move between them, and scrolling or pinching zooms in.
+/// It closes on Escape or a tap outside the image. Where it's at is kept in
+/// egui's memory until it closes, so the host only has to keep showing it.
+pub struct Lightbox<'a> {
+    img_cache: &'a mut ImageCache,
+    urls: &'a [String],
+    initial: usize,
+}
+
+pub struct LightboxResponse {
+    pub response: egui::Response,
+    /// Whether the lightbox was closed this frame
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LightboxState {
+    index: usize,
+    zoom: f32,
+    /// How far the zoomed image has been dragged from the center
+    pan: Vec2,
+    /// How far the current swipe has gone
+    swipe: f32,
+}
+
+impl LightboxState {
+    fn new(index: usize) -> Self {
+        LightboxState {
+            index,
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+            swipe: 0.0,
+        }
+    }
+
+    /// Move `delta` images along, stopping at either end. The next image
+    /// starts out unzoomed.
+    fn step(&mut self, delta: isize, len: usize) {
+        let index = self
+            .index
+            .saturating_add_signed(delta)
+            .min(len.saturating_sub(1));
+        if index != self.index {
+            *self = LightboxState::new(index);
+        }
+    }
+
+    fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(1.0, MAX_ZOOM);
+        if self.zoom == 1.0 {
+            self.pan = Vec2::ZERO;
+        }
+    }
+
+    /// Keep an image drawn `size` points big at no zoom from being dragged
+    /// off a `view` of that size
+    fn clamp_pan(&mut self, size: Vec2, view: Vec2) {
+        let max = ((size * self.zoom - view) / 2.0).max(Vec2::ZERO);
+        self.pan = self.pan.clamp(-max, max);
+    }
+}
+
+/// Which way a swipe `distance` points long moves: left to the next image,
+/// right to the one before
+fn swipe_step(distance: f32) -> isize {
+    if distance <= -SWIPE_DISTANCE {
+        1
+    } else if distance >= SWIPE_DISTANCE {
+        -1
+    } else {
+        0
+    }
+}
+
+impl<'a> Lightbox<'a> {
+    pub fn new(img_cache: &'a mut ImageCache, urls: &'a [String]) -> Self {
+        Lightbox {
+            img_cache,
+            urls,
+            initial: 0,
+        }
+    }
+
+    /// Which image to open on
+    pub fn initial(mut self, index: usize) -> Self {
+        self.initial = index;
+        self
+    }
+
+    fn id() -> egui::Id {
+        egui::Id::new("lightbox")
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> LightboxResponse {
+        #[cfg(feature = "profiling")]
+        puffin::profile_function!();
+
+        let ctx = ui.ctx().clone();
+        let mut state = ctx
+            .data(|d| d.get_temp::<LightboxState>(Self::id()))
+            .unwrap_or_else(|| LightboxState::new(self.initial));
+        state.index = state.index.min(self.urls.len().saturating_sub(1));
+
+        let screen = ctx.screen_rect();
+        let area = egui::Area::new(Self::id())
+            .order(Order::Foreground)
+            .fixed_pos(screen.min)
+            .show(&ctx, |ui| self.contents(ui, screen, &mut state));
+
+        let closed = area.inner;
+        if closed {
+            ctx.data_mut(|d| d.remove::<LightboxState>(Self::id()));
+        } else {
+            ctx.data_mut(|d| d.insert_temp(Self::id(), state));
+        }
+        LightboxResponse {
+            response: area.response,
+            closed,
+        }
+    }
+
+    /// Draw the lightbox over `screen`, returning whether it was closed
+    fn contents(&mut self, ui: &mut egui::Ui, screen: Rect, state: &mut LightboxState) -> bool {
+        let (rect, response) = ui.allocate_exact_size(screen.size(), Sense::click_and_drag());
+        ui.painter()
+            .rect_filled(rect, 0.0, Color32::from_black_alpha(230));
+
+        let urls = self.urls;
+        let Some(url) = urls.get(state.index) else {
+            return true;
+        };
+        let len = urls.len();
+        let mobile = ui::is_mobile(ui.ctx());
+
+        let (escape, key_step, pinch, scroll) = ui.input(|i| {
+            let key_step = if i.key_pressed(Key::ArrowRight) {
+                1
+            } else if i.key_pressed(Key::ArrowLeft) {
+                -1
+            } else {
+                0
+            };
+            (
+                i.key_pressed(Key::Escape),
+                key_step,
+                i.zoom_delta(),
+                i.smooth_scroll_delta.y,
+            )
+        });
+        state.step(key_step, len);
+
+        // pinching on touch screens, ctrl+scroll or plain scrolling on desktop
+        state.zoom_by(pinch);
+        if !mobile && scroll != 0.0 {
+            state.zoom_by((scroll / 200.0).exp());
+        }
+        if response.double_clicked() {
+            state.zoom_by(if state.zoom > 1.0 { 0.0 } else { 2.0 });
+        }
+
+        // dragging pans a zoomed image and swipes between unzoomed ones
+        if response.dragged() {
+            if state.zoom > 1.0 {
+                state.pan += response.drag_delta();
+            } else {
+                state.swipe += response.drag_delta().x;
+            }
+        } else if state.swipe != 0.0 {
+            let swipe = std::mem::take(&mut state.swipe);
+            state.step(swipe_step(swipe), len);
+        }
+
+        let (texture, full) = self.texture(ui, url);
+        let image_rect = texture.as_ref().map(|texture| {
+            let size = fit_within(texture.size_vec2(), rect.size());
+            state.clamp_pan(size, rect.size());
+            let center = rect.center() + state.pan + vec2(state.swipe, 0.0);
+            Rect::from_center_size(center, size * state.zoom)
+        });
+
+        match (&texture, image_rect) {
+            (Some(texture), Some(image_rect)) => {
+                Image::new(texture).paint_at(ui, image_rect);
+            }
+            _ if matches!(full, MediaTexture::Failed) => {
+                ui.painter().text(
+                    rect.center(),
+                    Align2::CENTER_CENTER,
+                    tr!(ui.ctx(), "Couldn't load image"),
+                    FontId::proportional(16.0),
+                    Color32::GRAY,
+                );
+            }
+            _ => {}
+        }
+        if matches!(full, MediaTexture::Loading) {
+            let spinner =
+                Rect::from_center_size(rect.center_bottom() - vec2(0.0, 40.0), Vec2::splat(24.0));
+            egui::Spinner::new()
+                .color(Color32::WHITE)
+                .paint_at(ui, spinner);
+        }
+
+        let mut closed = escape;
+        if response.clicked() {
+            let pointer = response.interact_pointer_pos();
+            closed |= !image_rect.is_some_and(|image| pointer.is_some_and(|p| image.contains(p)));
+        }
+
+        let close = Rect::from_min_size(rect.right_top() + vec2(-48.0, 8.0), Vec2::splat(40.0));
+        closed |= ui.put(close, overlay_button("✕")).clicked();
+
+        if len > 1 {
+            ui.painter().text(
+                rect.center_top() + vec2(0.0, 24.0),
+                Align2::CENTER_CENTER,
+                format!("{} / {}", state.index + 1, len),
+                FontId::proportional(14.0),
+                Color32::WHITE,
+            );
+
+            // phones swipe instead
+            if !mobile {
+                let side = Vec2::new(48.0, 80.0);
+                let prev = Rect::from_center_size(rect.left_center() + vec2(32.0, 0.0), side);
+                let next = Rect::from_center_size(rect.right_center() - vec2(32.0, 0.0), side);
+                if state.index > 0 && ui.put(prev, overlay_button("‹")).clicked() {
+                    state.step(-1, len);
+                }
+                if state.index + 1 < len && ui.put(next, overlay_button("›")).clicked() {
+                    state.step(1, len);
+                }
+            }
+        }
+
+        closed
+    }
+
+    /// The image at full resolution, fetching it if we haven't, or the
+    /// thumbnail the note showed until it's ready. Also says how the full
+    /// one is coming along.
+    fn texture(&mut self, ui: &egui::Ui, url: &str) -> (Option<egui::TextureHandle>, MediaTexture) {
+        let key = ImageCache::key(url, FULL_SIZE, ImageType::Content);
+        if !self.img_cache.contains(&key) {
+            let options = FetchOptions {
+                size: FULL_SIZE,
+                priority: Priority::High,
+                ..Default::default()
+            };
+            let fetch = images::fetch_img(self.img_cache, ui.ctx(), url, options);
+            self.img_cache.insert_fetch(key.clone(), fetch);
+        }
+
+        let full = match self.img_cache.ready(&key) {
+            Some(Ok(texture)) => {
+                return (Some(texture.clone()), MediaTexture::Loaded(texture.clone()))
+            }
+            Some(Err(_err)) => MediaTexture::Failed,
+            None => MediaTexture::Loading,
+        };
+        match cached_texture(self.img_cache, url) {
+            MediaTexture::Loaded(thumbnail) => (Some(thumbnail), full),
+            MediaTexture::Loading | MediaTexture::Failed => (None, full),
+        }
+    }
+}
+
+fn overlay_button(text: &str) -> egui::Button<'static> {
+    egui::Button::new(RichText::new(text).size(28.0).color(Color32::WHITE)).frame(false)
+}
+
+impl<'a> View for Lightbox<'a> {
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        self.show(ui).response
+    }
+
+    fn is_loading(&self) -> bool {
+        self.img_cache.has_pending()
+    }
+}
+
+mod preview {
+    use super::*;
+    use crate::ui::preview::mock::{MOCK_BROKEN_IMAGE_URL, MOCK_IMAGE_URL};
+    use crate::ui::preview::preview_image_cache;
+    use crate::ui::Preview;
+
+    pub struct LightboxPreview {
+        cache: ImageCache,
+        urls: Vec<String>,
+        open: bool,
+    }
+
+    impl View for LightboxPreview {
+        fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+            if !self.open {
+                let button = ui.button("Open lightbox");
+                self.open = button.clicked();
+                return button;
+            }
+
+            let resp = Lightbox::new(&mut self.cache, &self.urls).show(ui);
+            self.open = !resp.closed;
+            resp.response
+        }
+
+        fn is_loading(&self) -> bool {
+            self.cache.has_pending()
+        }
+    }
+
+    impl<'a> Preview for Lightbox<'a> {
+        type Prev = LightboxPreview;
+
+        fn preview() -> Self::Prev {
+            Self::with_image_cache(preview_image_cache())
+        }
+
+        fn with_image_cache(cache: ImageCache) -> Self::Prev {
+            LightboxPreview {
+                cache,
+                urls: vec![MOCK_IMAGE_URL.to_owned(), MOCK_BROKEN_IMAGE_URL.to_owned()],
+                open: true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_between_images_resets_the_zoom() {
+        let mut state = LightboxState::new(0);
+        state.zoom_by(3.0);
+        state.pan = vec2(10.0, 0.0);
+        state.step(-1, 3);
+        assert_eq!(state.zoom, 3.0, "already at the first image");
+
+        state.step(1, 3);
+        assert_eq!(state, LightboxState::new(1));
+        state.step(5, 3);
+        assert_eq!(state.index, 2);
+    }
+
+    #[test]
+    fn zoom_and_pan_stay_in_bounds() {
+        let mut state = LightboxState::new(0);
+        state.zoom_by(0.5);
+        assert_eq!(state.zoom, 1.0);
+        state.zoom_by(100.0);
+        assert_eq!(state.zoom, MAX_ZOOM);
+
+        state.zoom = 2.0;
+        state.pan = vec2(500.0, -500.0);
+        state.clamp_pan(vec2(100.0, 50.0), vec2(100.0, 100.0));
+        assert_eq!(state.pan, vec2(50.0, 0.0));
+
+        state.zoom_by(0.0);
+        assert_eq!(state.pan, Vec2::ZERO);
+    }
+
+    #[test]
+    fn swipes_need_some_distance() {
+        assert_eq!(swipe_step(-80.0), 1);
+        assert_eq!(swipe_step(80.0), -1);
+        assert_eq!(swipe_step(20.0), 0);
+    }
+}
@@ -1,61 +1,135 @@
-use crate::{colors, ui, Damus};
-use nostrdb::Transaction;
+use crate::imgcache::ImageCache;
+use crate::profile::usable_name;
+use crate::ui;
+use crate::ui::anim;
 
 pub struct Mention<'a> {
-    app: &'a mut Damus,
-    txn: &'a Transaction,
+    img_cache: &'a mut ImageCache,
+    profiles: &'a dyn ui::ProfileSource,
     pk: &'a [u8; 32],
     size: f32,
 }
 
 impl<'a> Mention<'a> {
-    pub fn new(app: &'a mut Damus, txn: &'a Transaction, pk: &'a [u8; 32]) -> Self {
+    pub fn new(
+        img_cache: &'a mut ImageCache,
+        profiles: &'a dyn ui::ProfileSource,
+        pk: &'a [u8; 32],
+    ) -> Self {
         let size = 16.0;
-        Mention { app, txn, pk, size }
+        Mention {
+            img_cache,
+            profiles,
+            pk,
+            size,
+        }
     }
 
     pub fn size(mut self, size: f32) -> Self {
         self.size = size;
         self
     }
+
+    pub fn show(self, ui: &mut egui::Ui) -> MentionResponse {
+        mention_ui(self.img_cache, self.profiles, self.pk, ui, self.size)
+    }
+}
+
+/// What happened when a mention was shown
+pub struct MentionResponse {
+    pub response: egui::Response,
+    /// The mentioned pubkey, if the mention was clicked. Opening their
+    /// profile is up to the host.
+    pub clicked: Option<[u8; 32]>,
 }
 
 impl<'a> egui::Widget for Mention<'a> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        mention_ui(self.app, self.txn, self.pk, ui, self.size)
+        self.show(ui).response
     }
 }
 
+/// The name to mention someone by: their display name, or their name, or
+/// `None` if their profile hasn't loaded or neither is usable
+fn mention_name(profile: Option<&ui::ProfileData>) -> Option<&str> {
+    let profile = profile?;
+    [&profile.display_name, &profile.name]
+        .into_iter()
+        .find_map(|name| name.as_deref().and_then(usable_name))
+}
+
 fn mention_ui(
-    app: &mut Damus,
-    txn: &Transaction,
+    img_cache: &mut ImageCache,
+    profiles: &dyn ui::ProfileSource,
     pk: &[u8; 32],
     ui: &mut egui::Ui,
     size: f32,
-) -> egui::Response {
+) -> MentionResponse {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
-    ui.horizontal(|ui| {
-        let profile = app.ndb.get_profile_by_pubkey(txn, pk).ok();
-
-        let name: String =
-            if let Some(name) = profile.as_ref().and_then(crate::profile::get_profile_name) {
-                format!("@{}", name.username())
-            } else {
-                "??".to_string()
-            };
-
-        let resp = ui.add(egui::Label::new(
-            egui::RichText::new(name).color(colors::PURPLE).size(size),
-        ));
-
-        if let Some(rec) = profile.as_ref() {
-            resp.on_hover_ui_at_pointer(|ui| {
-                ui.set_max_width(300.0);
-                ui.add(ui::ProfilePreview::new(rec, &mut app.img_cache));
-            });
+    let resp = ui.horizontal(|ui| {
+        let theme = ui::theme::NotedeckTheme::get(ui.ctx());
+        let profile = profiles.get(pk);
+        if profile.is_none() {
+            ui::ProfileRequests::request(ui.ctx(), pk);
         }
-    })
-    .response
+        let name = mention_name(profile.as_ref());
+
+        // the short npub shows until the profile loads, then the name fades
+        // in over it. Names we have from the start don't fade.
+        let fade = anim::fade_in(
+            ui.ctx(),
+            egui::Id::new(("mention_fade", pk)),
+            name.is_some(),
+        );
+        let (text, color) = match name {
+            Some(name) => (name.to_owned(), theme.link.gamma_multiply(fade)),
+            None => (ui::keys::short_npub(pk), theme.link),
+        };
+
+        let resp = ui
+            .add(
+                egui::Label::new(
+                    egui::RichText::new(format!("@{}", text))
+                        .color(color)
+                        .size(size),
+                )
+                .sense(egui::Sense::click()),
+            )
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+        ui::ProfileHoverCard::new(pk, profiles).show(ui, &resp, img_cache);
+        resp.clicked()
+    });
+
+    MentionResponse {
+        clicked: resp.inner.then_some(*pk),
+        response: resp.response,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_names_fall_back_to_the_npub() {
+        let profile = |display_name: Option<&str>, name: Option<&str>| ui::ProfileData {
+            display_name: display_name.map(str::to_owned),
+            name: name.map(str::to_owned),
+            ..Default::default()
+        };
+
+        assert_eq!(mention_name(None), None);
+        assert_eq!(mention_name(Some(&profile(Some("  "), None))), None);
+        assert_eq!(
+            mention_name(Some(&profile(Some(" "), Some("jb55")))),
+            Some("jb55")
+        );
+        assert_eq!(
+            mention_name(Some(&profile(Some("Will"), Some("jb55")))),
+            Some("Will")
+        );
+    }
 }
@@ -1,17 +1,38 @@
 pub mod anim;
+pub mod direction;
+pub mod emoji;
+pub mod expandable;
+pub mod fmt;
+pub mod focus;
+pub mod i18n;
+pub mod keys;
+pub mod media;
 pub mod mention;
+pub mod nip19;
 pub mod note;
 pub mod preview;
 pub mod profile;
 pub mod relay;
+pub mod skeleton;
+pub mod snapshot;
+pub mod text;
+pub mod theme;
+pub mod time;
 pub mod username;
+pub mod virtual_list;
 
-pub use mention::Mention;
-pub use note::Note;
-pub use preview::{Preview, PreviewApp};
-pub use profile::{ProfilePic, ProfilePreview};
-pub use relay::RelayView;
-pub use username::Username;
+pub use emoji::CustomEmojis;
+pub use media::{Lightbox, LightboxResponse};
+pub use mention::{Mention, MentionResponse};
+pub use note::{Note, NoteAction, NoteResponse, NoteStats, PublishState};
+pub use preview::{preview_image_cache, Preview, PreviewApp};
+pub use profile::{
+    MemoryProfiles, NdbProfiles, ProfileData, ProfileHoverCard, ProfilePic, ProfilePicAction,
+    ProfilePicResponse, ProfilePreview, ProfileRequests, ProfileSource,
+};
+pub use relay::{RelayAction, RelayPermissions, RelayView};
+pub use username::{NameSource, Username};
+pub use virtual_list::VirtualList;
 
 use egui::Margin;
 
@@ -22,8 +43,23 @@ use egui::Margin;
 ///
 /// There is some precendent for this, it looks like there's a similar trait
 /// in the egui demo library.
+///
+/// Like widgets, views return a Response so that parents can react to
+/// interaction with them.
 pub trait View {
-    fn ui(&mut self, ui: &mut egui::Ui);
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response;
+
+    /// Whether the view is still waiting on something, like images, before
+    /// it has its final look
+    fn is_loading(&self) -> bool {
+        false
+    }
+}
+
+/// Describe an image for screen readers, in place of whatever its widget
+/// would be announced as
+pub fn alt_text(response: &egui::Response, alt: &str) {
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, alt));
 }
 
 pub fn padding<R>(
@@ -36,14 +72,56 @@ pub fn padding<R>(
         .show(ui, add_contents)
 }
 
-#[inline]
-pub fn is_mobile(_ctx: &egui::Context) -> bool {
-    #[cfg(any(target_os = "android", target_os = "ios"))]
-    {
-        true
+/// How much room we have to work with, from narrowest to widest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    Mobile,
+    Tablet,
+    Desktop,
+}
+
+impl LayoutMode {
+    /// Screens narrower than this (in points) get the mobile layout
+    pub const MOBILE_MAX_WIDTH: f32 = 600.0;
+
+    /// Screens narrower than this but wider than mobile get the tablet layout
+    pub const TABLET_MAX_WIDTH: f32 = 1024.0;
+
+    pub fn from_width(width: f32) -> Self {
+        if width < Self::MOBILE_MAX_WIDTH {
+            LayoutMode::Mobile
+        } else if width < Self::TABLET_MAX_WIDTH {
+            LayoutMode::Tablet
+        } else {
+            LayoutMode::Desktop
+        }
     }
-    #[cfg(not(any(target_os = "android", target_os = "ios")))]
-    {
-        false
+}
+
+/// The layout to use for the current screen size. Android and iOS are
+/// always mobile.
+pub fn layout_mode(ctx: &egui::Context) -> LayoutMode {
+    if cfg!(any(target_os = "android", target_os = "ios")) {
+        return LayoutMode::Mobile;
+    }
+
+    LayoutMode::from_width(ctx.screen_rect().width())
+}
+
+#[inline]
+pub fn is_mobile(ctx: &egui::Context) -> bool {
+    layout_mode(ctx) == LayoutMode::Mobile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_mode_breakpoints() {
+        assert_eq!(LayoutMode::from_width(400.0), LayoutMode::Mobile);
+        assert_eq!(LayoutMode::from_width(600.0), LayoutMode::Tablet);
+        assert_eq!(LayoutMode::from_width(800.0), LayoutMode::Tablet);
+        assert_eq!(LayoutMode::from_width(1024.0), LayoutMode::Desktop);
     }
 }
@@ -0,0 +1,257 @@
+use crate::{Error, Result};
+use nostr_sdk::nips::nip01::Coordinate;
+use nostr_sdk::nips::nip19::{FromBech32, Nip19, Nip19Event, Nip19Profile};
+use nostr_sdk::{EventId, Kind, PublicKey, ToBech32};
+use std::ops::Range;
+
+/// The prefixes of the bech32 strings [`decode`] understands
+const PREFIXES: [&str; 5] = ["npub1", "nprofile1", "note1", "nevent1", "naddr1"];
+
+/// Something a NIP-19 bech32 string refers to. Secret keys aren't
+/// entities anything in the ui should be decoding, so they're left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip19Entity {
+    /// An `npub`
+    Pubkey([u8; 32]),
+    /// An `nprofile`, a pubkey along with relays that have their notes
+    Profile {
+        pubkey: [u8; 32],
+        relays: Vec<String>,
+    },
+    /// A `note`
+    Note([u8; 32]),
+    /// An `nevent`, a note id along with hints for finding it
+    Event {
+        id: [u8; 32],
+        author: Option<[u8; 32]>,
+        relays: Vec<String>,
+    },
+    /// An `naddr`, the latest version of a replaceable note
+    Address {
+        kind: u64,
+        pubkey: [u8; 32],
+        /// What's in the note's `d` tag
+        identifier: String,
+        relays: Vec<String>,
+    },
+}
+
+impl Nip19Entity {
+    /// Whose profile this is, or who wrote it when that's known
+    pub fn pubkey(&self) -> Option<[u8; 32]> {
+        match self {
+            Nip19Entity::Pubkey(pubkey)
+            | Nip19Entity::Profile { pubkey, .. }
+            | Nip19Entity::Address { pubkey, .. } => Some(*pubkey),
+            Nip19Entity::Event { author, .. } => *author,
+            Nip19Entity::Note(_) => None,
+        }
+    }
+
+    /// The id of the note this refers to, unless it only points at a
+    /// profile or a replaceable note
+    pub fn note_id(&self) -> Option<[u8; 32]> {
+        match self {
+            Nip19Entity::Note(id) | Nip19Entity::Event { id, .. } => Some(*id),
+            Nip19Entity::Pubkey(_) | Nip19Entity::Profile { .. } | Nip19Entity::Address { .. } => {
+                None
+            }
+        }
+    }
+}
+
+fn invalid(s: &str, err: impl std::fmt::Display) -> Error {
+    Error::Generic(format!("invalid nip19 entity {}: {}", s, err))
+}
+
+fn unencodable(err: impl std::fmt::Display) -> Error {
+    Error::Generic(format!("couldn't encode nip19 entity: {}", err))
+}
+
+fn pubkey(pubkey: &[u8; 32]) -> Result<PublicKey> {
+    PublicKey::from_slice(pubkey).map_err(unencodable)
+}
+
+fn event_id(id: &[u8; 32]) -> Result<EventId> {
+    EventId::from_slice(id).map_err(unencodable)
+}
+
+/// Decode an `npub`, `nprofile`, `note`, `nevent` or `naddr`, with or
+/// without a leading "nostr:"
+pub fn decode(s: &str) -> Result<Nip19Entity> {
+    let bech32 = s.trim();
+    let bech32 = bech32.strip_prefix("nostr:").unwrap_or(bech32);
+
+    let entity = match Nip19::from_bech32(bech32).map_err(|err| invalid(s, err))? {
+        Nip19::Pubkey(pubkey) => Nip19Entity::Pubkey(pubkey.to_bytes()),
+        Nip19::Profile(profile) => Nip19Entity::Profile {
+            pubkey: profile.public_key.to_bytes(),
+            relays: profile.relays,
+        },
+        Nip19::EventId(id) => Nip19Entity::Note(id.to_bytes()),
+        Nip19::Event(event) => Nip19Entity::Event {
+            id: event.event_id.to_bytes(),
+            author: event.author.map(|author| author.to_bytes()),
+            relays: event.relays,
+        },
+        Nip19::Coordinate(coordinate) => Nip19Entity::Address {
+            kind: coordinate.kind.as_u64(),
+            pubkey: coordinate.public_key.to_bytes(),
+            identifier: coordinate.identifier,
+            relays: coordinate.relays,
+        },
+        _ => return Err(invalid(s, "not a public entity")),
+    };
+    Ok(entity)
+}
+
+/// The first entity written out in `text` without a "nostr:" in front,
+/// as where it is in `text` and what it decodes to. A word only counts if
+/// its checksum does, so things that happen to start with "npub1" aren't
+/// mistaken for one.
+pub fn find_bare(text: &str) -> Option<(Range<usize>, Nip19Entity)> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| PREFIXES.iter().any(|prefix| word.starts_with(prefix)))
+        .find_map(|word| {
+            let entity = decode(word).ok()?;
+            let start = word.as_ptr() as usize - text.as_ptr() as usize;
+            Some((start..start + word.len(), entity))
+        })
+}
+
+/// The bech32 string for `entity`, without a "nostr:" in front
+pub fn encode(entity: &Nip19Entity) -> Result<String> {
+    let encoded = match entity {
+        Nip19Entity::Pubkey(pk) => pubkey(pk)?.to_bech32(),
+        Nip19Entity::Profile { pubkey: pk, relays } => {
+            Nip19Profile::new(pubkey(pk)?, relays.iter().cloned()).to_bech32()
+        }
+        Nip19Entity::Note(id) => event_id(id)?.to_bech32(),
+        Nip19Entity::Event { id, author, relays } => {
+            let mut event = Nip19Event::new(event_id(id)?, relays.iter().cloned());
+            event.author = author.as_ref().map(pubkey).transpose()?;
+            event.to_bech32()
+        }
+        Nip19Entity::Address {
+            kind,
+            pubkey: pk,
+            identifier,
+            relays,
+        } => {
+            let mut coordinate =
+                Coordinate::new(Kind::from(*kind), pubkey(pk)?).identifier(identifier);
+            coordinate.relays = relays.clone();
+            coordinate.to_bech32()
+        }
+    };
+    encoded.map_err(unencodable)
+}
+
+/// Someone's `npub`
+pub fn npub(pubkey: &[u8; 32]) -> Result<String> {
+    encode(&Nip19Entity::Pubkey(*pubkey))
+}
+
+/// A note's id as an `nevent`, the form other clients share notes in
+pub fn nevent(id: &[u8; 32], relays: &[String]) -> Result<String> {
+    encode(&Nip19Entity::Event {
+        id: *id,
+        author: None,
+        relays: relays.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the vectors from NIP-19
+    const NPUB: &str = "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg";
+    const NPUB_HEX: &str = "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e";
+    const NPROFILE: &str = "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p";
+    const NPROFILE_HEX: &str = "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d";
+
+    const NOTE: &str = "note16j3utakj5xcvn68h566uf5lz7xstnjxhum66fv7z68s0n29hcm2s46xpre";
+    const NOTE_HEX: &str = "d4a3c5f6d2a1b0c9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5";
+
+    fn bytes(hex: &str) -> [u8; 32] {
+        hex::decode(hex).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn decodes_known_vectors() {
+        assert_eq!(decode(NPUB).unwrap(), Nip19Entity::Pubkey(bytes(NPUB_HEX)));
+        assert_eq!(
+            decode(&format!("nostr:{}", NPUB)).unwrap(),
+            decode(NPUB).unwrap()
+        );
+        assert_eq!(
+            decode(NPROFILE).unwrap(),
+            Nip19Entity::Profile {
+                pubkey: bytes(NPROFILE_HEX),
+                relays: vec![
+                    "wss://r.x.com".to_owned(),
+                    "wss://djbas.sadkb.com".to_owned()
+                ],
+            }
+        );
+        assert_eq!(decode(NOTE).unwrap(), Nip19Entity::Note(bytes(NOTE_HEX)));
+
+        assert_eq!(npub(&bytes(NPUB_HEX)).unwrap(), NPUB);
+        assert_eq!(encode(&decode(NPROFILE).unwrap()).unwrap(), NPROFILE);
+        assert_eq!(encode(&Nip19Entity::Note(bytes(NOTE_HEX))).unwrap(), NOTE);
+    }
+
+    #[test]
+    fn round_trips_events_and_addresses() {
+        let entities = [
+            Nip19Entity::Event {
+                id: bytes(NOTE_HEX),
+                author: Some(bytes(NPUB_HEX)),
+                relays: vec!["wss://relay.damus.io".to_owned()],
+            },
+            Nip19Entity::Event {
+                id: bytes(NOTE_HEX),
+                author: None,
+                relays: vec![],
+            },
+            Nip19Entity::Address {
+                kind: 30023,
+                pubkey: bytes(NPUB_HEX),
+                identifier: "long-form".to_owned(),
+                relays: vec!["wss://nos.lol".to_owned()],
+            },
+        ];
+        for entity in entities {
+            let encoded = encode(&entity).unwrap();
+            assert_eq!(decode(&encoded).unwrap(), entity, "{}", encoded);
+        }
+        assert!(nevent(&bytes(NOTE_HEX), &[])
+            .unwrap()
+            .starts_with("nevent1"));
+    }
+
+    #[test]
+    fn finds_bare_entities_in_text() {
+        let text = format!("thanks ({})! see {}.", NPUB, NOTE);
+        let (range, entity) = find_bare(&text).unwrap();
+        assert_eq!(&text[range.clone()], NPUB);
+        assert_eq!(entity, Nip19Entity::Pubkey(bytes(NPUB_HEX)));
+
+        let (range, entity) = find_bare(&text[range.end..]).unwrap();
+        assert_eq!(range.start, 7);
+        assert_eq!(entity, Nip19Entity::Note(bytes(NOTE_HEX)));
+
+        // the right prefix with a bad checksum is just text
+        let typo = NPUB.replace("vjptg", "vjptq");
+        assert_eq!(find_bare(&format!("gm {} gm", typo)), None);
+        assert_eq!(find_bare("note1 npub1abc nevent1"), None);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_decode() {
+        assert!(decode("npub1notbech32").is_err());
+        assert!(decode(NPUB_HEX).is_err());
+        assert!(decode("nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5").is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::fonts::NamedFontFamily;
+use crate::images::Priority;
+use crate::imgcache::ImageCache;
+use crate::ui::i18n::tr;
+use crate::ui::nip19::{self, Nip19Entity};
+use crate::ui::note::contents::{
+    cached_texture, fetch_content_image, tag_str, MediaTexture, MAX_EMBED_WIDTH,
+};
+use crate::ui::note::gallery::fit_within;
+use crate::ui::text::truncate_graphemes;
+use crate::ui::theme::NotedeckTheme;
+use egui::{vec2, CursorIcon, Image, Label, RichText, Sense};
+use nostrdb::{Filter, Ndb, Transaction};
+
+/// The kind of NIP-23 long-form posts
+pub const LONG_FORM_KIND: u64 = 30023;
+
+/// How tall an article's hero image is at most, in a card
+const HERO_HEIGHT: f32 = 160.0;
+
+/// How much of an article's summary a card shows
+const MAX_SUMMARY_GRAPHEMES: usize = 200;
+
+/// What a card shows of a NIP-23 article, from the article's tags
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    /// The url of the article's hero image
+    pub image: Option<String>,
+}
+
+impl Article {
+    /// Read an article from `(name, value)` tag pairs. The first of each
+    /// tag wins, and empty ones don't count.
+    pub fn from_tags<'a>(tags: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut article = Article::default();
+        for (name, value) in tags {
+            let field = match name {
+                "title" => &mut article.title,
+                "summary" => &mut article.summary,
+                "image" => &mut article.image,
+                _ => continue,
+            };
+            let value = value.trim();
+            if field.is_none() && !value.is_empty() {
+                *field = Some(value.to_owned());
+            }
+        }
+        article
+    }
+
+    pub fn from_note(note: &nostrdb::Note) -> Self {
+        Self::from_tags(
+            note.tags()
+                .iter()
+                .filter_map(|tag| Some((tag_str(&tag, 0)?, tag_str(&tag, 1)?))),
+        )
+    }
+
+    /// The newest version of the article at `address` that we have, if
+    /// it's an address of a long-form post
+    pub fn lookup(ndb: &Ndb, txn: &Transaction, address: &Nip19Entity) -> Option<Self> {
+        let Nip19Entity::Address {
+            kind,
+            pubkey,
+            identifier,
+            ..
+        } = address
+        else {
+            return None;
+        };
+
+        let filters = vec![Filter::new()
+            .kinds(vec![*kind])
+            .authors(vec![*pubkey])
+            .tags(vec![identifier.clone()], 'd')
+            .build()];
+        let results = ndb.query(txn, filters, 10).ok()?;
+        results
+            .iter()
+            .max_by_key(|result| result.note.created_at())
+            .map(|result| Self::from_note(&result.note))
+    }
+}
+
+/// Whether `bech32` is an `naddr` of a long-form post
+pub fn article_address(bech32: &str) -> Option<Nip19Entity> {
+    match nip19::decode(bech32).ok()? {
+        address @ Nip19Entity::Address {
+            kind: LONG_FORM_KIND,
+            ..
+        } => Some(address),
+        _ => None,
+    }
+}
+
+/// A card for an article a note links to, with its hero image, title and
+/// summary. When we don't have the article it's a chip saying there's a
+/// long-form post there instead.
+pub fn article_card(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    article: Option<&Article>,
+    naddr: &str,
+) -> egui::Response {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let theme = NotedeckTheme::get(ui.ctx());
+    let Some(article) = article else {
+        return ui
+            .add(
+                egui::Button::new(
+                    RichText::new(tr!(ui.ctx(), "📄 Long-form post")).color(theme.link),
+                )
+                .fill(theme.surface)
+                .rounding(egui::Rounding::same(10.0)),
+            )
+            .on_hover_cursor(CursorIcon::PointingHand);
+    };
+
+    let frame = egui::Frame::none()
+        .rounding(egui::Rounding::same(8.0))
+        .inner_margin(egui::Margin::same(8.0))
+        .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width().min(MAX_EMBED_WIDTH));
+            if let Some(image) = &article.image {
+                hero_image(ui, img_cache, image);
+            }
+            let title = match &article.title {
+                Some(title) => title.clone(),
+                None => tr!(ui.ctx(), "Untitled post").into_owned(),
+            };
+            ui.add(
+                Label::new(
+                    RichText::new(title)
+                        .size(16.0)
+                        .family(NamedFontFamily::Bold.as_family()),
+                )
+                .wrap(true),
+            );
+            if let Some(summary) = &article.summary {
+                ui.label(
+                    RichText::new(truncate_graphemes(summary, MAX_SUMMARY_GRAPHEMES))
+                        .small()
+                        .color(theme.muted_text),
+                );
+            }
+        });
+
+    ui.interact(
+        frame.response.rect,
+        ui.id().with(("article_card", naddr)),
+        Sense::click(),
+    )
+    .on_hover_cursor(CursorIcon::PointingHand)
+}
+
+fn hero_image(ui: &mut egui::Ui, img_cache: &mut ImageCache, url: &str) {
+    let width = ui.available_width();
+    fetch_content_image(ui, img_cache, url, Priority::Low);
+    match cached_texture(img_cache, url) {
+        MediaTexture::Loaded(texture) => {
+            let size = fit_within(texture.size_vec2(), vec2(width, HERO_HEIGHT));
+            let (rect, _) = ui.allocate_exact_size(vec2(width, size.y), Sense::hover());
+            Image::new(&texture)
+                .rounding(4.0)
+                .paint_at(ui, egui::Rect::from_center_size(rect.center(), size));
+        }
+        MediaTexture::Loading => {
+            let (rect, _) = ui.allocate_exact_size(vec2(width, HERO_HEIGHT), Sense::hover());
+            ui.painter()
+                .rect_filled(rect, 4.0, ui.visuals().faint_bg_color);
+        }
+        // no room is kept for an image that isn't coming
+        MediaTexture::Failed => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JB55: &str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+
+    #[test]
+    fn reads_article_tags() {
+        let article = Article::from_tags([
+            ("d", "my-post"),
+            ("title", "Why nostr"),
+            ("summary", "  "),
+            ("title", "A later title"),
+            ("image", "https://example.com/hero.png"),
+            ("summary", "It's simple"),
+        ]);
+        assert_eq!(
+            article,
+            Article {
+                title: Some("Why nostr".to_owned()),
+                summary: Some("It's simple".to_owned()),
+                image: Some("https://example.com/hero.png".to_owned()),
+            }
+        );
+        assert_eq!(Article::from_tags([("t", "nostr")]), Article::default());
+    }
+
+    #[test]
+    fn only_long_form_addresses_are_articles() {
+        let pubkey: [u8; 32] = hex::decode(JB55).unwrap().try_into().unwrap();
+        let address = |kind| {
+            nip19::encode(&Nip19Entity::Address {
+                kind,
+                pubkey,
+                identifier: "my-post".to_owned(),
+                relays: vec![],
+            })
+            .unwrap()
+        };
+        assert!(article_address(&address(LONG_FORM_KIND)).is_some());
+        assert!(article_address(&format!("nostr:{}", address(LONG_FORM_KIND))).is_some());
+        assert!(article_address(&address(30311)).is_none());
+        assert!(article_address(&nip19::npub(&pubkey).unwrap()).is_none());
+    }
+}
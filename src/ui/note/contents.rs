@@ -1,7 +1,22 @@
-use crate::ui::note::NoteOptions;
-use crate::{colors, ui, Damus};
-use egui::{Color32, Hyperlink, Image, RichText};
-use nostrdb::{BlockType, Mention, Note, NoteKey, Transaction};
+use crate::fonts::NamedFontFamily;
+use crate::images::{self, FetchOptions, Priority};
+use crate::imgcache::ImageCache;
+use crate::lightning::{self, Invoice};
+use crate::link_preview::{self, LinkCard};
+use crate::ui::emoji::{self, CustomEmojis, EmojiSpan};
+use crate::ui::i18n::tr;
+use crate::ui::nip19::{self, Nip19Entity};
+use crate::ui::note::article::{self, Article};
+use crate::ui::note::gallery;
+use crate::ui::note::markdown::{self, Chunk, Span};
+use crate::ui::note::{NoteOptions, MAX_QUOTE_DEPTH};
+use crate::ui::text::{self, truncate_graphemes};
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::NoteAction;
+use crate::ui::{anim, keys};
+use crate::{bidi, fonts, ui, Damus};
+use egui::{vec2, Color32, CursorIcon, Hyperlink, Image, Label, RichText, Sense, TextureHandle};
+use nostrdb::{BlockType, Mention, NdbStrVariant, Note, NoteKey, Tag, Transaction};
 use tracing::warn;
 
 pub struct NoteContents<'a> {
@@ -10,6 +25,10 @@ pub struct NoteContents<'a> {
     note: &'a Note<'a>,
     note_key: NoteKey,
     options: NoteOptions,
+    quote_depth: usize,
+    emojis: CustomEmojis,
+    image_priority: Priority,
+    max_media_height: f32,
 }
 
 impl<'a> NoteContents<'a> {
@@ -26,32 +45,70 @@ impl<'a> NoteContents<'a> {
             note,
             note_key,
             options,
+            quote_depth: 0,
+            emojis: CustomEmojis::from_note(note),
+            image_priority: Priority::default(),
+            max_media_height: f32::INFINITY,
         }
     }
+
+    /// How many quoted notes these contents are nested inside of
+    pub fn quote_depth(mut self, depth: usize) -> Self {
+        self.quote_depth = depth;
+        self
+    }
+
+    /// The custom emoji to show in place of `:shortcode:`s. Defaults to the
+    /// ones the note defines.
+    pub fn emojis(mut self, emojis: CustomEmojis) -> Self {
+        self.emojis = emojis;
+        self
+    }
+
+    /// How soon to download the note's images when lots are loading, eg.
+    /// [`Priority::High`] for the note the user is looking at
+    pub fn image_priority(mut self, priority: Priority) -> Self {
+        self.image_priority = priority;
+        self
+    }
+
+    /// The tallest an image is drawn before it's cropped, in points
+    pub fn max_media_height(mut self, height: f32) -> Self {
+        self.max_media_height = height;
+        self
+    }
+}
+
+/// What the user clicked in a note's contents
+#[derive(Debug, Default)]
+pub struct ContentsResponse {
+    /// A hashtag, without the leading #
+    pub clicked_hashtag: Option<String>,
+    pub action: NoteAction,
+}
+
+impl NoteContents<'_> {
+    pub fn show(self, ui: &mut egui::Ui) -> egui::InnerResponse<ContentsResponse> {
+        render_note_contents(ui, self)
+    }
 }
 
 impl egui::Widget for NoteContents<'_> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        render_note_contents(
-            ui,
-            self.damus,
-            self.txn,
-            self.note,
-            self.note_key,
-            self.options,
-        )
-        .response
+        self.show(ui).response
     }
 }
 
 /// Render an inline note preview with a border. These are used when
-/// notes are references within a note
+/// notes are references within a note. `quote_depth` is the depth of the
+/// quoted note itself.
 fn render_note_preview(
     ui: &mut egui::Ui,
     app: &mut Damus,
     txn: &Transaction,
     id: &[u8; 32],
     _id_str: &str,
+    quote_depth: usize,
 ) -> egui::Response {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
@@ -83,6 +140,7 @@ fn render_note_preview(
         //.fill(egui::Color32::BLACK.gamma_multiply(0.2))
         //
         .rounding(egui::Rounding::same(10.0))
+        .inner_margin(egui::Margin::same(8.0))
         .stroke(egui::Stroke::new(
             1.0,
             egui::Color32::from_rgb(0x2C, 0x2C, 0x2C),
@@ -91,109 +149,1223 @@ fn render_note_preview(
             ui.add(
                 ui::Note::new(app, &note)
                     .actionbar(false)
-                    .note_previews(false),
+                    .quote_depth(quote_depth),
             )
         })
         .response
 }
 
-fn render_note_contents(
+/// Render a note quoted by the note at `quote_depth`. Past
+/// [`MAX_QUOTE_DEPTH`] the quote is collapsed until the user expands it.
+fn render_quoted_note(
+    ui: &mut egui::Ui,
+    app: &mut Damus,
+    txn: &Transaction,
+    id: &[u8; 32],
+    id_str: &str,
+    quote_depth: usize,
+) {
+    let depth = quote_depth + 1;
+    if depth > MAX_QUOTE_DEPTH {
+        let expand_id = ui.id().with(("expand_quote", id));
+        let expanded = ui.data(|d| d.get_temp(expand_id)).unwrap_or(false);
+        if !expanded {
+            if ui.link(tr!(ui.ctx(), "show quoted note")).clicked() {
+                ui.data_mut(|d| d.insert_temp(expand_id, true));
+            }
+            return;
+        }
+    }
+
+    render_note_preview(ui, app, txn, id, id_str, depth);
+}
+
+/// NIP-12 hashtags are letters, numbers and underscores. A bare # isn't one.
+fn is_hashtag(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Split punctuation that ends a sentence off the end of a url, eg. the
+/// period in "check out https://damus.io." Closing parens are only split
+/// off when they don't have a matching open paren in the url, so wikipedia
+/// style links stay intact.
+pub(crate) fn split_trailing_punctuation(url: &str) -> (&str, &str) {
+    let mut end = url.len();
+    for (i, c) in url.char_indices().rev() {
+        let unbalanced_paren =
+            c == ')' && url[..end].matches('(').count() < url[..end].matches(')').count();
+        if matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"') || unbalanced_paren {
+            end = i;
+        } else {
+            break;
+        }
+    }
+    url.split_at(end)
+}
+
+/// Whether a url points at an image we can embed, going by its extension
+fn is_image_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    [".jpg", ".jpeg", ".png", ".gif", ".webp"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// The widest an embedded image is drawn, in points
+pub(crate) const MAX_EMBED_WIDTH: f32 = 400.0;
+
+/// Space reserved for an embed before we know its real size
+const PLACEHOLDER_ASPECT_RATIO: f32 = 0.5;
+
+/// Whether a url points at a video, going by its extension
+fn is_video_url(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    [".mp4", ".webm", ".mov"]
+        .iter()
+        .any(|ext| path.ends_with(ext))
+}
+
+/// Space reserved for a video's thumbnail
+const VIDEO_ASPECT_RATIO: f32 = 9.0 / 16.0;
+
+/// A content image in the cache, as far as it's gotten
+pub(crate) enum MediaTexture {
+    Loading,
+    Loaded(TextureHandle),
+    Failed,
+}
+
+fn content_key(url: &str) -> String {
+    format!("content:{}", url)
+}
+
+/// Where the quick, low resolution preview of a large image is cached
+fn preview_key(url: &str) -> String {
+    format!("preview:{}", url)
+}
+
+fn cached(img_cache: &mut ImageCache, key: &str) -> MediaTexture {
+    match img_cache.ready(key) {
+        Some(Ok(texture)) => MediaTexture::Loaded(texture.clone()),
+        Some(Err(_err)) => MediaTexture::Failed,
+        None => MediaTexture::Loading,
+    }
+}
+
+pub(crate) fn cached_texture(img_cache: &mut ImageCache, url: &str) -> MediaTexture {
+    cached(img_cache, &content_key(url))
+}
+
+fn cached_preview(img_cache: &mut ImageCache, url: &str) -> Option<TextureHandle> {
+    match cached(img_cache, &preview_key(url)) {
+        MediaTexture::Loaded(texture) => Some(texture),
+        MediaTexture::Loading | MediaTexture::Failed => None,
+    }
+}
+
+/// Drop an image's preview once it's no longer needed, whatever state it
+/// got to
+fn forget_preview(img_cache: &mut ImageCache, url: &str) {
+    let key = preview_key(url);
+    if img_cache.contains(&key) {
+        img_cache.remove(&key);
+    }
+}
+
+/// Start fetching an image in a note's content, unless we already have
+pub(crate) fn fetch_content_image(
+    ui: &egui::Ui,
+    img_cache: &mut ImageCache,
+    url: &str,
+    priority: Priority,
+) {
+    let key = content_key(url);
+    if img_cache.contains(&key) {
+        return;
+    }
+
+    let img_size = (MAX_EMBED_WIDTH * ui.ctx().pixels_per_point()) as u32;
+    let options = FetchOptions {
+        size: img_size,
+        priority,
+        ..Default::default()
+    };
+    let (preview, fetch) = images::fetch_img_progressive(img_cache, ui.ctx(), url, options);
+    img_cache.insert_fetch(key, fetch);
+    if let Some(preview) = preview {
+        img_cache.insert(preview_key(url), preview);
+    }
+}
+
+/// Scale a texture's size in pixels to fit `width` points, without ever
+/// drawing it larger than it is
+fn fit_width(ui: &egui::Ui, texture: &TextureHandle, width: f32) -> egui::Vec2 {
+    let mut size = texture.size_vec2() / ui.ctx().pixels_per_point();
+    if size.x > width {
+        size *= width / size.x;
+    }
+    size
+}
+
+/// The size an image of `dims` pixels will be drawn at once it's loaded
+fn expected_size(ui: &egui::Ui, dims: [u32; 2], width: f32) -> egui::Vec2 {
+    let ppp = ui.ctx().pixels_per_point();
+    let [w, h] = images::fitted_size(dims, (MAX_EMBED_WIDTH * ppp) as u32);
+    let mut size = vec2(w as f32, h as f32) / ppp;
+    if size.x > width {
+        size *= width / size.x;
+    }
+    size
+}
+
+/// The part of an image drawn `size` points big that's shown when it's
+/// capped to `max_height`, as the size it's drawn at and the uv rect of
+/// the texture to draw. Too tall images keep their middle.
+fn crop_to_height(size: egui::Vec2, max_height: f32) -> (egui::Vec2, egui::Rect) {
+    let full = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    if size.y <= max_height {
+        return (size, full);
+    }
+    let shown = max_height / size.y;
+    let top = (1.0 - shown) / 2.0;
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, top), egui::pos2(1.0, top + shown));
+    (vec2(size.x, max_height), uv)
+}
+
+/// Draw a prompt over the bottom of a cropped image saying there's more
+fn expand_hint(ui: &egui::Ui, rect: egui::Rect) {
+    let galley = ui.painter().layout_no_wrap(
+        tr!(ui.ctx(), "⤢ Tap to expand").into_owned(),
+        egui::FontId::proportional(12.0),
+        Color32::WHITE,
+    );
+    let pill = egui::Rect::from_center_size(
+        egui::pos2(rect.center().x, rect.bottom() - galley.size().y - 8.0),
+        galley.size() + vec2(16.0, 8.0),
+    );
+    let painter = ui.painter();
+    painter.rect_filled(pill, pill.height() / 2.0, Color32::from_black_alpha(160));
+    painter.galley(pill.center() - galley.size() / 2.0, galley, Color32::WHITE);
+}
+
+/// Draw an image embedded in a note, scaled down to fit the available width
+/// and cropped to `max_height`. The image isn't fetched until its
+/// placeholder scrolls into view. When we know its `dims` up front, eg.
+/// from an `imeta` tag, the placeholder is the size the image will be, so
+/// nothing moves when it loads.
+fn render_media(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    url: &str,
+    dims: Option<[u32; 2]>,
+    priority: Priority,
+    max_height: f32,
+) -> egui::Response {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let width = ui.available_width().min(MAX_EMBED_WIDTH);
+    let fade_id = egui::Id::new(("media_fade", url));
+
+    let texture = match cached_texture(img_cache, url) {
+        MediaTexture::Loaded(texture) => texture,
+
+        // fall back to a plain link so the url isn't lost
+        MediaTexture::Failed => {
+            forget_preview(img_cache, url);
+            return ui.add(Hyperlink::from_label_and_url(
+                RichText::new(url).color(NotedeckTheme::get(ui.ctx()).link),
+                url,
+            ));
+        }
+
+        MediaTexture::Loading => {
+            // a preview is stretched to the width the full image will
+            // likely have, so it doesn't jump when it swaps in
+            let preview = cached_preview(img_cache, url);
+            let size = match (dims, &preview) {
+                (Some(dims), _) => expected_size(ui, dims, width),
+                (None, Some(preview)) => {
+                    let preview_size = preview.size_vec2();
+                    vec2(width, width * preview_size.y / preview_size.x)
+                }
+                (None, None) => vec2(width, width * PLACEHOLDER_ASPECT_RATIO),
+            };
+            let (size, uv) = crop_to_height(size, max_height);
+            let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+            if !ui.is_rect_visible(rect) {
+                return response;
+            }
+
+            fetch_content_image(ui, img_cache, url, priority);
+            anim::fade_in(ui.ctx(), fade_id, false);
+            match preview {
+                Some(preview) => Image::new(&preview).uv(uv).rounding(8.0).paint_at(ui, rect),
+                None => {
+                    ui.painter()
+                        .rect_filled(rect, 8.0, ui.visuals().faint_bg_color);
+                    egui::Spinner::new().paint_at(ui, rect.shrink2(vec2(0.0, rect.height() / 3.0)));
+                }
+            }
+            return response;
+        }
+    };
+
+    let (size, uv) = crop_to_height(fit_width(ui, &texture, width), max_height);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+
+    // cross-fade from the preview, then drop it whether or not it ever
+    // loaded
+    let opacity = anim::fade_in(ui.ctx(), fade_id, true);
+    if opacity < 1.0 {
+        if let Some(preview) = cached_preview(img_cache, url) {
+            Image::new(&preview).uv(uv).rounding(8.0).paint_at(ui, rect);
+        }
+    } else {
+        forget_preview(img_cache, url);
+    }
+    Image::new(&texture)
+        .rounding(8.0)
+        .uv(uv)
+        .tint(Color32::WHITE.gamma_multiply(opacity))
+        .paint_at(ui, rect);
+    if uv.height() < 1.0 {
+        expand_hint(ui, rect);
+    }
+    response
+}
+
+/// Draw a video embedded in a note as its thumbnail, or a blank frame when
+/// it doesn't have one, with a play button over it. We don't play videos
+/// ourselves, so clicking it is left to the caller.
+fn render_video(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    thumbnail: Option<&str>,
+    priority: Priority,
+) -> egui::Response {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let width = ui.available_width().min(MAX_EMBED_WIDTH);
+    let size = vec2(width, width * VIDEO_ASPECT_RATIO);
+    let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+
+    ui.painter()
+        .rect_filled(rect, 8.0, ui.visuals().extreme_bg_color);
+
+    if let Some(url) = thumbnail {
+        fetch_content_image(ui, img_cache, url, priority);
+        if let MediaTexture::Loaded(texture) = cached_texture(img_cache, url) {
+            // letterbox the thumbnail rather than stretching it
+            let texture_size = texture.size_vec2();
+            let scale = (size.x / texture_size.x).min(size.y / texture_size.y);
+            let thumb_rect = egui::Rect::from_center_size(rect.center(), texture_size * scale);
+            Image::new(&texture).rounding(8.0).paint_at(ui, thumb_rect);
+        }
+    }
+
+    let center = rect.center();
+    let painter = ui.painter();
+    painter.circle_filled(center, 24.0, Color32::from_black_alpha(160));
+    painter.add(egui::Shape::convex_polygon(
+        vec![
+            center + vec2(-7.0, -11.0),
+            center + vec2(-7.0, 11.0),
+            center + vec2(12.0, 0.0),
+        ],
+        Color32::WHITE,
+        egui::Stroke::NONE,
+    ));
+
+    response.on_hover_cursor(CursorIcon::PointingHand)
+}
+
+/// How big a link card's thumbnail is, in points
+const LINK_THUMBNAIL_SIZE: f32 = 64.0;
+
+fn render_link_thumbnail(ui: &mut egui::Ui, img_cache: &mut ImageCache, url: &str) {
+    let size = vec2(LINK_THUMBNAIL_SIZE, LINK_THUMBNAIL_SIZE);
+    let (rect, _response) = ui.allocate_exact_size(size, Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return;
+    }
+
+    fetch_content_image(ui, img_cache, url, Priority::Low);
+    match cached_texture(img_cache, url) {
+        MediaTexture::Loaded(texture) => {
+            let texture_size = texture.size_vec2();
+            let scale = (size.x / texture_size.x).min(size.y / texture_size.y);
+            let thumb_rect = egui::Rect::from_center_size(rect.center(), texture_size * scale);
+            Image::new(&texture).rounding(4.0).paint_at(ui, thumb_rect);
+        }
+        MediaTexture::Loading | MediaTexture::Failed => {
+            ui.painter()
+                .rect_filled(rect, 4.0, ui.visuals().faint_bg_color);
+        }
+    }
+}
+
+/// An OpenGraph card for a link in a note. While the page is loading, or
+/// when it doesn't describe itself, this is just the link's domain.
+fn render_link_card(ui: &mut egui::Ui, app: &mut Damus, url: &str) -> egui::Response {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let card: Option<&LinkCard> = match app.link_previews.get(ui.ctx(), url) {
+        Some(Ok(card)) if card.has_metadata() => Some(card),
+        _ => None,
+    };
+    let img_cache = &mut app.img_cache;
+
+    let frame = egui::Frame::none()
+        .rounding(egui::Rounding::same(8.0))
+        .inner_margin(egui::Margin::same(8.0))
+        .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width().min(MAX_EMBED_WIDTH));
+            ui.horizontal(|ui| {
+                if let Some(image) = card.and_then(|card| card.image.as_deref()) {
+                    render_link_thumbnail(ui, img_cache, image);
+                }
+
+                ui.vertical(|ui| {
+                    if let Some(title) = card.and_then(|card| card.title.as_deref()) {
+                        ui.add(
+                            Label::new(
+                                RichText::new(title).family(NamedFontFamily::Bold.as_family()),
+                            )
+                            .truncate(true),
+                        );
+                    }
+                    if let Some(description) = card.and_then(|card| card.description.as_deref()) {
+                        ui.add(Label::new(RichText::new(description).small()).truncate(true));
+                    }
+                    ui.label(
+                        RichText::new(link_preview::domain(url))
+                            .small()
+                            .color(NotedeckTheme::get(ui.ctx()).muted_text),
+                    );
+                });
+            });
+        });
+
+    let response = ui
+        .interact(
+            frame.response.rect,
+            ui.id().with(("link_card", url)),
+            Sense::click(),
+        )
+        .on_hover_cursor(CursorIcon::PointingHand)
+        .on_hover_text(url);
+    if response.clicked() {
+        ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+    }
+    response
+}
+
+/// The value of `key` in a NIP-92 `imeta` tag for `url`, from the tag's
+/// "key value" fields
+fn imeta_field<'a>(
+    fields: impl IntoIterator<Item = &'a str>,
+    url: &str,
+    key: &str,
+) -> Option<&'a str> {
+    let mut tag_url = None;
+    let mut value = None;
+    for field in fields {
+        match field.split_once(' ') {
+            Some(("url", field_url)) => tag_url = Some(field_url),
+            Some((field_key, field_value)) if field_key == key => value = Some(field_value),
+            _ => {}
+        }
+    }
+
+    if tag_url? == url {
+        value
+    } else {
+        None
+    }
+}
+
+/// The `image` a NIP-92 `imeta` tag gives for `url`
+fn imeta_image<'a>(fields: impl IntoIterator<Item = &'a str>, url: &str) -> Option<&'a str> {
+    imeta_field(fields, url, "image")
+}
+
+pub(crate) fn tag_str<'a>(tag: &Tag<'a>, ind: u16) -> Option<&'a str> {
+    match tag.get(ind)?.variant() {
+        NdbStrVariant::Str(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// The value of `key` in the note's `imeta` tag for `url`
+fn note_imeta(note: &Note, url: &str, key: &str) -> Option<String> {
+    note.tags().iter().find_map(|tag| {
+        if tag_str(&tag, 0)? != "imeta" {
+            return None;
+        }
+        let fields = (1..tag.count()).filter_map(|i| tag_str(&tag, i));
+        imeta_field(fields, url, key).map(str::to_owned)
+    })
+}
+
+/// A thumbnail for a video in the note, from its `imeta` tags
+fn video_thumbnail(note: &Note, url: &str) -> Option<String> {
+    note_imeta(note, url, "image")
+}
+
+/// A description of media in the note for screen readers, from the `alt`
+/// or `summary` in its `imeta` tag, or just what kind of media it is
+fn media_alt(note: &Note, url: &str, kind: &str) -> String {
+    note_imeta(note, url, "alt")
+        .or_else(|| note_imeta(note, url, "summary"))
+        .filter(|alt| !alt.trim().is_empty())
+        .unwrap_or_else(|| kind.to_owned())
+}
+
+/// How big an image in the note is, in pixels, if its `imeta` tags say
+fn image_dims(note: &Note, url: &str) -> Option<[u32; 2]> {
+    note_imeta(note, url, "dim").and_then(|dim| images::parse_dim(&dim))
+}
+
+/// Lay out text containing right to left script, one run at a time so that
+/// wrapping and word order come out right
+fn render_bidi_text(ui: &mut egui::Ui, text: &str, rtl: bool) {
+    for run in bidi::runs(text, rtl) {
+        match run {
+            bidi::TextRun::Text(text) => {
+                ui.label(text.as_ref());
+            }
+            bidi::TextRun::Break => ui.end_row(),
+        }
+    }
+}
+
+/// A url in the note's text. This is a label rather than a `Hyperlink` so
+/// it can be part of a text selection, which hyperlinks can't.
+fn link_label(ui: &mut egui::Ui, url: &str) -> egui::Response {
+    let resp = ui
+        .add(
+            Label::new(RichText::new(url).color(NotedeckTheme::get(ui.ctx()).link))
+                .sense(Sense::click()),
+        )
+        .on_hover_cursor(CursorIcon::PointingHand)
+        .on_hover_text(url);
+    if resp.clicked() || resp.middle_clicked() {
+        ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+    }
+    resp
+}
+
+/// The most of an invoice's description we show in its chip
+const MAX_INVOICE_DESCRIPTION: usize = 32;
+
+/// What a chip for an invoice or LNURL says, instead of the string itself
+fn lightning_label(ctx: &egui::Context, payment: &str) -> String {
+    if lightning::is_lnurl(payment) {
+        // LNURLs are urls, so they're best known by their host
+        let host = lightning::decode_lnurl(payment).and_then(|url| {
+            let rest = url.split_once("://")?.1;
+            rest.split(['/', '?']).next().map(str::to_owned)
+        });
+        return match host {
+            Some(host) => tr!(ctx, "⚡ Pay {}", host),
+            None => tr!(ctx, "⚡ LNURL").into_owned(),
+        };
+    }
+
+    match lightning::decode_invoice(payment) {
+        Some(Invoice {
+            msat: Some(msat),
+            description,
+        }) => {
+            let sats = tr!(ctx, "⚡ Pay {} sats", msat / 1000);
+            match description.filter(|d| !d.is_empty()) {
+                Some(d) => format!(
+                    "{} · {}",
+                    sats,
+                    truncate_graphemes(&d, MAX_INVOICE_DESCRIPTION)
+                ),
+                None => sats,
+            }
+        }
+        _ => tr!(ctx, "⚡ Lightning invoice").into_owned(),
+    }
+}
+
+/// An invoice or LNURL in the note, as a compact chip
+fn lightning_chip(ui: &mut egui::Ui, payment: &str) -> egui::Response {
+    let theme = NotedeckTheme::get(ui.ctx());
+    ui.add(
+        egui::Button::new(RichText::new(lightning_label(ui.ctx(), payment)).color(theme.accent))
+            .fill(theme.surface)
+            .rounding(egui::Rounding::same(10.0)),
+    )
+    .on_hover_cursor(CursorIcon::PointingHand)
+}
+
+/// The byte range of the first invoice or LNURL in some text
+fn find_payment(text: &str) -> Option<(usize, usize)> {
+    text.split(char::is_whitespace).find_map(|word| {
+        let (word, _) = split_trailing_punctuation(word);
+        if !lightning::is_invoice(word) && !lightning::is_lnurl(word) {
+            return None;
+        }
+        let start = word.as_ptr() as usize - text.as_ptr() as usize;
+        Some((start, start + word.len()))
+    })
+}
+
+/// What a note's text refers to that's shown below it rather than inline
+#[derive(Default)]
+struct Embeds<'a> {
+    /// Quoted notes' ids, and how they were written in the note
+    notes: Vec<([u8; 32], &'a str)>,
+    /// Long-form posts, if we have them, and their `naddr`s
+    articles: Vec<(Option<Article>, &'a str)>,
+}
+
+/// Lay out note text with `render`, except for NIP-19 entities written out
+/// without a "nostr:" in front, which are shown the same as the ones with
+/// it. Returns whose mention was clicked, if anyone's.
+fn render_text_with_entities<'a>(
     ui: &mut egui::Ui,
     damus: &mut Damus,
     txn: &Transaction,
-    note: &Note,
-    note_key: NoteKey,
     options: NoteOptions,
-) -> egui::InnerResponse<()> {
+    text: &'a str,
+    embeds: &mut Embeds<'a>,
+    mut render: impl FnMut(&mut egui::Ui, &'a str),
+) -> Option<[u8; 32]> {
+    let mut clicked = None;
+    let mut rest = text;
+    while let Some((range, entity)) = nip19::find_bare(rest) {
+        if range.start > 0 {
+            render(ui, &rest[..range.start]);
+        }
+        let bech32 = &rest[range.clone()];
+        rest = &rest[range.end..];
+
+        let previews = options.has_note_previews();
+        match entity {
+            Nip19Entity::Pubkey(pubkey) | Nip19Entity::Profile { pubkey, .. } => {
+                let profiles = ui::NdbProfiles::new(&damus.ndb, txn);
+                let resp = ui::Mention::new(&mut damus.img_cache, &profiles, &pubkey).show(ui);
+                clicked = clicked.or(resp.clicked);
+            }
+            Nip19Entity::Note(id) | Nip19Entity::Event { id, .. } if previews => {
+                embeds.notes.push((id, bech32));
+            }
+            address @ Nip19Entity::Address {
+                kind: article::LONG_FORM_KIND,
+                ..
+            } if previews => {
+                let found = Article::lookup(&damus.ndb, txn, &address);
+                embeds.articles.push((found, bech32));
+            }
+            _ => {
+                ui.colored_label(
+                    NotedeckTheme::get(ui.ctx()).link,
+                    format!(
+                        "@{}",
+                        keys::shorten_bech32(bech32, keys::PREFIX_LEN, keys::SUFFIX_LEN)
+                    ),
+                );
+            }
+        }
+    }
+    if !rest.is_empty() {
+        render(ui, rest);
+    }
+    clicked
+}
+
+/// Lay out note text with `render`, except for invoices and LNURLs, which
+/// are shown as chips. Returns the one that was clicked, if any.
+fn render_text_with_payments(
+    ui: &mut egui::Ui,
+    text: &str,
+    rtl: bool,
+    render: fn(&mut egui::Ui, &str, bool),
+) -> Option<String> {
+    let mut clicked = None;
+    let mut rest = text;
+    while let Some((start, end)) = find_payment(rest) {
+        if start > 0 {
+            render(ui, &rest[..start], rtl);
+        }
+        let payment = &rest[start..end];
+        if lightning_chip(ui, payment).clicked() {
+            clicked = Some(lightning::strip_lightning_scheme(payment).to_owned());
+        }
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        render(ui, rest, rtl);
+    }
+    clicked
+}
+
+/// Lay out a piece of plain note text
+fn render_text(ui: &mut egui::Ui, text: &str, rtl: bool) {
+    if rtl || bidi::has_rtl(text) {
+        render_bidi_text(ui, text, rtl);
+    } else {
+        ui.label(text);
+    }
+}
+
+/// Lay out note text with its inline markdown styled
+fn render_markdown_text(ui: &mut egui::Ui, text: &str, rtl: bool) {
+    for span in markdown::inline_spans(text) {
+        match span {
+            Span::Plain(text) => render_text(ui, text, rtl),
+            Span::Bold(text) => {
+                ui.label(RichText::new(text).family(NamedFontFamily::Bold.as_family()));
+            }
+            Span::Italic(text) => {
+                ui.label(RichText::new(text).italics());
+            }
+            Span::Code(text) => {
+                ui.label(RichText::new(text).code());
+            }
+        }
+    }
+}
+
+/// Code block text, highlighted when we know its language
+#[cfg_attr(not(feature = "syntax-highlighting"), allow(unused_variables))]
+fn code_text(ui: &egui::Ui, code: &str, lang: Option<&str>) -> egui::WidgetText {
+    #[cfg(feature = "syntax-highlighting")]
+    if let Some(lang) = lang {
+        use egui_extras::syntax_highlighting::{highlight, CodeTheme};
+        // egui_extras caches the layout by theme, code and language, so
+        // this is only highlighted once. Unknown languages come back plain.
+        let theme = CodeTheme::from_memory(ui.ctx());
+        return highlight(ui.ctx(), &theme, code, lang).into();
+    }
+
+    RichText::new(code).monospace().into()
+}
+
+/// A fenced code block on its own row, scrolling sideways instead of
+/// wrapping so indentation survives
+fn render_code_block(ui: &mut egui::Ui, id: impl std::hash::Hash, code: &str, lang: Option<&str>) {
+    ui.end_row();
+    egui::Frame::none()
+        .rounding(egui::Rounding::same(4.0))
+        .stroke(ui.visuals().widgets.noninteractive.bg_stroke)
+        .fill(ui.visuals().extreme_bg_color)
+        .show(ui, |ui| {
+            ui::padding(8.0, ui, |ui| {
+                egui::ScrollArea::horizontal().id_source(id).show(ui, |ui| {
+                    ui.add(Label::new(code_text(ui, code.trim_matches('\n'), lang)).wrap(false));
+                });
+            });
+        });
+    ui.end_row();
+}
+
+/// Notes of more emoji than this are shown at the normal size
+pub const MAX_JUMBO_EMOJI: usize = 7;
+
+/// How much bigger a note of `count` emoji and nothing else is drawn, if
+/// it's few enough to be drawn bigger at all
+fn jumbo_scale(count: usize) -> Option<f32> {
+    match count {
+        0 => None,
+        1..=3 => Some(3.0),
+        _ if count <= MAX_JUMBO_EMOJI => Some(2.0),
+        _ => None,
+    }
+}
+
+fn render_note_contents(
+    ui: &mut egui::Ui,
+    contents: NoteContents<'_>,
+) -> egui::InnerResponse<ContentsResponse> {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
-    let images: Vec<String> = vec![];
-    let mut inline_note: Option<(&[u8; 32], &str)> = None;
+    let NoteContents {
+        damus,
+        txn,
+        note,
+        note_key,
+        options,
+        quote_depth,
+        emojis,
+        image_priority,
+        max_media_height,
+    } = contents;
+
+    // a few emoji on their own are shown big, like other clients do
+    if let Some(scale) = text::emoji_count(note.content()).and_then(jumbo_scale) {
+        let size = egui::TextStyle::Body.resolve(ui.style()).size * scale;
+        let resp = ui.add(
+            Label::new(RichText::new(note.content().trim()).size(size))
+                .selectable(options.has_selectable_text()),
+        );
+        return egui::InnerResponse::new(ContentsResponse::default(), resp);
+    }
+
+    let mut media: Vec<String> = vec![];
+    let mut card_link: Option<String> = None;
+    let mut embeds = Embeds::default();
 
-    let resp = ui.horizontal_wrapped(|ui| {
+    let rtl = bidi::is_rtl(note.content());
+    let add_contents = |ui: &mut egui::Ui| {
         let blocks = if let Ok(blocks) = damus.ndb.get_blocks_by_key(txn, note_key) {
             blocks
         } else {
             warn!("missing note content blocks? '{}'", note.content());
             ui.weak(note.content());
-            return;
+            return ContentsResponse::default();
         };
 
+        let theme = NotedeckTheme::get(ui.ctx());
         ui.spacing_mut().item_spacing.x = 0.0;
+        // egui selects across every selectable label in the order they were
+        // added, so a selection can run from plain text through a mention
+        // or link and out the other side, copying what they display.
+        // Those tokens sense clicks as well as drags, so pressing and
+        // releasing on one without moving still opens it.
+        ui.style_mut().interaction.selectable_labels = options.has_selectable_text();
+        let mut clicked_hashtag = None;
+        let mut paid = None;
+        let mut mentioned = None;
+
+        let mut fences = markdown::Fences::default();
+        let mut code = String::new();
+        let mut lang: Option<String> = None;
+        let mut code_blocks = 0;
 
         for block in blocks.iter(note) {
+            // everything inside a code block is shown as written
+            if options.has_markdown() && fences.in_code() && block.blocktype() != BlockType::Text {
+                if block.blocktype() == BlockType::Hashtag {
+                    code.push('#');
+                }
+                code.push_str(block.as_str());
+                continue;
+            }
+
             match block.blocktype() {
                 BlockType::MentionBech32 => match block.as_mention().unwrap() {
                     Mention::Profile(profile) => {
-                        ui.add(ui::Mention::new(damus, txn, profile.pubkey()));
+                        let profiles = ui::NdbProfiles::new(&damus.ndb, txn);
+                        let resp =
+                            ui::Mention::new(&mut damus.img_cache, &profiles, profile.pubkey())
+                                .show(ui);
+                        mentioned = mentioned.or(resp.clicked);
                     }
 
                     Mention::Pubkey(npub) => {
-                        ui.add(ui::Mention::new(damus, txn, npub.pubkey()));
+                        let profiles = ui::NdbProfiles::new(&damus.ndb, txn);
+                        let resp = ui::Mention::new(&mut damus.img_cache, &profiles, npub.pubkey())
+                            .show(ui);
+                        mentioned = mentioned.or(resp.clicked);
                     }
 
                     Mention::Note(note) if options.has_note_previews() => {
-                        inline_note = Some((note.id(), block.as_str()));
+                        embeds.notes.push((*note.id(), block.as_str()));
                     }
 
                     Mention::Event(note) if options.has_note_previews() => {
-                        inline_note = Some((note.id(), block.as_str()));
+                        embeds.notes.push((*note.id(), block.as_str()));
                     }
 
+                    // anything we can't resolve to a profile, besides articles
                     _ => {
-                        ui.colored_label(colors::PURPLE, format!("@{}", &block.as_str()[4..16]));
+                        let naddr = block.as_str();
+                        let naddr = naddr.strip_prefix("nostr:").unwrap_or(naddr);
+                        if let Some(address) =
+                            article::article_address(naddr).filter(|_| options.has_note_previews())
+                        {
+                            let found = Article::lookup(&damus.ndb, txn, &address);
+                            embeds.articles.push((found, naddr));
+                            continue;
+                        }
+
+                        ui.colored_label(
+                            theme.link,
+                            format!(
+                                "@{}",
+                                keys::shorten_bech32(
+                                    block.as_str(),
+                                    keys::PREFIX_LEN,
+                                    keys::SUFFIX_LEN
+                                )
+                            ),
+                        );
                     }
                 },
 
                 BlockType::Hashtag => {
                     #[cfg(feature = "profiling")]
                     puffin::profile_scope!("hashtag contents");
-                    ui.colored_label(colors::PURPLE, format!("#{}", block.as_str()));
+                    let tag = block.as_str();
+                    if is_hashtag(tag) {
+                        let resp = ui
+                            .add(
+                                Label::new(RichText::new(format!("#{}", tag)).color(theme.link))
+                                    .sense(Sense::click()),
+                            )
+                            .on_hover_cursor(CursorIcon::PointingHand);
+                        if resp.clicked() {
+                            clicked_hashtag = Some(tag.to_owned());
+                        }
+                    } else {
+                        ui.label(format!("#{}", tag));
+                    }
                 }
 
                 BlockType::Url => {
-                    /*
-                    let url = block.as_str().to_lowercase();
-                    if url.ends_with("png") || url.ends_with("jpg") {
-                        images.push(url);
-                    } else {
-                    */
                     #[cfg(feature = "profiling")]
                     puffin::profile_scope!("url contents");
-                    ui.add(Hyperlink::from_label_and_url(
-                        RichText::new(block.as_str()).color(colors::PURPLE),
-                        block.as_str(),
-                    ));
-                    //}
+                    let (url, trailing) = split_trailing_punctuation(block.as_str());
+                    if options.has_media() && (is_image_url(url) || is_video_url(url)) {
+                        // embedded below the text instead
+                        media.push(url.to_owned());
+                    } else {
+                        if options.has_link_previews() && card_link.is_none() {
+                            card_link = Some(url.to_owned());
+                        }
+                        link_label(ui, url);
+                    }
+                    if !trailing.is_empty() {
+                        ui.label(trailing);
+                    }
                 }
 
                 BlockType::Text => {
                     #[cfg(feature = "profiling")]
                     puffin::profile_scope!("text contents");
-                    ui.label(block.as_str());
+                    fonts::load_fonts_for(ui.ctx(), block.as_str());
+                    for span in emojis.spans(block.as_str()) {
+                        let text = match span {
+                            EmojiSpan::Text(text) => text,
+                            EmojiSpan::Emoji { shortcode, .. }
+                                if options.has_markdown() && fences.in_code() =>
+                            {
+                                code.push_str(&format!(":{}:", shortcode));
+                                continue;
+                            }
+                            EmojiSpan::Emoji { shortcode, url } => {
+                                emoji::emoji_image(ui, &mut damus.img_cache, shortcode, url);
+                                continue;
+                            }
+                        };
+
+                        if !options.has_markdown() {
+                            let clicked = render_text_with_entities(
+                                ui,
+                                damus,
+                                txn,
+                                options,
+                                text,
+                                &mut embeds,
+                                |ui, text| {
+                                    let clicked =
+                                        render_text_with_payments(ui, text, rtl, render_text);
+                                    paid = paid.or(clicked);
+                                },
+                            );
+                            mentioned = mentioned.or(clicked);
+                            continue;
+                        }
+
+                        for chunk in fences.split(text) {
+                            match chunk {
+                                Chunk::Text(text) => {
+                                    let clicked = render_text_with_entities(
+                                        ui,
+                                        damus,
+                                        txn,
+                                        options,
+                                        text,
+                                        &mut embeds,
+                                        |ui, text| {
+                                            let clicked = render_text_with_payments(
+                                                ui,
+                                                text,
+                                                rtl,
+                                                render_markdown_text,
+                                            );
+                                            paid = paid.or(clicked);
+                                        },
+                                    );
+                                    mentioned = mentioned.or(clicked);
+                                }
+                                Chunk::Language(tag) => lang = Some(tag.to_owned()),
+                                Chunk::Code(text) => code.push_str(text),
+                                Chunk::EndCode => {
+                                    render_code_block(
+                                        ui,
+                                        (note_key.as_u64(), code_blocks),
+                                        &code,
+                                        lang.as_deref(),
+                                    );
+                                    code.clear();
+                                    lang = None;
+                                    code_blocks += 1;
+                                }
+                            }
+                        }
+                    }
+                }
+
+                BlockType::Invoice => {
+                    if lightning_chip(ui, block.as_str()).clicked() {
+                        paid = Some(lightning::strip_lightning_scheme(block.as_str()).to_owned());
+                    }
                 }
 
                 _ => {
-                    ui.colored_label(colors::PURPLE, block.as_str());
+                    ui.colored_label(theme.link, block.as_str());
                 }
             }
         }
-    });
 
-    if let Some((id, block_str)) = inline_note {
-        render_note_preview(ui, damus, txn, id, block_str);
+        // a code block that was never closed runs to the end of the note
+        if fences.in_code() {
+            render_code_block(ui, (note_key.as_u64(), code_blocks), &code, lang.as_deref());
+        }
+
+        ContentsResponse {
+            clicked_hashtag,
+            action: paid
+                .map(NoteAction::Pay)
+                .or(mentioned.map(NoteAction::MentionClicked))
+                .unwrap_or(NoteAction::None),
+        }
+    };
+
+    // right to left notes flow from the right edge
+    let resp = if rtl {
+        ui.with_layout(
+            egui::Layout::right_to_left(egui::Align::TOP).with_main_wrap(true),
+            add_contents,
+        )
+    } else {
+        ui.horizontal_wrapped(add_contents)
+    };
+
+    for (id, block_str) in embeds.notes {
+        render_quoted_note(ui, damus, txn, &id, block_str, quote_depth);
     }
 
-    for image in images {
-        let img_resp = ui.add(Image::new(image.clone()));
-        img_resp.context_menu(|ui| {
-            if ui.button("Copy Link").clicked() {
-                ui.ctx().copy_text(image);
+    let mut action = resp.inner.action;
+
+    for (found, naddr) in embeds.articles {
+        if article::article_card(ui, &mut damus.img_cache, found.as_ref(), naddr).clicked() {
+            action = NoteAction::OpenArticle(naddr.to_owned());
+        }
+    }
+
+    // several images share a grid on wider screens, and stack on phones
+    let images: Vec<String> = media
+        .iter()
+        .filter(|url| !is_video_url(url))
+        .cloned()
+        .collect();
+    let media = if images.len() > 1 && !ui::is_mobile(ui.ctx()) {
+        let width = ui.available_width().min(MAX_EMBED_WIDTH);
+        let cells = gallery::gallery(ui, &mut damus.img_cache, &images, width, image_priority);
+        for (cell, url) in cells.iter().zip(&images) {
+            ui::alt_text(cell, &media_alt(note, url, &tr!(ui.ctx(), "image")));
+            if cell.clicked() {
+                action = NoteAction::OpenMedia(url.clone());
+            }
+            cell.context_menu(|ui| {
+                if ui.button(tr!(ui.ctx(), "Copy Link")).clicked() {
+                    ui.ctx().copy_text(url.clone());
+                    ui.close_menu();
+                }
+            });
+        }
+        media.into_iter().filter(|url| is_video_url(url)).collect()
+    } else {
+        media
+    };
+
+    for url in media {
+        let media_resp = if is_video_url(&url) {
+            let thumbnail = video_thumbnail(note, &url);
+            let video_resp = render_video(
+                ui,
+                &mut damus.img_cache,
+                thumbnail.as_deref(),
+                image_priority,
+            );
+            ui::alt_text(&video_resp, &media_alt(note, &url, &tr!(ui.ctx(), "video")));
+            if video_resp.clicked() {
+                action = NoteAction::OpenMedia(url.clone());
+            }
+            video_resp
+        } else {
+            let dims = image_dims(note, &url);
+            let image_resp = render_media(
+                ui,
+                &mut damus.img_cache,
+                &url,
+                dims,
+                image_priority,
+                max_media_height,
+            );
+            ui::alt_text(&image_resp, &media_alt(note, &url, &tr!(ui.ctx(), "image")));
+            if image_resp.clicked() {
+                action = NoteAction::OpenMedia(url.clone());
+            }
+            image_resp
+        };
+
+        media_resp.context_menu(|ui| {
+            if ui.button(tr!(ui.ctx(), "Copy Link")).clicked() {
+                ui.ctx().copy_text(url);
                 ui.close_menu();
             }
         });
     }
 
-    resp
+    if let Some(url) = card_link {
+        render_link_card(ui, damus, &url);
+    }
+
+    egui::InnerResponse::new(
+        ContentsResponse {
+            clicked_hashtag: resp.inner.clicked_hashtag,
+            action,
+        },
+        resp.response,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashtag_chars() {
+        assert!(is_hashtag("nostr"));
+        assert!(is_hashtag("plebchain_2024"));
+        assert!(!is_hashtag(""));
+        assert!(!is_hashtag("foo-bar"));
+    }
+
+    #[test]
+    fn tall_images_are_cropped_to_their_middle() {
+        let (size, uv) = crop_to_height(vec2(400.0, 300.0), 500.0);
+        assert_eq!(size, vec2(400.0, 300.0));
+        assert_eq!(uv.height(), 1.0);
+
+        let (size, uv) = crop_to_height(vec2(400.0, 1000.0), 500.0);
+        assert_eq!(size, vec2(400.0, 500.0));
+        assert_eq!((uv.top(), uv.bottom()), (0.25, 0.75));
+
+        let (_, uv) = crop_to_height(vec2(400.0, 1000.0), f32::INFINITY);
+        assert_eq!(uv.height(), 1.0);
+    }
+
+    #[test]
+    fn few_emoji_are_jumbo() {
+        assert_eq!(jumbo_scale(1), Some(3.0));
+        assert_eq!(jumbo_scale(4), Some(2.0));
+        assert_eq!(jumbo_scale(MAX_JUMBO_EMOJI), Some(2.0));
+        assert_eq!(jumbo_scale(MAX_JUMBO_EMOJI + 1), None);
+    }
+
+    #[test]
+    fn image_urls() {
+        assert!(is_image_url("https://example.com/cat.JPG"));
+        assert!(is_image_url("https://example.com/cat.webp?width=400"));
+        assert!(!is_image_url("https://example.com/png"));
+        assert!(!is_image_url("https://example.com/cat.mp4"));
+    }
+
+    #[test]
+    fn mock_note_media() {
+        use crate::ui::preview::mock::{self, MockVariant};
+
+        let note = mock::mock_note();
+        let urls: Vec<&str> = note
+            .content
+            .split_whitespace()
+            .filter(|word| word.starts_with("https://"))
+            .map(|url| split_trailing_punctuation(url).0)
+            .collect();
+        assert_eq!(urls, vec!["https://damus.io", mock::MOCK_IMAGE_URL]);
+        assert!(!is_image_url(urls[0]));
+        assert!(is_image_url(urls[1]));
+
+        assert!(bidi::is_rtl(
+            &mock::mock_note_variant(MockVariant::Rtl).content
+        ));
+        assert!(!bidi::is_rtl(&note.content));
+    }
+
+    #[test]
+    fn video_urls() {
+        assert!(is_video_url("https://example.com/clip.MP4"));
+        assert!(is_video_url("https://example.com/clip.webm#t=10"));
+        assert!(!is_video_url("https://example.com/clip.png"));
+    }
+
+    #[test]
+    fn imeta_thumbnails() {
+        let url = "https://example.com/clip.mp4";
+        let fields = [
+            "url https://example.com/clip.mp4",
+            "m video/mp4",
+            "image https://example.com/clip.jpg",
+        ];
+        assert_eq!(
+            imeta_image(fields, url),
+            Some("https://example.com/clip.jpg")
+        );
+        assert_eq!(imeta_image(fields, "https://example.com/other.mp4"), None);
+        assert_eq!(imeta_image(["url https://example.com/clip.mp4"], url), None);
+        assert_eq!(
+            imeta_field(
+                ["dim 640x360", "url https://example.com/clip.mp4"],
+                url,
+                "dim"
+            ),
+            Some("640x360")
+        );
+    }
+
+    #[test]
+    fn splits_trailing_punctuation() {
+        assert_eq!(
+            split_trailing_punctuation("https://damus.io."),
+            ("https://damus.io", ".")
+        );
+        assert_eq!(
+            split_trailing_punctuation("https://damus.io/a?b=c!?"),
+            ("https://damus.io/a?b=c", "!?")
+        );
+        assert_eq!(
+            split_trailing_punctuation("https://en.wikipedia.org/wiki/Rust_(language))."),
+            ("https://en.wikipedia.org/wiki/Rust_(language)", ").")
+        );
+        assert_eq!(
+            split_trailing_punctuation("https://damus.io"),
+            ("https://damus.io", "")
+        );
+    }
+
+    #[test]
+    fn finds_payments_in_text() {
+        let text = "tips welcome: lightning:lnurl1dp68gurn8ghj7. thanks";
+        let (start, end) = find_payment(text).unwrap();
+        assert_eq!(&text[start..end], "lightning:lnurl1dp68gurn8ghj7");
+        assert_eq!(find_payment("lnbc is cheaper than a bank"), None);
+        assert_eq!(find_payment("no invoices here"), None);
+    }
 }
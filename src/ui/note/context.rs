@@ -0,0 +1,111 @@
+use crate::ui::i18n::tr;
+use crate::ui::is_mobile;
+use crate::ui::nip19;
+use crate::ui::NoteAction;
+use egui::{Order, Pos2, Rect};
+use std::time::Duration;
+
+/// How long a touch has to be held before it opens the context menu
+const LONG_PRESS: Duration = Duration::from_millis(500);
+
+/// A touch that moves further than this is a scroll, not a long press
+const LONG_PRESS_SLOP: f32 = 8.0;
+
+/// Whether the pointer has been held down inside `rect`, without moving,
+/// for at least [`LONG_PRESS`]
+pub(crate) fn long_pressed(ui: &egui::Ui, rect: Rect) -> bool {
+    let (pressing, held) = ui.input(|i| {
+        let pointer = &i.pointer;
+        let (Some(origin), Some(pos), Some(start)) = (
+            pointer.press_origin(),
+            pointer.interact_pos(),
+            pointer.press_start_time(),
+        ) else {
+            return (false, false);
+        };
+
+        let pressing = pointer.primary_down()
+            && rect.contains(origin)
+            && origin.distance(pos) < LONG_PRESS_SLOP;
+        (
+            pressing,
+            pressing && i.time - start >= LONG_PRESS.as_secs_f64(),
+        )
+    });
+
+    if pressing && !held {
+        // wake up when the press is long enough, even if nothing moves
+        ui.ctx().request_repaint_after(LONG_PRESS);
+    }
+
+    held
+}
+
+/// A menu for copying a note's text, id or author, or muting its author,
+/// opened by right clicking anywhere on the note, or with a long press on
+/// mobile. Returns [`NoteAction::Mute`] if the author was muted.
+pub fn note_context_menu(
+    ui: &mut egui::Ui,
+    note: &nostrdb::Note<'_>,
+    rect: Rect,
+) -> Option<NoteAction> {
+    let menu_id = ui.id().with(("note_context_menu", note.id()));
+    let open: Option<Pos2> = ui.data(|d| d.get_temp(menu_id));
+
+    let requested = if is_mobile(ui.ctx()) {
+        open.is_none() && long_pressed(ui, rect)
+    } else {
+        ui.rect_contains_pointer(rect) && ui.input(|i| i.pointer.secondary_clicked())
+    };
+
+    if requested {
+        if let Some(pos) = ui.input(|i| i.pointer.interact_pos()) {
+            ui.data_mut(|d| d.insert_temp(menu_id, pos));
+        }
+        return None;
+    }
+
+    let pos = open?;
+
+    let mut copied: Option<String> = None;
+    let mut muted = false;
+    let menu = egui::Area::new(menu_id)
+        .order(Order::Foreground)
+        .fixed_pos(pos)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::menu(ui.style()).show(ui, |ui| {
+                if ui.button(tr!(ui.ctx(), "Copy text")).clicked() {
+                    copied = Some(note.content().to_owned());
+                }
+                if ui.button(tr!(ui.ctx(), "Copy note id")).clicked() {
+                    copied = nip19::nevent(note.id(), &[]).ok();
+                }
+                if ui.button(tr!(ui.ctx(), "Copy author npub")).clicked() {
+                    copied = nip19::npub(note.pubkey()).ok();
+                }
+                ui.separator();
+                if ui.button(tr!(ui.ctx(), "Mute author")).clicked() {
+                    muted = true;
+                }
+            });
+        })
+        .response;
+
+    let clicked_elsewhere = ui.input(|i| {
+        i.pointer.any_pressed()
+            && i.pointer
+                .interact_pos()
+                .is_some_and(|pos| !menu.rect.contains(pos))
+    });
+
+    if let Some(text) = copied {
+        ui.output_mut(|o| o.copied_text = text);
+        ui.data_mut(|d| d.remove::<Pos2>(menu_id));
+    } else if muted {
+        ui.data_mut(|d| d.remove::<Pos2>(menu_id));
+        return Some(NoteAction::Mute(*note.pubkey()));
+    } else if clicked_elsewhere || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        ui.data_mut(|d| d.remove::<Pos2>(menu_id));
+    }
+    None
+}
@@ -0,0 +1,157 @@
+use crate::images::Priority;
+use crate::imgcache::ImageCache;
+use crate::ui;
+use crate::ui::note::contents::{cached_texture, fetch_content_image, MediaTexture};
+use egui::{pos2, vec2, Align2, Color32, CursorIcon, FontId, Image, Rect, Sense, Vec2};
+
+/// The most images a gallery shows. The last one says how many more
+/// there are.
+pub const MAX_GALLERY_IMAGES: usize = 4;
+
+/// The space between cells
+const GAP: f32 = 4.0;
+
+/// Where each of `count` images goes in a gallery `width` wide, relative
+/// to its top left. They're in square cells two to a row, except that an
+/// odd one out at the end gets a row to itself.
+pub fn gallery_cells(count: usize, width: f32) -> Vec<Rect> {
+    let shown = count.min(MAX_GALLERY_IMAGES);
+    let cell = (width - GAP) / 2.0;
+    (0..shown)
+        .map(|i| {
+            let y = (i / 2) as f32 * (cell + GAP);
+            if shown % 2 == 1 && i == shown - 1 {
+                Rect::from_min_size(pos2(0.0, y), vec2(width, cell))
+            } else {
+                let x = (i % 2) as f32 * (cell + GAP);
+                Rect::from_min_size(pos2(x, y), Vec2::splat(cell))
+            }
+        })
+        .collect()
+}
+
+/// The largest size with the aspect ratio of `size` that fits in `cell`
+pub(crate) fn fit_within(size: Vec2, cell: Vec2) -> Vec2 {
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return cell;
+    }
+    size * (cell.x / size.x).min(cell.y / size.y)
+}
+
+/// Lay out a note's images in a grid, each letterboxed in its cell.
+/// Returns a response per cell, in the order of `urls`, so the caller can
+/// open the one that's clicked.
+pub fn gallery(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    urls: &[String],
+    width: f32,
+    priority: Priority,
+) -> Vec<egui::Response> {
+    #[cfg(feature = "profiling")]
+    puffin::profile_function!();
+
+    let cells = gallery_cells(urls.len(), width);
+    let height = cells.iter().map(|cell| cell.bottom()).fold(0.0, f32::max);
+    let (rect, _) = ui.allocate_exact_size(vec2(width, height), Sense::hover());
+    let hidden = urls.len().saturating_sub(cells.len());
+
+    cells
+        .iter()
+        .zip(urls)
+        .enumerate()
+        .map(|(i, (cell, url))| {
+            let cell = cell.translate(rect.min.to_vec2());
+            let more = if i + 1 == cells.len() { hidden } else { 0 };
+            gallery_cell(ui, img_cache, url, cell, more, priority)
+        })
+        .collect()
+}
+
+fn gallery_cell(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    url: &str,
+    cell: Rect,
+    more: usize,
+    priority: Priority,
+) -> egui::Response {
+    let response = ui
+        .interact(cell, ui.id().with(("gallery", url)), Sense::click())
+        .on_hover_cursor(CursorIcon::PointingHand);
+    if !ui.is_rect_visible(cell) {
+        return response;
+    }
+
+    let painter = ui.painter();
+    painter.rect_filled(cell, 8.0, ui.visuals().faint_bg_color);
+
+    fetch_content_image(ui, img_cache, url, priority);
+    match cached_texture(img_cache, url) {
+        MediaTexture::Loaded(texture) => {
+            let size = fit_within(texture.size_vec2(), cell.size());
+            Image::new(&texture)
+                .rounding(8.0)
+                .paint_at(ui, Rect::from_center_size(cell.center(), size));
+        }
+        MediaTexture::Loading => {
+            egui::Spinner::new().paint_at(ui, cell.shrink(cell.height() / 3.0));
+        }
+        MediaTexture::Failed => {
+            ui.painter().text(
+                cell.center(),
+                Align2::CENTER_CENTER,
+                "⚠",
+                FontId::proportional(20.0),
+                ui.visuals().weak_text_color(),
+            );
+        }
+    }
+
+    if more > 0 {
+        let painter = ui.painter();
+        painter.rect_filled(cell, 8.0, Color32::from_black_alpha(140));
+        painter.text(
+            cell.center(),
+            Align2::CENTER_CENTER,
+            format!("+{}", ui::fmt::compact_number(more as u64)),
+            FontId::proportional(24.0),
+            Color32::WHITE,
+        );
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_two_up_and_two_by_two() {
+        let two = gallery_cells(2, 204.0);
+        assert_eq!(two.len(), 2);
+        assert_eq!(
+            two[0],
+            Rect::from_min_size(pos2(0.0, 0.0), Vec2::splat(100.0))
+        );
+        assert_eq!(two[1].left(), 104.0);
+
+        // the odd one out spans the row
+        let three = gallery_cells(3, 204.0);
+        assert_eq!(
+            three[2],
+            Rect::from_min_size(pos2(0.0, 104.0), vec2(204.0, 100.0))
+        );
+
+        assert_eq!(gallery_cells(7, 204.0).len(), MAX_GALLERY_IMAGES);
+    }
+
+    #[test]
+    fn images_keep_their_aspect_ratio() {
+        let cell = Vec2::splat(100.0);
+        assert_eq!(fit_within(vec2(400.0, 200.0), cell), vec2(100.0, 50.0));
+        assert_eq!(fit_within(vec2(10.0, 20.0), cell), vec2(50.0, 100.0));
+        assert_eq!(fit_within(Vec2::ZERO, cell), cell);
+    }
+}
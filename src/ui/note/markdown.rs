@@ -0,0 +1,194 @@
+/// A styled piece of a line of note text
+#[derive(Debug, PartialEq, Eq)]
+pub enum Span<'a> {
+    Plain(&'a str),
+    /// `**bold**`
+    Bold(&'a str),
+    /// `*italic*`
+    Italic(&'a str),
+    /// `` `code` ``
+    Code(&'a str),
+}
+
+const FENCE: &str = "```";
+
+/// Split text into plain and styled spans. Unmatched markers, and markers
+/// in the middle of a word like `2*3*4`, are left as plain text.
+pub fn inline_spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = vec![];
+    let mut plain_start = 0;
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let marker = if rest.starts_with('`') {
+            "`"
+        } else if rest.starts_with("**") {
+            "**"
+        } else if rest.starts_with('*') {
+            "*"
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+
+        let mid_word = text[..i]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let inner_start = i + marker.len();
+        let inner = text[inner_start..]
+            .find(marker)
+            .map(|len| &text[inner_start..inner_start + len])
+            .filter(|inner| !mid_word && !inner.is_empty() && inner.trim() == *inner);
+
+        let Some(inner) = inner else {
+            i += marker.len();
+            continue;
+        };
+
+        if plain_start < i {
+            spans.push(Span::Plain(&text[plain_start..i]));
+        }
+        spans.push(match marker {
+            "`" => Span::Code(inner),
+            "**" => Span::Bold(inner),
+            _ => Span::Italic(inner),
+        });
+        i = inner_start + inner.len() + marker.len();
+        plain_start = i;
+    }
+
+    if plain_start < text.len() {
+        spans.push(Span::Plain(&text[plain_start..]));
+    }
+
+    spans
+}
+
+/// A piece of note text either side of a ``` fence
+#[derive(Debug, PartialEq, Eq)]
+pub enum Chunk<'a> {
+    /// Text outside of a code block, to style with [`inline_spans`]
+    Text(&'a str),
+    /// The language tag of the code block that just opened, eg. `rust`
+    Language(&'a str),
+    /// Text inside a code block. Code blocks can span several note blocks
+    /// (eg. a url inside code), so this may only be part of one.
+    Code(&'a str),
+    /// The end of a code block
+    EndCode,
+}
+
+/// Tracks whether we're inside a fenced code block across the blocks of a
+/// note
+#[derive(Default)]
+pub struct Fences {
+    in_code: bool,
+    /// Whether we still need to skip the language tag after an opening fence
+    at_code_start: bool,
+}
+
+impl Fences {
+    pub fn in_code(&self) -> bool {
+        self.in_code
+    }
+
+    /// Split a piece of note text at its fences
+    pub fn split<'a>(&mut self, mut text: &'a str) -> Vec<Chunk<'a>> {
+        let mut chunks = vec![];
+
+        loop {
+            if self.in_code && self.at_code_start {
+                let (lang, code) = split_language_tag(text);
+                if let Some(lang) = lang {
+                    chunks.push(Chunk::Language(lang));
+                }
+                text = code;
+                self.at_code_start = false;
+            }
+
+            let Some(fence) = text.find(FENCE) else {
+                if !text.is_empty() {
+                    chunks.push(self.chunk(text));
+                }
+                return chunks;
+            };
+
+            if fence > 0 {
+                chunks.push(self.chunk(&text[..fence]));
+            }
+            if self.in_code {
+                chunks.push(Chunk::EndCode);
+            }
+
+            self.in_code = !self.in_code;
+            self.at_code_start = self.in_code;
+            text = &text[fence + FENCE.len()..];
+        }
+    }
+
+    fn chunk<'a>(&self, text: &'a str) -> Chunk<'a> {
+        if self.in_code {
+            Chunk::Code(text)
+        } else {
+            Chunk::Text(text)
+        }
+    }
+}
+
+/// Split the `rust` off of "```rust\n", dropping the newline
+fn split_language_tag(text: &str) -> (Option<&str>, &str) {
+    match text.split_once('\n') {
+        Some((tag, code)) if !tag.contains(char::is_whitespace) => {
+            (Some(tag).filter(|tag| !tag.is_empty()), code)
+        }
+        _ => (None, text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styles_inline_spans() {
+        assert_eq!(
+            inline_spans("a **b** *c* `d`"),
+            vec![
+                Span::Plain("a "),
+                Span::Bold("b"),
+                Span::Plain(" "),
+                Span::Italic("c"),
+                Span::Plain(" "),
+                Span::Code("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_stray_markers() {
+        assert_eq!(inline_spans("2*3*4"), vec![Span::Plain("2*3*4")]);
+        assert_eq!(inline_spans("a * b * c"), vec![Span::Plain("a * b * c")]);
+        assert_eq!(inline_spans("**open"), vec![Span::Plain("**open")]);
+    }
+
+    #[test]
+    fn splits_fences() {
+        let mut fences = Fences::default();
+        assert_eq!(
+            fences.split("look:\n```rust\nfn main() {}\n``` neat"),
+            vec![
+                Chunk::Text("look:\n"),
+                Chunk::Language("rust"),
+                Chunk::Code("fn main() {}\n"),
+                Chunk::EndCode,
+                Chunk::Text(" neat"),
+            ]
+        );
+        assert!(!fences.in_code());
+
+        assert_eq!(fences.split("```\nopen"), vec![Chunk::Code("open")]);
+        assert!(fences.in_code());
+    }
+}
@@ -1,27 +1,133 @@
+pub mod article;
 pub mod contents;
+pub mod context;
+pub mod gallery;
+pub mod markdown;
 pub mod options;
+pub mod warning;
+pub mod zap;
 
-pub use contents::NoteContents;
+pub use contents::{ContentsResponse, NoteContents};
 pub use options::NoteOptions;
 
-use crate::{colors, ui, ui::is_mobile, Damus};
+use crate::images::Priority;
+use crate::ui::direction::{self, LayoutDirection};
+use crate::ui::i18n::tr;
+use crate::ui::theme::NotedeckTheme;
+use crate::{ui, ui::is_mobile, Damus};
 use egui::{Label, RichText, Sense};
 use nostrdb::{NoteKey, Transaction};
 use std::hash::{Hash, Hasher};
 
+/// How far each level of a thread is indented
+const THREAD_INDENT: f32 = 24.0;
+
+/// Threads deeper than this stop indenting, so they stay readable
+const MAX_THREAD_INDENT_DEPTH: usize = 4;
+
+fn thread_indent(depth: usize) -> f32 {
+    depth.min(MAX_THREAD_INDENT_DEPTH) as f32 * THREAD_INDENT
+}
+
+/// Where the line down from the center of a reply's parent's avatar goes,
+/// past the parent's indent. Past the indent limit the parent is indented
+/// as much as the reply, so this isn't simply one indent less.
+fn thread_line_offset(depth: usize) -> f32 {
+    thread_indent(depth.saturating_sub(1)) + ui::ProfilePic::default_size() / 2.0
+}
+
+/// How deeply quoted notes are rendered inline before we stop and show a
+/// link to expand them instead
+pub const MAX_QUOTE_DEPTH: usize = 2;
+
 pub struct Note<'a> {
     app: &'a mut Damus,
     note: &'a nostrdb::Note<'a>,
     flags: NoteOptions,
+    quote_depth: usize,
+    thread_depth: usize,
+    stats: NoteStats,
+    content_warning: Option<String>,
+    muted: bool,
+    emojis: Option<ui::CustomEmojis>,
+    publish_state: PublishState,
+    focused: bool,
+    max_media_height: f32,
+    seen_on: &'a [String],
+}
+
+/// How far along publishing a note the user just sent is, so it can be
+/// shown before any relay has confirmed it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PublishState {
+    /// Sent, but no relay has accepted it yet
+    Sending,
+    #[default]
+    Confirmed,
+    /// Every relay refused it, or we couldn't reach them
+    Failed,
+}
+
+/// What the user asked to do with a note from its action bar. Acting on it,
+/// eg. publishing a reaction, is up to the caller.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NoteAction {
+    #[default]
+    None,
+    Reply,
+    Repost,
+    /// React with this emoji
+    React(String),
+    /// Zap this many millisats, with an optional comment for the recipient.
+    /// Paying the invoice is up to the host.
+    Zap {
+        msat: u64,
+        comment: Option<String>,
+    },
+    /// Open the media at this url, full size in a [`Lightbox`](ui::Lightbox)
+    /// for images or elsewhere for videos we can't play inline
+    OpenMedia(String),
+    /// Hide notes by this author. Keeping the mute list is up to the host.
+    Mute([u8; 32]),
+    /// Pay this BOLT11 invoice or LNURL, which was clicked in the note
+    Pay(String),
+    /// Try publishing the note again, after it failed
+    Retry,
+    /// Open the note on its own, with its thread
+    Open,
+    /// Someone mentioned in the note was clicked. Opening their profile is
+    /// up to the host.
+    MentionClicked([u8; 32]),
+    /// A long-form post the note links to was clicked, at this `naddr`
+    OpenArticle(String),
+}
+
+/// Counts shown in a note's action bar, gathered by the caller
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoteStats {
+    pub reactions: u32,
+    pub reposts: u32,
+    pub zaps_msat: u64,
+    /// Whether the current user has reacted to the note
+    pub reacted: bool,
+    /// Whether the current user has reposted the note
+    pub reposted: bool,
+}
+
+/// The reactions offered in the react button's picker
+pub const DEFAULT_REACTIONS: [&str; 6] = ["🤙", "❤", "😂", "🔥", "👀", "🙏"];
+
+/// What happened when a note was shown
+pub struct NoteResponse {
+    pub response: egui::Response,
+    /// A hashtag in the note that was clicked, without the leading #
+    pub clicked_hashtag: Option<String>,
+    pub action: NoteAction,
 }
 
 impl<'a> egui::Widget for Note<'a> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        if self.app.textmode {
-            self.textmode_ui(ui)
-        } else {
-            self.standard_ui(ui)
-        }
+        self.show(ui).response
     }
 }
 
@@ -45,10 +151,11 @@ fn reply_desc(
     app: &mut Damus,
     note_key: NoteKey,
     note: &nostrdb::Note<'_>,
-) {
+) -> Option<[u8; 32]> {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
+    let theme = NotedeckTheme::get(ui.ctx());
     let note_reply = app
         .get_note_cache_mut(note_key, note)
         .reply
@@ -58,33 +165,41 @@ fn reply_desc(
         reply
     } else {
         // not a reply, nothing to do here
-        return;
+        return None;
     };
 
     ui.add(Label::new(
-        RichText::new("replying to")
+        RichText::new(tr!(ui.ctx(), "replying to"))
             .size(10.0)
-            .color(colors::GRAY_SECONDARY),
+            .color(theme.muted_text),
     ));
 
     let reply_note = if let Ok(reply_note) = app.ndb.get_note_by_id(txn, reply.id) {
         reply_note
     } else {
         ui.add(Label::new(
-            RichText::new("a note")
+            RichText::new(tr!(ui.ctx(), "a note"))
                 .size(10.0)
-                .color(colors::GRAY_SECONDARY),
+                .color(theme.muted_text),
         ));
-        return;
+        return None;
     };
 
+    let profiles = ui::NdbProfiles::new(&app.ndb, txn);
+    let mut clicked = None;
+    let mut mention = |ui: &mut egui::Ui, pk: &[u8; 32]| {
+        let resp = ui::Mention::new(&mut app.img_cache, &profiles, pk)
+            .size(10.0)
+            .show(ui);
+        clicked = clicked.or(resp.clicked);
+    };
     if note_reply.is_reply_to_root() {
         // We're replying to the root, let's show this
-        ui.add(ui::Mention::new(app, txn, reply_note.pubkey()).size(10.0));
+        mention(ui, reply_note.pubkey());
         ui.add(Label::new(
-            RichText::new("'s note")
+            RichText::new(tr!(ui.ctx(), "'s note"))
                 .size(10.0)
-                .color(colors::GRAY_SECONDARY),
+                .color(theme.muted_text),
         ));
     } else if let Some(root) = note_reply.root() {
         // replying to another post in a thread, not the root
@@ -92,41 +207,138 @@ fn reply_desc(
         if let Ok(root_note) = app.ndb.get_note_by_id(txn, root.id) {
             if root_note.pubkey() == reply_note.pubkey() {
                 // simply "replying to bob's note" when replying to bob in his thread
-                ui.add(ui::Mention::new(app, txn, reply_note.pubkey()).size(10.0));
+                mention(ui, reply_note.pubkey());
                 ui.add(Label::new(
-                    RichText::new("'s note")
+                    RichText::new(tr!(ui.ctx(), "'s note"))
                         .size(10.0)
-                        .color(colors::GRAY_SECONDARY),
+                        .color(theme.muted_text),
                 ));
             } else {
                 // replying to bob in alice's thread
 
-                ui.add(ui::Mention::new(app, txn, reply_note.pubkey()).size(10.0));
+                mention(ui, reply_note.pubkey());
                 ui.add(Label::new(
-                    RichText::new("in").size(10.0).color(colors::GRAY_SECONDARY),
+                    RichText::new(tr!(ui.ctx(), "in"))
+                        .size(10.0)
+                        .color(theme.muted_text),
                 ));
-                ui.add(ui::Mention::new(app, txn, root_note.pubkey()).size(10.0));
+                mention(ui, root_note.pubkey());
                 ui.add(Label::new(
-                    RichText::new("'s thread")
+                    RichText::new(tr!(ui.ctx(), "'s thread"))
                         .size(10.0)
-                        .color(colors::GRAY_SECONDARY),
+                        .color(theme.muted_text),
                 ));
             }
         } else {
-            ui.add(ui::Mention::new(app, txn, reply_note.pubkey()).size(10.0));
+            mention(ui, reply_note.pubkey());
             ui.add(Label::new(
-                RichText::new("in someone's thread")
+                RichText::new(tr!(ui.ctx(), "in someone's thread"))
                     .size(10.0)
-                    .color(colors::GRAY_SECONDARY),
+                    .color(theme.muted_text),
             ));
         }
     }
+
+    clicked
 }
 
 impl<'a> Note<'a> {
     pub fn new(app: &'a mut Damus, note: &'a nostrdb::Note<'a>) -> Self {
-        let flags = NoteOptions::actionbar | NoteOptions::note_previews;
-        Note { app, note, flags }
+        let flags = NoteOptions::actionbar
+            | NoteOptions::note_previews
+            | NoteOptions::media
+            | NoteOptions::selectable_text;
+        Note {
+            app,
+            note,
+            flags,
+            quote_depth: 0,
+            thread_depth: 0,
+            stats: NoteStats::default(),
+            content_warning: warning::content_warning(note),
+            muted: false,
+            emojis: None,
+            publish_state: PublishState::default(),
+            focused: false,
+            max_media_height: f32::INFINITY,
+            seen_on: &[],
+        }
+    }
+
+    /// Whether this is the note selected with the keyboard. It's drawn with
+    /// a focus ring and takes keyboard focus, so the
+    /// [`NoteKeyAction`](ui::focus::NoteKeyAction) shortcuts act on it.
+    /// Its pictures are downloaded ahead of other notes'.
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    /// The tallest an image in the note is drawn, in points. Taller ones
+    /// are cropped to this with a prompt to tap them to see all of it.
+    /// Images aren't capped by default.
+    pub fn max_media_height(mut self, height: f32) -> Self {
+        self.max_media_height = height;
+        self
+    }
+
+    /// The urls of the relays the note was seen on, listed when hovering
+    /// its timestamp
+    pub fn seen_on(mut self, relays: &'a [String]) -> Self {
+        self.seen_on = relays;
+        self
+    }
+
+    /// Show the note as still sending, dimmed with a spinner, or as failed
+    /// with a button emitting [`NoteAction::Retry`]. Notes are confirmed
+    /// by default.
+    pub fn publish_state(mut self, state: PublishState) -> Self {
+        self.publish_state = state;
+        self
+    }
+
+    /// The custom emoji to show in place of `:shortcode:`s in the note's
+    /// text, instead of the ones its `emoji` tags define
+    pub fn emojis(mut self, emojis: ui::CustomEmojis) -> Self {
+        self.emojis = Some(emojis);
+        self
+    }
+
+    /// Collapse the note to a one line stub, because the host has muted its
+    /// author. The stub can still be expanded to show the note.
+    pub fn muted(mut self, muted: bool) -> Self {
+        self.muted = muted;
+        self
+    }
+
+    /// Hide the note's text and media behind an overlay giving this reason
+    /// until it's clicked. Defaults to the note's NIP-36 `content-warning`
+    /// tag, if it has one.
+    pub fn content_warning(mut self, reason: Option<String>) -> Self {
+        self.content_warning = reason;
+        self
+    }
+
+    /// How deep this note is in a reply thread, as worked out by the
+    /// caller. Replies are indented under their parent with a line leading
+    /// back up to the parent's avatar.
+    pub fn thread_depth(mut self, depth: usize) -> Self {
+        self.thread_depth = depth;
+        self
+    }
+
+    /// Reaction, repost and zap counts to show in the action bar
+    pub fn stats(mut self, stats: NoteStats) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// How many quoted notes this note is nested inside of. Quotes deeper
+    /// than [`MAX_QUOTE_DEPTH`] are collapsed behind a link, so notes that
+    /// quote themselves don't recurse forever.
+    pub fn quote_depth(mut self, depth: usize) -> Self {
+        self.quote_depth = depth;
+        self
     }
 
     pub fn actionbar(mut self, enable: bool) -> Self {
@@ -139,6 +351,41 @@ impl<'a> Note<'a> {
         self
     }
 
+    /// Embed images linked in the note below its text. When disabled the
+    /// links are shown like any other url.
+    pub fn show_media(mut self, enable: bool) -> Self {
+        self.options_mut().set_media(enable);
+        self
+    }
+
+    /// Show a preview card for the first link in the note that isn't
+    /// media. Off by default, since it means fetching whatever page the note
+    /// links to.
+    pub fn link_previews(mut self, enable: bool) -> Self {
+        self.options_mut().set_link_previews(enable);
+        self
+    }
+
+    /// Style `**bold**`, `*italic*`, `` `code` `` and fenced code blocks
+    /// in the note's text. Off by default, so text shows up as written.
+    pub fn markdown(mut self, enable: bool) -> Self {
+        self.options_mut().set_markdown(enable);
+        self
+    }
+
+    /// Let the note's text be drag selected and copied. Links, mentions
+    /// and hashtags still open on a plain click.
+    pub fn selectable_text(mut self, enable: bool) -> Self {
+        self.options_mut().set_selectable_text(enable);
+        self
+    }
+
+    /// Render the author's name in the bold font family
+    pub fn bold_author(mut self, enable: bool) -> Self {
+        self.options_mut().set_bold_author(enable);
+        self
+    }
+
     pub fn options(&self) -> NoteOptions {
         self.flags
     }
@@ -147,22 +394,58 @@ impl<'a> Note<'a> {
         &mut self.flags
     }
 
-    fn textmode_ui(self, ui: &mut egui::Ui) -> egui::Response {
+    pub fn show(self, ui: &mut egui::Ui) -> NoteResponse {
+        let note = self.note;
+        let shown_id = egui::Id::new(("show_muted_note", note.id()));
+        if self.muted && !ui.data(|d| d.get_temp(shown_id)).unwrap_or(false) {
+            return muted_stub_ui(ui, shown_id);
+        }
+
+        let publish_state = self.publish_state;
+        let focused = self.focused;
+        let mut resp = if self.app.textmode {
+            self.textmode_ui(ui)
+        } else {
+            self.standard_ui(ui)
+        };
+
+        if let Some(action) = context::note_context_menu(ui, note, resp.response.rect) {
+            resp.action = action;
+        }
+        if let Some(action) = publish_state_ui(ui, publish_state, &mut resp.response) {
+            resp.action = action;
+        }
+        if focused {
+            let stroke = egui::Stroke::new(1.5, NotedeckTheme::get(ui.ctx()).accent);
+            ui.painter()
+                .rect_stroke(resp.response.rect.shrink(1.0), 4.0, stroke);
+            let focus_id = egui::Id::new(("note_focus", note.id()));
+            if let Some(key) = ui::focus::focus_note(ui, focus_id) {
+                resp.action = key.note_action();
+            }
+        }
+        resp
+    }
+
+    fn textmode_ui(self, ui: &mut egui::Ui) -> NoteResponse {
         let note_key = self.note.key().expect("todo: implement non-db notes");
         let txn = self.note.txn().expect("todo: implement non-db notes");
 
-        ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        let resp = ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
             let profile = self.app.ndb.get_profile_by_pubkey(txn, self.note.pubkey());
 
             //ui.horizontal(|ui| {
             ui.spacing_mut().item_spacing.x = 2.0;
 
+            let created_at = self.note.created_at();
+            let note_id = self.note.id();
+            let seen_on = self.seen_on;
             let note_cache = self.app.get_note_cache_mut(note_key, self.note);
 
             let (_id, rect) = ui.allocate_space(egui::vec2(50.0, 20.0));
             ui.allocate_rect(rect, Sense::hover());
             ui.put(rect, |ui: &mut egui::Ui| {
-                render_reltime(ui, note_cache, false).response
+                render_reltime(ui, note_cache, created_at, note_id, seen_on, false).response
             });
             let (_id, rect) = ui.allocate_space(egui::vec2(150.0, 20.0));
             ui.allocate_rect(rect, Sense::hover());
@@ -170,29 +453,69 @@ impl<'a> Note<'a> {
                 ui.add(
                     ui::Username::new(profile.as_ref().ok(), self.note.pubkey())
                         .abbreviated(6)
-                        .pk_colored(true),
+                        .pk_colored(true)
+                        .bold(self.options().has_bold_author()),
                 )
             });
 
-            ui.add(NoteContents::new(
-                self.app, txn, self.note, note_key, self.flags,
-            ));
+            NoteContents::new(self.app, txn, self.note, note_key, self.flags)
+                .quote_depth(self.quote_depth)
+                .show(ui)
+                .inner
             //});
-        })
-        .response
+        });
+
+        NoteResponse {
+            response: resp.response,
+            clicked_hashtag: resp.inner.clicked_hashtag,
+            action: resp.inner.action,
+        }
     }
 
-    pub fn standard_ui(self, ui: &mut egui::Ui) -> egui::Response {
+    pub fn standard_ui(mut self, ui: &mut egui::Ui) -> NoteResponse {
         #[cfg(feature = "profiling")]
         puffin::profile_function!();
         let note_key = self.note.key().expect("todo: support non-db notes");
         let txn = self.note.txn().expect("todo: support non-db notes");
 
-        crate::ui::padding(12.0, ui, |ui| {
-            ui.with_layout(egui::Layout::left_to_right(egui::Align::TOP), |ui| {
+        let mut clicked_hashtag = None;
+        let mut action = NoteAction::None;
+        let mut reply_mention = None;
+        let pad = 12.0;
+        let indent = thread_indent(self.thread_depth);
+        let direction = LayoutDirection::get(ui.ctx());
+        // replies are indented from the side the avatar is on
+        let margin = if direction.is_rtl() {
+            egui::Margin {
+                right: pad + indent,
+                ..egui::Margin::same(pad)
+            }
+        } else {
+            egui::Margin {
+                left: pad + indent,
+                ..egui::Margin::same(pad)
+            }
+        };
+        let thread_depth = self.thread_depth;
+
+        let response = crate::ui::padding(margin, ui, |ui| {
+            ui.with_layout(direction.row(egui::Align::TOP), |ui| {
                 ui.spacing_mut().item_spacing.x = 16.0;
 
                 let profile = self.app.ndb.get_profile_by_pubkey(txn, self.note.pubkey());
+                let pfp_priority = if self.focused {
+                    Priority::High
+                } else {
+                    Priority::Low
+                };
+                let pfp_alt = ui::ProfilePic::alt_for(
+                    ui.ctx(),
+                    profile
+                        .as_ref()
+                        .ok()
+                        .and_then(crate::profile::get_profile_name)
+                        .map(|name| name.display_name()),
+                );
 
                 match profile
                     .as_ref()
@@ -206,9 +529,18 @@ impl<'a> Note<'a> {
                         let anim_speed = 0.05;
                         let profile_key = profile.as_ref().unwrap().record().note_key();
                         let note_key = note_key.as_u64();
+                        // where the note was seen is a better hint for
+                        // finding its author than our own relays
+                        let seen_on = self.seen_on;
 
                         if is_mobile(ui.ctx()) {
-                            ui.add(ui::ProfilePic::new(&mut self.app.img_cache, pic));
+                            ui.add(
+                                ui::ProfilePic::new(&mut self.app.img_cache, pic)
+                                    .pubkey(self.note.pubkey())
+                                    .alt(pfp_alt)
+                                    .interactive(false)
+                                    .priority(pfp_priority),
+                            );
                         } else {
                             let (rect, size) = ui::anim::hover_expand(
                                 ui,
@@ -223,62 +555,231 @@ impl<'a> Note<'a> {
 
                             ui.put(
                                 rect,
-                                ui::ProfilePic::new(&mut self.app.img_cache, pic).size(size),
+                                ui::ProfilePic::new(&mut self.app.img_cache, pic)
+                                    .size(size)
+                                    .pubkey(self.note.pubkey())
+                                    .alt(pfp_alt)
+                                    .interactive(false)
+                                    .priority(pfp_priority),
                             )
                             .on_hover_ui_at_pointer(|ui| {
                                 ui.set_max_width(300.0);
-                                ui.add(ui::ProfilePreview::new(
-                                    profile.as_ref().unwrap(),
-                                    &mut self.app.img_cache,
-                                ));
+                                ui.add(
+                                    ui::ProfilePreview::new(
+                                        profile.as_ref().unwrap(),
+                                        &mut self.app.img_cache,
+                                    )
+                                    .pubkey(self.note.pubkey())
+                                    .relays(seen_on),
+                                );
                             });
                         }
                     }
                     None => {
-                        ui.add(ui::ProfilePic::new(
-                            &mut self.app.img_cache,
-                            ui::ProfilePic::no_pfp_url(),
-                        ));
+                        ui.add(
+                            ui::ProfilePic::new(
+                                &mut self.app.img_cache,
+                                ui::ProfilePic::no_pfp_url(),
+                            )
+                            .alt(pfp_alt)
+                            .interactive(false),
+                        );
                     }
                 }
 
-                ui.with_layout(egui::Layout::top_down(egui::Align::LEFT), |ui| {
-                    ui.horizontal(|ui| {
+                ui.with_layout(direction.column(), |ui| {
+                    direction::horizontal(ui, |ui| {
                         ui.spacing_mut().item_spacing.x = 2.0;
+                        let bold = self.options().has_bold_author();
+                        let profiles = ui::NdbProfiles::new(&self.app.ndb, txn);
+                        let emojis = profile
+                            .as_ref()
+                            .ok()
+                            .filter(|p| has_shortcodes(p))
+                            .map(|p| ui::CustomEmojis::from_profile(&self.app.ndb, txn, p))
+                            .unwrap_or_default();
                         ui.add(
                             ui::Username::new(profile.as_ref().ok(), self.note.pubkey())
-                                .abbreviated(20),
+                                .abbreviated(20)
+                                .bold(bold)
+                                .image_cache(&mut self.app.img_cache)
+                                .hover_card(&profiles)
+                                .emojis(emojis),
                         );
 
+                        let created_at = self.note.created_at();
                         let note_cache = self.app.get_note_cache_mut(note_key, self.note);
-                        render_reltime(ui, note_cache, true);
+                        render_reltime(
+                            ui,
+                            note_cache,
+                            created_at,
+                            self.note.id(),
+                            self.seen_on,
+                            true,
+                        );
                     });
 
-                    ui.horizontal(|ui| {
+                    direction::horizontal(ui, |ui| {
                         ui.spacing_mut().item_spacing.x = 2.0;
-                        reply_desc(ui, txn, self.app, note_key, self.note);
+                        reply_mention = reply_desc(ui, txn, self.app, note_key, self.note);
                     });
 
-                    ui.add(NoteContents::new(
-                        self.app,
-                        txn,
-                        self.note,
-                        note_key,
-                        self.options(),
-                    ));
+                    let options = self.options();
+                    // what's being looked at loads first
+                    let image_priority = if self.focused {
+                        Priority::High
+                    } else {
+                        Priority::Normal
+                    };
+                    let mut show_contents = |ui: &mut egui::Ui, options| {
+                        let mut contents =
+                            NoteContents::new(self.app, txn, self.note, note_key, options)
+                                .quote_depth(self.quote_depth)
+                                .image_priority(image_priority)
+                                .max_media_height(self.max_media_height);
+                        if let Some(emojis) = self.emojis.take() {
+                            contents = contents.emojis(emojis);
+                        }
+                        contents.show(ui).inner
+                    };
+                    let contents = match &self.content_warning {
+                        Some(reason) => {
+                            let id = egui::Id::new(("content_warning", self.note.id()));
+                            warning::sensitive_ui(ui, id, reason, |ui, revealed| {
+                                let mut options = options;
+                                options.set_media(revealed && options.has_media());
+                                options.set_link_previews(revealed && options.has_link_previews());
+                                show_contents(ui, options)
+                            })
+                        }
+                        None => show_contents(ui, options),
+                    };
+                    clicked_hashtag = contents.clicked_hashtag;
+                    action = contents.action;
+                    if let (NoteAction::None, Some(pk)) = (&action, reply_mention) {
+                        action = NoteAction::MentionClicked(pk);
+                    }
 
                     if self.options().has_actionbar() {
-                        render_note_actionbar(ui);
+                        let bar_action = render_note_actionbar(ui, note_key, &self.stats).inner;
+                        if bar_action != NoteAction::None {
+                            action = bar_action;
+                        }
                     }
                 });
             });
         })
-        .response
+        .response;
+
+        if thread_depth > 0 {
+            // down through our gutter from the center of the parent's avatar
+            let offset = thread_line_offset(thread_depth) + pad;
+            let x = if direction.is_rtl() {
+                response.rect.right() - offset
+            } else {
+                response.rect.left() + offset
+            };
+            ui.painter().vline(
+                x,
+                response.rect.y_range(),
+                ui.visuals().widgets.noninteractive.bg_stroke,
+            );
+        }
+
+        NoteResponse {
+            response,
+            clicked_hashtag,
+            action,
+        }
     }
 }
 
-fn render_note_actionbar(ui: &mut egui::Ui) -> egui::InnerResponse<()> {
-    ui.horizontal(|ui| {
+/// Whether someone's names could have custom emoji in them, which is
+/// worth checking before looking up their metadata note's tags
+fn has_shortcodes(profile: &nostrdb::ProfileRecord) -> bool {
+    let Some(p) = profile.record().profile() else {
+        return false;
+    };
+    [p.display_name(), p.name()]
+        .into_iter()
+        .flatten()
+        .any(|name| name.matches(':').count() >= 2)
+}
+
+/// Dim a note that's still sending and put a spinner in its corner, or add
+/// a row under one that failed offering to retry. `response` grows to cover
+/// anything added.
+fn publish_state_ui(
+    ui: &mut egui::Ui,
+    state: PublishState,
+    response: &mut egui::Response,
+) -> Option<NoteAction> {
+    let rect = response.rect;
+    match state {
+        PublishState::Confirmed => None,
+
+        PublishState::Sending => {
+            ui.painter()
+                .rect_filled(rect, 0.0, ui.visuals().panel_fill.gamma_multiply(0.5));
+            let spinner = egui::Rect::from_min_size(
+                rect.right_top() + egui::vec2(-24.0, 12.0),
+                egui::vec2(12.0, 12.0),
+            );
+            egui::Spinner::new().size(12.0).paint_at(ui, spinner);
+            None
+        }
+
+        PublishState::Failed => {
+            let theme = NotedeckTheme::get(ui.ctx());
+            let row = crate::ui::padding(egui::Margin::symmetric(12.0, 4.0), ui, |ui| {
+                direction::horizontal(ui, |ui| {
+                    ui.label(
+                        RichText::new(tr!(ui.ctx(), "⚠ Couldn't send this note"))
+                            .size(11.0)
+                            .color(theme.error),
+                    );
+                    ui.link(RichText::new(tr!(ui.ctx(), "Retry")).size(11.0))
+                        .clicked()
+                })
+                .inner
+            });
+            *response = response.union(row.response);
+            row.inner.then_some(NoteAction::Retry)
+        }
+    }
+}
+
+/// What's shown instead of a note by a muted author, until "show" is
+/// clicked, which is remembered under `shown_id`
+fn muted_stub_ui(ui: &mut egui::Ui, shown_id: egui::Id) -> NoteResponse {
+    let response = crate::ui::padding(12.0, ui, |ui| {
+        direction::horizontal(ui, |ui| {
+            ui.spacing_mut().item_spacing.x = 4.0;
+            secondary_label(ui, tr!(ui.ctx(), "Muted note —"));
+            if ui
+                .link(RichText::new(tr!(ui.ctx(), "show")).size(10.0))
+                .clicked()
+            {
+                ui.data_mut(|d| d.insert_temp(shown_id, true));
+            }
+        });
+    })
+    .response;
+
+    NoteResponse {
+        response,
+        clicked_hashtag: None,
+        action: NoteAction::None,
+    }
+}
+
+fn render_note_actionbar(
+    ui: &mut egui::Ui,
+    note_key: NoteKey,
+    stats: &NoteStats,
+) -> egui::InnerResponse<NoteAction> {
+    direction::horizontal(ui, |ui| {
+        let mut action = NoteAction::None;
         let img_data = if ui.style().visuals.dark_mode {
             egui::include_image!("../../../assets/icons/reply.png")
         } else {
@@ -286,6 +787,7 @@ fn render_note_actionbar(ui: &mut egui::Ui) -> egui::InnerResponse<()> {
         };
 
         ui.spacing_mut().button_padding = egui::vec2(0.0, 0.0);
+        ui.spacing_mut().item_spacing.x = 24.0;
         if ui
             .add(
                 egui::Button::image(egui::Image::new(img_data).max_width(10.0))
@@ -293,36 +795,120 @@ fn render_note_actionbar(ui: &mut egui::Ui) -> egui::InnerResponse<()> {
                     .frame(false)
                     .fill(ui.style().visuals.panel_fill),
             )
+            .on_hover_text(tr!(ui.ctx(), "Reply"))
             .clicked()
-        {}
+        {
+            action = NoteAction::Reply;
+        }
+
+        if actionbar_button(ui, "🔁", "Repost", stats.reposts.into(), stats.reposted).clicked() {
+            action = NoteAction::Repost;
+        }
 
-        //if ui.add(egui::Button::new("like")).clicked() {}
+        let react = actionbar_button(ui, "🤙", "React", stats.reactions.into(), stats.reacted);
+        let picker_id = ui.id().with(("reaction_picker", note_key.as_u64()));
+        if react.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(picker_id));
+        }
+        egui::popup::popup_below_widget(ui, picker_id, &react, |ui| {
+            ui.horizontal(|ui| {
+                for emoji in DEFAULT_REACTIONS {
+                    if ui.button(emoji).clicked() {
+                        action = NoteAction::React(emoji.to_owned());
+                        ui.memory_mut(|m| m.close_popup());
+                    }
+                }
+            });
+        });
+
+        let zap = actionbar_button(ui, "⚡", "Zap", stats.zaps_msat / 1000, false);
+        let zap_id = ui.id().with(("zap_picker", note_key.as_u64()));
+        if let Some(zap) = zap::zap_picker(ui, zap_id, &zap) {
+            action = zap;
+        }
+
+        action
     })
 }
 
+/// An action bar button with its count next to it. Zero counts are left
+/// off, and buttons for things the user has already done are highlighted.
+fn actionbar_button(
+    ui: &mut egui::Ui,
+    icon: &str,
+    tooltip: &str,
+    count: u64,
+    active: bool,
+) -> egui::Response {
+    let text = if count == 0 {
+        icon.to_owned()
+    } else {
+        format!("{} {}", icon, ui::fmt::compact_number(count))
+    };
+    let theme = NotedeckTheme::get(ui.ctx());
+    let color = if active {
+        theme.accent
+    } else {
+        theme.muted_text
+    };
+
+    ui.add(egui::Button::new(RichText::new(text).size(11.0).color(color)).frame(false))
+        .on_hover_text(tr!(ui.ctx(), tooltip))
+}
+
 fn secondary_label(ui: &mut egui::Ui, s: impl Into<String>) {
+    let theme = NotedeckTheme::get(ui.ctx());
     ui.add(Label::new(
-        RichText::new(s).size(10.0).color(colors::GRAY_SECONDARY),
+        RichText::new(s).size(10.0).color(theme.muted_text),
     ));
 }
 
 fn render_reltime(
     ui: &mut egui::Ui,
     note_cache: &mut crate::notecache::NoteCache,
+    created_at: u64,
+    note_id: &[u8; 32],
+    seen_on: &[String],
     before: bool,
 ) -> egui::InnerResponse<()> {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
+    let reltime = note_cache
+        .reltime()
+        .format(&ui::i18n::Strings::get_for(ui.ctx()));
     ui.horizontal(|ui| {
         if before {
             secondary_label(ui, "⋅");
         }
 
-        secondary_label(ui, note_cache.reltime_str());
+        ui.add(
+            ui::time::Timestamp::new(created_at, &reltime)
+                .note_id(note_id)
+                .seen_on(seen_on),
+        );
 
         if !before {
             secondary_label(ui, "⋅");
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_lines_follow_the_parents_avatar() {
+        let avatar_center = ui::ProfilePic::default_size() / 2.0;
+        assert_eq!(thread_line_offset(1), avatar_center);
+        assert_eq!(thread_line_offset(2), THREAD_INDENT + avatar_center);
+
+        // past the limit the parent doesn't indent any less than its reply
+        let deep = MAX_THREAD_INDENT_DEPTH + 2;
+        assert_eq!(
+            thread_line_offset(deep),
+            thread_indent(deep) + avatar_center
+        );
+    }
+}
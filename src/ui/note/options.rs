@@ -7,6 +7,11 @@ bitflags! {
     pub struct NoteOptions: u32 {
         const actionbar     = 0b00000001;
         const note_previews = 0b00000010;
+        const bold_author   = 0b00000100;
+        const media         = 0b00001000;
+        const markdown      = 0b00010000;
+        const link_previews = 0b00100000;
+        const selectable_text = 0b01000000;
     }
 }
 
@@ -21,6 +26,31 @@ impl NoteOptions {
         (self & NoteOptions::note_previews) == NoteOptions::note_previews
     }
 
+    #[inline]
+    pub fn has_bold_author(self) -> bool {
+        (self & NoteOptions::bold_author) == NoteOptions::bold_author
+    }
+
+    #[inline]
+    pub fn has_media(self) -> bool {
+        (self & NoteOptions::media) == NoteOptions::media
+    }
+
+    #[inline]
+    pub fn has_markdown(self) -> bool {
+        (self & NoteOptions::markdown) == NoteOptions::markdown
+    }
+
+    #[inline]
+    pub fn has_link_previews(self) -> bool {
+        (self & NoteOptions::link_previews) == NoteOptions::link_previews
+    }
+
+    #[inline]
+    pub fn has_selectable_text(self) -> bool {
+        (self & NoteOptions::selectable_text) == NoteOptions::selectable_text
+    }
+
     #[inline]
     pub fn set_note_previews(&mut self, enable: bool) {
         if enable {
@@ -38,4 +68,49 @@ impl NoteOptions {
             *self &= !NoteOptions::actionbar;
         }
     }
+
+    #[inline]
+    pub fn set_bold_author(&mut self, enable: bool) {
+        if enable {
+            *self |= NoteOptions::bold_author;
+        } else {
+            *self &= !NoteOptions::bold_author;
+        }
+    }
+
+    #[inline]
+    pub fn set_media(&mut self, enable: bool) {
+        if enable {
+            *self |= NoteOptions::media;
+        } else {
+            *self &= !NoteOptions::media;
+        }
+    }
+
+    #[inline]
+    pub fn set_markdown(&mut self, enable: bool) {
+        if enable {
+            *self |= NoteOptions::markdown;
+        } else {
+            *self &= !NoteOptions::markdown;
+        }
+    }
+
+    #[inline]
+    pub fn set_link_previews(&mut self, enable: bool) {
+        if enable {
+            *self |= NoteOptions::link_previews;
+        } else {
+            *self &= !NoteOptions::link_previews;
+        }
+    }
+
+    #[inline]
+    pub fn set_selectable_text(&mut self, enable: bool) {
+        if enable {
+            *self |= NoteOptions::selectable_text;
+        } else {
+            *self &= !NoteOptions::selectable_text;
+        }
+    }
 }
@@ -0,0 +1,107 @@
+use crate::ui::i18n::tr;
+use crate::ui::note::contents::tag_str;
+use crate::ui::theme::NotedeckTheme;
+use egui::{Align2, CursorIcon, FontId, Sense};
+use nostrdb::Note;
+
+/// Hidden notes are at least this tall, so the overlay's text fits
+const MIN_HEIGHT: f32 = 56.0;
+
+/// The reason given by a NIP-36 `content-warning` tag, or `None` if it
+/// doesn't have one. A warning without a reason is an empty string.
+pub fn content_warning(note: &Note) -> Option<String> {
+    note.tags().iter().find_map(|tag| {
+        let fields = (0..tag.count()).filter_map(|i| tag_str(&tag, i));
+        warning_reason(fields)
+    })
+}
+
+fn warning_reason<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<String> {
+    if fields.next()? != "content-warning" {
+        return None;
+    }
+    Some(fields.next().unwrap_or_default().to_owned())
+}
+
+/// Show `add_contents` behind a frosted panel until it's clicked, which is
+/// remembered under `id`. `add_contents` is told whether the contents have
+/// been revealed, so it can hold off on loading media until they are.
+///
+/// egui can't blur what's already been painted, so while hidden the
+/// contents are drawn disabled and then covered with a mostly opaque panel
+/// saying why.
+pub fn sensitive_ui<R>(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    reason: &str,
+    add_contents: impl FnOnce(&mut egui::Ui, bool) -> R,
+) -> R {
+    let revealed = ui.data(|d| d.get_temp(id)).unwrap_or(false);
+    if revealed {
+        return add_contents(ui, true);
+    }
+
+    let inner = ui.add_enabled_ui(false, |ui| {
+        ui.set_min_height(MIN_HEIGHT);
+        add_contents(ui, false)
+    });
+    let rect = inner.response.rect;
+
+    let theme = NotedeckTheme::get(ui.ctx());
+    let painter = ui.painter_at(rect.expand(2.0));
+    painter.rect_filled(rect, 8.0, ui.visuals().panel_fill.gamma_multiply(0.96));
+    painter.rect_stroke(rect, 8.0, (1.0, theme.surface));
+
+    let title = tr!(ui.ctx(), "Sensitive content — tap to reveal");
+    if reason.is_empty() {
+        painter.text(
+            rect.center(),
+            Align2::CENTER_CENTER,
+            &title,
+            FontId::proportional(14.0),
+            ui.visuals().strong_text_color(),
+        );
+    } else {
+        painter.text(
+            rect.center() - egui::vec2(0.0, 9.0),
+            Align2::CENTER_CENTER,
+            title,
+            FontId::proportional(14.0),
+            ui.visuals().strong_text_color(),
+        );
+        painter.text(
+            rect.center() + egui::vec2(0.0, 9.0),
+            Align2::CENTER_CENTER,
+            reason,
+            FontId::proportional(12.0),
+            theme.muted_text,
+        );
+    }
+
+    // added after the contents, so it gets the click instead of them
+    let response = ui
+        .interact(rect, id.with("reveal"), Sense::click())
+        .on_hover_cursor(CursorIcon::PointingHand);
+    if response.clicked() {
+        ui.data_mut(|d| d.insert_temp(id, true));
+    }
+
+    inner.inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_warning_tags() {
+        let reason = |fields: &[&str]| warning_reason(fields.iter().copied());
+        assert_eq!(
+            reason(&["content-warning", "spoilers"]),
+            Some("spoilers".to_owned())
+        );
+        assert_eq!(reason(&["content-warning"]), Some(String::new()));
+        assert_eq!(reason(&["t", "content-warning"]), None);
+        assert_eq!(reason(&[]), None);
+    }
+}
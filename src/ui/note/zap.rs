@@ -0,0 +1,145 @@
+use crate::ui;
+use crate::ui::i18n::tr;
+use crate::ui::note::NoteAction;
+use crate::ui::theme::NotedeckTheme;
+use egui::{vec2, Key, Order, RichText, TextEdit};
+
+/// The amounts offered in the zap picker, in sats
+pub const ZAP_PRESETS: [u64; 3] = [21, 100, 1000];
+
+/// The longest comment the picker lets you attach. LNURL servers have
+/// their own limit, which is up to the host to check.
+pub const MAX_ZAP_COMMENT_LEN: usize = 140;
+
+/// What's been typed into an open picker
+#[derive(Debug, Clone, Default)]
+struct ZapPicker {
+    custom: String,
+    comment: String,
+}
+
+/// A custom amount of sats in millisats, if it's a positive whole number
+pub fn parse_sats(s: &str) -> Option<u64> {
+    let sats: u64 = s.trim().parse().ok()?;
+    if sats == 0 {
+        return None;
+    }
+    sats.checked_mul(1000)
+}
+
+/// A picker below the note's zap button, toggled by clicking it, with
+/// preset amounts, a custom amount and a comment. Returns
+/// [`NoteAction::Zap`] once an amount has been picked.
+pub fn zap_picker(ui: &mut egui::Ui, id: egui::Id, button: &egui::Response) -> Option<NoteAction> {
+    if button.clicked() {
+        if ui.data(|d| d.get_temp::<ZapPicker>(id)).is_some() {
+            ui.data_mut(|d| d.remove::<ZapPicker>(id));
+        } else {
+            ui.data_mut(|d| d.insert_temp(id, ZapPicker::default()));
+        }
+    }
+
+    let mut picker: ZapPicker = ui.data(|d| d.get_temp(id))?;
+    let theme = NotedeckTheme::get(ui.ctx());
+    let mut msat = None;
+
+    let area = egui::Area::new(id)
+        .order(Order::Foreground)
+        .fixed_pos(button.rect.left_bottom() + vec2(0.0, 4.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::menu(ui.style()).show(ui, |ui| {
+                ui.set_max_width(220.0);
+
+                ui.horizontal(|ui| {
+                    for sats in ZAP_PRESETS {
+                        let label = format!("⚡ {}", ui::fmt::compact_number(sats));
+                        if ui.button(label).clicked() {
+                            msat = Some(sats * 1000);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let field = ui.add(
+                        TextEdit::singleline(&mut picker.custom)
+                            .hint_text(tr!(ui.ctx(), "sats"))
+                            .desired_width(100.0),
+                    );
+                    let custom = parse_sats(&picker.custom);
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                    if ui
+                        .add_enabled(custom.is_some(), egui::Button::new(tr!(ui.ctx(), "Zap")))
+                        .clicked()
+                        || entered
+                    {
+                        msat = msat.or(custom);
+                    }
+                });
+                if !picker.custom.trim().is_empty() && parse_sats(&picker.custom).is_none() {
+                    ui.label(
+                        RichText::new(tr!(ui.ctx(), "Enter a whole number of sats"))
+                            .size(11.0)
+                            .color(theme.error),
+                    );
+                }
+
+                ui.add(
+                    TextEdit::multiline(&mut picker.comment)
+                        .hint_text(tr!(ui.ctx(), "Comment (optional)"))
+                        .char_limit(MAX_ZAP_COMMENT_LEN)
+                        .desired_rows(2),
+                );
+                ui.label(
+                    RichText::new(format!(
+                        "{}/{}",
+                        picker.comment.chars().count(),
+                        MAX_ZAP_COMMENT_LEN
+                    ))
+                    .size(10.0)
+                    .color(theme.muted_text),
+                );
+            });
+        })
+        .response;
+
+    // clicks on the button itself are handled as toggles above
+    let clicked_elsewhere = ui.input(|i| {
+        i.pointer.any_pressed()
+            && i.pointer
+                .interact_pos()
+                .is_some_and(|pos| !area.rect.contains(pos) && !button.rect.contains(pos))
+    });
+
+    if let Some(msat) = msat {
+        ui.data_mut(|d| d.remove::<ZapPicker>(id));
+        let comment = picker.comment.trim();
+        return Some(NoteAction::Zap {
+            msat,
+            comment: (!comment.is_empty()).then(|| comment.to_owned()),
+        });
+    }
+
+    if clicked_elsewhere || ui.input(|i| i.key_pressed(Key::Escape)) {
+        ui.data_mut(|d| d.remove::<ZapPicker>(id));
+    } else {
+        ui.data_mut(|d| d.insert_temp(id, picker));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_amounts_are_positive_whole_sats() {
+        assert_eq!(parse_sats("21"), Some(21_000));
+        assert_eq!(parse_sats(" 5000 "), Some(5_000_000));
+        assert_eq!(parse_sats("0"), None);
+        assert_eq!(parse_sats("-5"), None);
+        assert_eq!(parse_sats("2.5"), None);
+        assert_eq!(parse_sats("lots"), None);
+        assert_eq!(parse_sats(""), None);
+        assert_eq!(parse_sats(&u64::MAX.to_string()), None);
+    }
+}
@@ -0,0 +1,212 @@
+use nostr_sdk::ToBech32;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+// Made up, but representative, notes and profiles for previews and tests.
+// Everything here is deterministic: the same builder always gives the same
+// data, down to the event id.
+
+/// The author of every mock note
+pub const MOCK_PUBKEY: &str = "32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245";
+
+/// Who mock notes mention
+pub const MOCK_MENTIONED_PUBKEY: &str =
+    "3efdaebb1d8923ebd99c9e7ace3b4194ab45512e2be79c1b7d68d9243e0d2681";
+
+pub const MOCK_CREATED_AT: u64 = 1_700_000_000;
+
+pub const MOCK_IMAGE_URL: &str = "https://cdn.jb55.com/img/red-me.jpg";
+
+pub const MOCK_BROKEN_IMAGE_URL: &str = "https://example.invalid/missing.jpg";
+
+/// The edge cases a mock can cover
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MockVariant {
+    #[default]
+    Typical,
+    /// A profile with a `name` but no `display_name`
+    NoDisplayName,
+    /// A profile whose picture doesn't load
+    BrokenAvatar,
+    /// A note written in right to left script
+    Rtl,
+}
+
+/// An event, as it would be sent to a relay. Mocks aren't signed, so the
+/// signature is all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockNote {
+    pub pubkey: String,
+    pub created_at: u64,
+    pub kind: u32,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+}
+
+impl MockNote {
+    /// The NIP-01 id of the event, in hex
+    pub fn id(&self) -> String {
+        let serialized = json!([
+            0,
+            self.pubkey,
+            self.created_at,
+            self.kind,
+            self.tags,
+            self.content
+        ])
+        .to_string();
+        hex::encode(Sha256::digest(serialized.as_bytes()))
+    }
+
+    pub fn to_json(&self) -> String {
+        json!({
+            "id": self.id(),
+            "pubkey": self.pubkey,
+            "created_at": self.created_at,
+            "kind": self.kind,
+            "tags": self.tags,
+            "content": self.content,
+            "sig": "0".repeat(128),
+        })
+        .to_string()
+    }
+}
+
+/// The metadata in a kind 0 event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockProfile {
+    pub pubkey: String,
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub picture: Option<String>,
+    pub nip05: Option<String>,
+    pub about: Option<String>,
+}
+
+impl MockProfile {
+    /// The profile as a kind 0 event
+    pub fn to_note(&self) -> MockNote {
+        let content = json!({
+            "name": self.name,
+            "display_name": self.display_name,
+            "picture": self.picture,
+            "nip05": self.nip05,
+            "about": self.about,
+        });
+
+        MockNote {
+            pubkey: self.pubkey.clone(),
+            created_at: MOCK_CREATED_AT,
+            kind: 0,
+            tags: vec![],
+            content: content.to_string(),
+        }
+    }
+}
+
+fn tag(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|part| part.to_string()).collect()
+}
+
+/// A `nostr:npub...` mention of [`MOCK_MENTIONED_PUBKEY`]
+pub fn mock_mention() -> String {
+    let npub = nostr_sdk::PublicKey::from_hex(MOCK_MENTIONED_PUBKEY)
+        .expect("valid pubkey")
+        .to_bech32()
+        .expect("bech32");
+    format!("nostr:{}", npub)
+}
+
+/// A text note with a mention, a link, a hashtag and an image
+pub fn mock_note() -> MockNote {
+    mock_note_variant(MockVariant::Typical)
+}
+
+pub fn mock_note_variant(variant: MockVariant) -> MockNote {
+    let content = match variant {
+        MockVariant::Rtl => format!(
+            "שלום {} ברוכים הבאים ל https://damus.io #nostr\n{}",
+            mock_mention(),
+            MOCK_IMAGE_URL
+        ),
+        MockVariant::Typical | MockVariant::NoDisplayName | MockVariant::BrokenAvatar => {
+            format!(
+                "gm {}! check out https://damus.io #nostr\n{}",
+                mock_mention(),
+                MOCK_IMAGE_URL
+            )
+        }
+    };
+
+    MockNote {
+        pubkey: MOCK_PUBKEY.to_owned(),
+        created_at: MOCK_CREATED_AT,
+        kind: 1,
+        tags: vec![
+            tag(&["p", MOCK_MENTIONED_PUBKEY]),
+            tag(&["t", "nostr"]),
+            tag(&[
+                "imeta",
+                &format!("url {}", MOCK_IMAGE_URL),
+                "m image/jpeg",
+                "dim 1024x768",
+            ]),
+        ],
+        content,
+    }
+}
+
+/// The author of the mock notes, with a picture and all their names
+pub fn mock_profile() -> MockProfile {
+    mock_profile_variant(MockVariant::Typical)
+}
+
+pub fn mock_profile_variant(variant: MockVariant) -> MockProfile {
+    let mut profile = MockProfile {
+        pubkey: MOCK_PUBKEY.to_owned(),
+        name: Some("jb55".to_owned()),
+        display_name: Some("Will".to_owned()),
+        picture: Some(MOCK_IMAGE_URL.to_owned()),
+        nip05: Some("_@jb55.com".to_owned()),
+        about: Some("I made damus, npubs and zaps.".to_owned()),
+    };
+
+    match variant {
+        MockVariant::Typical => {}
+        MockVariant::NoDisplayName => profile.display_name = None,
+        MockVariant::BrokenAvatar => profile.picture = Some(MOCK_BROKEN_IMAGE_URL.to_owned()),
+        MockVariant::Rtl => {
+            profile.display_name = Some("ויל".to_owned());
+            profile.about = Some("עשיתי את דאמוס".to_owned());
+        }
+    }
+
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mocks_are_deterministic() {
+        assert_eq!(mock_note(), mock_note());
+        assert_eq!(mock_note().id(), mock_note().id());
+        assert_eq!(mock_note().id().len(), 64);
+        assert_ne!(mock_note().id(), mock_note_variant(MockVariant::Rtl).id());
+    }
+
+    #[test]
+    fn mock_json_is_an_event() {
+        let note = mock_note();
+        let event: serde_json::Value = serde_json::from_str(&note.to_json()).unwrap();
+        assert_eq!(event["id"], note.id());
+        assert_eq!(event["kind"], 1);
+        assert!(note.content.contains(&mock_mention()));
+
+        let profile = mock_profile_variant(MockVariant::NoDisplayName).to_note();
+        let content: serde_json::Value = serde_json::from_str(&profile.content).unwrap();
+        assert!(content["display_name"].is_null());
+        assert_eq!(content["name"], "jb55");
+    }
+}
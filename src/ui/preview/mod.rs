@@ -0,0 +1,104 @@
+pub mod mock;
+
+use crate::app_creation::setup_ctx;
+use crate::imgcache::ImageCache;
+use crate::ui::snapshot::Canvas;
+use crate::ui::View;
+use egui::{ColorImage, RawInput, Rect};
+
+pub trait Preview {
+    type Prev: View;
+
+    fn preview() -> Self::Prev;
+
+    /// A preview that loads its images with `cache`. Previews without any
+    /// images just ignore it.
+    fn with_image_cache(cache: ImageCache) -> Self::Prev {
+        drop(cache);
+        Self::preview()
+    }
+}
+
+/// An image cache for previews, in a temp dir so previews don't fill up
+/// the app's own cache
+pub fn preview_image_cache() -> ImageCache {
+    let dir = std::env::temp_dir()
+        .join("notedeck-preview")
+        .join(ImageCache::rel_datadir());
+    let _ = std::fs::create_dir_all(&dir);
+    ImageCache::new(dir, ImageCache::default_max_bytes())
+}
+
+pub struct PreviewApp {
+    view: Box<dyn View>,
+}
+
+impl<V> From<V> for PreviewApp
+where
+    V: View + 'static,
+{
+    fn from(v: V) -> Self {
+        PreviewApp::new(v)
+    }
+}
+
+/// How many frames we lay out before taking a snapshot. Fonts set up in
+/// the first frame only take effect in the next one, and some widgets,
+/// like areas, are invisible until they've measured themselves.
+const SNAPSHOT_FRAMES: usize = 3;
+
+impl PreviewApp {
+    pub fn new(view: impl View + 'static) -> PreviewApp {
+        let view = Box::new(view);
+        Self { view }
+    }
+
+    fn draw(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| self.view.ui(ui));
+    }
+
+    /// Draw the preview into a `size` pixel image without a window, with
+    /// the same fonts and style as the app. Useful for comparing widgets
+    /// against committed snapshots.
+    pub fn render_to_image(&mut self, size: [u32; 2]) -> ColorImage {
+        let ctx = egui::Context::default();
+        ctx.set_pixels_per_point(1.0);
+        setup_ctx(&ctx);
+
+        let size = [size[0] as usize, size[1] as usize];
+        let input = RawInput {
+            screen_rect: Some(Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(size[0] as f32, size[1] as f32),
+            )),
+            ..Default::default()
+        };
+
+        let mut canvas = Canvas::new(size, ctx.style().visuals.panel_fill);
+        for frame in 0..SNAPSHOT_FRAMES {
+            let output = ctx.run(input.clone(), |ctx| self.draw(ctx));
+            canvas.set_textures(&output.textures_delta);
+            if frame == SNAPSHOT_FRAMES - 1 {
+                let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+                canvas.paint(&primitives);
+            }
+            canvas.free_textures(&output.textures_delta);
+        }
+
+        canvas.image
+    }
+}
+
+impl eframe::App for PreviewApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.draw(ctx);
+
+        // keep drawing until everything has loaded, so the preview isn't
+        // left blank waiting on an input event
+        if self.view.is_loading() {
+            ctx.request_repaint();
+        }
+    }
+}
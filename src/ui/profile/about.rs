@@ -0,0 +1,158 @@
+use crate::ui::expandable::expandable_text;
+use crate::ui::nip19::{self, Nip19Entity};
+use crate::ui::note::contents::split_trailing_punctuation;
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::{keys, ProfileSource};
+use egui::text::{LayoutJob, TextFormat};
+use std::ops::Range;
+
+/// How many lines of about text we show before it needs expanding
+pub const ABOUT_MAX_ROWS: usize = 3;
+
+/// A piece of a profile's about text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AboutSpan<'a> {
+    Text(&'a str),
+    Url(&'a str),
+    /// A `nostr:npub...` or `nostr:nprofile...` mention
+    Mention {
+        bech32: &'a str,
+        pubkey: [u8; 32],
+    },
+}
+
+/// The pubkey in a bech32 `npub` or `nprofile`
+fn mentioned_pubkey(bech32: &str) -> Option<[u8; 32]> {
+    match nip19::decode(bech32).ok()? {
+        Nip19Entity::Pubkey(pubkey) | Nip19Entity::Profile { pubkey, .. } => Some(pubkey),
+        _ => None,
+    }
+}
+
+/// The runs of non whitespace in `s`, with their byte offsets
+fn words(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut rest = 0;
+    std::iter::from_fn(move || {
+        let start = rest + s[rest..].find(|c: char| !c.is_whitespace())?;
+        let end = s[start..]
+            .find(char::is_whitespace)
+            .map_or(s.len(), |end| start + end);
+        rest = end;
+        Some((start, &s[start..end]))
+    })
+}
+
+/// Find the links and mentions in about text. About text isn't parsed into
+/// blocks by nostrdb like note content is, so we do it here.
+pub fn about_spans(about: &str) -> Vec<AboutSpan<'_>> {
+    let mut spans = vec![];
+    let mut text_start = 0;
+
+    for (start, word) in words(about) {
+        let (link, _) = split_trailing_punctuation(word);
+        let span = if let Some(bech32) = link.strip_prefix("nostr:") {
+            match mentioned_pubkey(bech32) {
+                Some(pubkey) => AboutSpan::Mention {
+                    bech32: link,
+                    pubkey,
+                },
+                None => continue,
+            }
+        } else if ["https://", "http://"]
+            .iter()
+            .any(|scheme| link.len() > scheme.len() && link.starts_with(scheme))
+        {
+            AboutSpan::Url(link)
+        } else {
+            continue;
+        };
+
+        if text_start < start {
+            spans.push(AboutSpan::Text(&about[text_start..start]));
+        }
+        spans.push(span);
+        text_start = start + link.len();
+    }
+
+    if text_start < about.len() {
+        spans.push(AboutSpan::Text(&about[text_start..]));
+    }
+
+    spans
+}
+
+/// Show about text clamped to a few lines, with links that open and
+/// mentions shown by name, like they are in notes. `id` remembers whether
+/// it's been expanded.
+pub fn about_ui(
+    ui: &mut egui::Ui,
+    id: egui::Id,
+    about: &str,
+    profiles: Option<&dyn ProfileSource>,
+) -> egui::Response {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let text = TextFormat::simple(font_id.clone(), ui.visuals().text_color());
+    let link = TextFormat::simple(font_id, NotedeckTheme::get(ui.ctx()).link);
+
+    let mut job = LayoutJob::default();
+    let mut urls: Vec<(Range<usize>, &str)> = vec![];
+    for span in about_spans(about) {
+        match span {
+            AboutSpan::Text(s) => job.append(s, 0.0, text.clone()),
+            AboutSpan::Url(url) => {
+                let start = job.text.len();
+                job.append(url, 0.0, link.clone());
+                urls.push((start..job.text.len(), url));
+            }
+            AboutSpan::Mention { bech32, pubkey } => {
+                let name = profiles
+                    .and_then(|profiles| profiles.get(&pubkey))
+                    .and_then(|data| data.username().map(str::to_owned))
+                    .unwrap_or_else(|| {
+                        keys::shorten_bech32(bech32, keys::PREFIX_LEN, keys::SUFFIX_LEN)
+                    });
+                job.append(&format!("@{}", name), 0.0, link.clone());
+            }
+        }
+    }
+
+    let clamped = expandable_text(ui, id, job, ABOUT_MAX_ROWS);
+    if let Some(at) = clamped.clicked_at {
+        if let Some((_, url)) = urls.iter().find(|(range, _)| range.contains(&at)) {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(*url));
+        }
+    }
+
+    clamped.response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::preview::mock::{mock_mention, MOCK_MENTIONED_PUBKEY};
+
+    #[test]
+    fn finds_links_and_mentions() {
+        let about = format!("dev at https://damus.io. friends with {}!", mock_mention());
+        let spans = about_spans(&about);
+
+        assert_eq!(spans[0], AboutSpan::Text("dev at "));
+        assert_eq!(spans[1], AboutSpan::Url("https://damus.io"));
+        assert_eq!(spans[2], AboutSpan::Text(". friends with "));
+        match spans[3] {
+            AboutSpan::Mention { pubkey, .. } => {
+                assert_eq!(hex::encode(pubkey), MOCK_MENTIONED_PUBKEY)
+            }
+            ref other => panic!("expected a mention, got {:?}", other),
+        }
+        assert_eq!(spans[4], AboutSpan::Text("!"));
+    }
+
+    #[test]
+    fn leaves_plain_text_alone() {
+        assert_eq!(
+            about_spans("just https:// and nostr:nope"),
+            vec![AboutSpan::Text("just https:// and nostr:nope")]
+        );
+    }
+}
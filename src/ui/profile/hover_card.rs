@@ -0,0 +1,200 @@
+use crate::app_style::NotedeckTextStyle;
+use crate::imgcache::ImageCache;
+use crate::profile::usable_name;
+use crate::ui::keys::short_npub;
+use crate::ui::profile::ProfileSource;
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::ProfilePic;
+use egui::{Order, Pos2, RichText};
+use nostrdb::ProfileRecord;
+
+/// How long the pointer has to rest on a name before its card opens
+const HOVER_DELAY: f64 = 0.4;
+
+/// How long the card stays open after the pointer leaves, so it can be
+/// moved from the name into the card without it closing
+const LEAVE_GRACE: f64 = 0.3;
+
+const CARD_WIDTH: f32 = 300.0;
+
+/// What a profile hover card shows. Callers fill this in from whatever they
+/// know about the profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileData {
+    pub name: Option<String>,
+    pub display_name: Option<String>,
+    pub picture: Option<String>,
+    pub nip05: Option<String>,
+    pub about: Option<String>,
+    /// eg. "Follows you" or "Followed by 3 people you follow"
+    pub follower_hint: Option<String>,
+}
+
+impl ProfileData {
+    pub fn from_record(record: &ProfileRecord<'_>) -> Self {
+        let Some(profile) = record.record().profile() else {
+            return ProfileData::default();
+        };
+        let owned = |s: Option<&str>| s.and_then(usable_name).map(str::to_owned);
+
+        ProfileData {
+            name: owned(profile.name()),
+            display_name: owned(profile.display_name()),
+            picture: profile.picture().map(str::to_owned),
+            nip05: owned(profile.nip05()),
+            about: profile.about().map(str::to_owned),
+            follower_hint: None,
+        }
+    }
+
+    /// What to call them in an @mention: their name, falling back to their
+    /// display name
+    pub fn username(&self) -> Option<&str> {
+        self.name.as_deref().or(self.display_name.as_deref())
+    }
+}
+
+/// Per widget hover state, kept in egui memory between frames
+#[derive(Clone)]
+struct HoverState {
+    pubkey: [u8; 32],
+    /// Looked up once when the hover starts, so the card doesn't change
+    /// under the pointer
+    data: Option<ProfileData>,
+    anchor: Pos2,
+    hover_start: f64,
+    last_hovered: f64,
+}
+
+/// A floating profile card that opens after hovering `response` for a
+/// moment, and stays open while the pointer is over it
+pub struct ProfileHoverCard<'a> {
+    pubkey: &'a [u8; 32],
+    profiles: &'a dyn ProfileSource,
+}
+
+impl<'a> ProfileHoverCard<'a> {
+    pub fn new(pubkey: &'a [u8; 32], profiles: &'a dyn ProfileSource) -> Self {
+        ProfileHoverCard { pubkey, profiles }
+    }
+
+    /// Show the card for `response`, the widget showing the profile's name
+    pub fn show(self, ui: &egui::Ui, response: &egui::Response, img_cache: &mut ImageCache) {
+        let id = response.id.with("profile_hover_card");
+        let now = ui.input(|i| i.time);
+        let mut state: Option<HoverState> = ui.data(|d| d.get_temp(id));
+
+        if response.hovered() {
+            if !state.as_ref().is_some_and(|s| s.pubkey == *self.pubkey) {
+                state = Some(HoverState {
+                    pubkey: *self.pubkey,
+                    data: self.profiles.get(self.pubkey),
+                    anchor: response.rect.left_bottom(),
+                    hover_start: now,
+                    last_hovered: now,
+                });
+            }
+            if let Some(state) = &mut state {
+                state.last_hovered = now;
+            }
+        }
+
+        let Some(mut state) = state else {
+            return;
+        };
+
+        let waited = now - state.hover_start;
+        if waited < HOVER_DELAY {
+            if now - state.last_hovered > LEAVE_GRACE {
+                ui.data_mut(|d| d.remove::<HoverState>(id));
+            } else {
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(
+                        HOVER_DELAY - waited,
+                    ));
+                ui.data_mut(|d| d.insert_temp(id, state));
+            }
+            return;
+        }
+
+        let card = egui::Area::new(id)
+            .order(Order::Tooltip)
+            .fixed_pos(state.anchor + egui::vec2(0.0, 4.0))
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(CARD_WIDTH);
+                    card_ui(ui, img_cache, &state.pubkey, state.data.as_ref());
+                });
+            })
+            .response;
+
+        let in_card = ui
+            .input(|i| i.pointer.hover_pos())
+            .is_some_and(|pos| card.rect.contains(pos));
+        if in_card {
+            state.last_hovered = now;
+        }
+
+        if now - state.last_hovered > LEAVE_GRACE {
+            ui.data_mut(|d| d.remove::<HoverState>(id));
+        } else {
+            if !in_card && !response.hovered() {
+                // close once the grace period is up, even if nothing moves
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(LEAVE_GRACE));
+            }
+            ui.data_mut(|d| d.insert_temp(id, state));
+        }
+    }
+}
+
+fn card_ui(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    pubkey: &[u8; 32],
+    data: Option<&ProfileData>,
+) {
+    let picture = data
+        .and_then(|data| data.picture.as_deref())
+        .unwrap_or(ProfilePic::no_pfp_url());
+
+    let theme = NotedeckTheme::get(ui.ctx());
+    let name = data.and_then(|data| data.display_name.as_deref().or(data.name.as_deref()));
+    ui.horizontal(|ui| {
+        ui.add(
+            ProfilePic::new(img_cache, picture)
+                .size(48.0)
+                .pubkey(pubkey)
+                .alt(ProfilePic::alt_for(ui.ctx(), name)),
+        );
+
+        ui.vertical(|ui| {
+            let display_name = data.and_then(|data| data.display_name.as_deref());
+            let name = data.and_then(|data| data.name.as_deref());
+            let heading = display_name
+                .or(name)
+                .map_or_else(|| short_npub(pubkey), str::to_owned);
+            ui.label(RichText::new(heading).text_style(NotedeckTextStyle::Heading3.text_style()));
+
+            if let (Some(_), Some(name)) = (display_name, name) {
+                ui.label(
+                    RichText::new(format!("@{}", name))
+                        .size(12.0)
+                        .color(theme.muted_text),
+                );
+            }
+
+            if let Some(nip05) = data.and_then(|data| data.nip05.as_deref()) {
+                ui.label(RichText::new(nip05).size(12.0).color(theme.accent));
+            }
+        });
+    });
+
+    if let Some(about) = data.and_then(|data| data.about.as_deref()) {
+        ui.label(about);
+    }
+
+    if let Some(hint) = data.and_then(|data| data.follower_hint.as_deref()) {
+        ui.label(RichText::new(hint).size(12.0).color(theme.muted_text));
+    }
+}
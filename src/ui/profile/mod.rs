@@ -1,5 +1,12 @@
+pub mod about;
+pub mod hover_card;
 pub mod picture;
+pub mod prefetch;
 pub mod preview;
+pub mod source;
 
-pub use picture::ProfilePic;
+pub use hover_card::{ProfileData, ProfileHoverCard};
+pub use picture::{ProfilePic, ProfilePicAction, ProfilePicResponse};
+pub use prefetch::ProfileRequests;
 pub use preview::ProfilePreview;
+pub use source::{MemoryProfiles, NdbProfiles, ProfileSource};
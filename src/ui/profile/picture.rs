@@ -1,23 +1,73 @@
+use crate::images::{self, AvatarShape, FetchOptions, ImageType, Priority, ResizeQuality};
 use crate::imgcache::ImageCache;
-use crate::ui::{Preview, View};
-use egui::{vec2, Sense, TextureHandle};
+use crate::ui::i18n::tr;
+use crate::ui::nip19;
+use crate::ui::note::context::long_pressed;
+use crate::ui::{anim, is_mobile, Preview, View};
+use egui::{vec2, Color32, Order, Pos2, Rect, Sense, Stroke, TextureHandle};
+use poll_promise::Promise;
+
+/// Pictures are fetched in steps of this many pixels, so hover animations
+/// and sizes that are nearly the same share a texture
+const FETCH_STEP: u32 = 32;
+
+/// The space between a picture and its ring
+const RING_GAP: f32 = 2.0;
 
 pub struct ProfilePic<'cache, 'url> {
     cache: &'cache mut ImageCache,
     url: &'url str,
     size: f32,
+    blurhash: Option<&'url str>,
+    pubkey: Option<&'url [u8; 32]>,
+    quality: ResizeQuality,
+    shape: AvatarShape,
+    animate: bool,
+    ring: Option<Stroke>,
+    alt: Option<String>,
+    interactive: bool,
+    priority: Priority,
+}
+
+/// Something done to an interactive [`ProfilePic`]. Opening the profile
+/// and muting are up to the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilePicAction {
+    OpenProfile,
+    Mute,
+}
+
+pub struct ProfilePicResponse {
+    pub response: egui::Response,
+    pub action: Option<ProfilePicAction>,
+    /// Whether a long press (or a right click on desktop) opened the quick
+    /// actions menu this frame, rather than the picture being clicked
+    pub long_pressed: bool,
 }
 
 impl<'cache, 'url> egui::Widget for ProfilePic<'cache, 'url> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        render_pfp(ui, self.cache, self.url, self.size)
+        self.show(ui).response
     }
 }
 
 impl<'cache, 'url> ProfilePic<'cache, 'url> {
     pub fn new(cache: &'cache mut ImageCache, url: &'url str) -> Self {
         let size = Self::default_size();
-        ProfilePic { cache, url, size }
+        ProfilePic {
+            cache,
+            url,
+            size,
+            blurhash: None,
+            pubkey: None,
+            quality: ResizeQuality::default(),
+            shape: AvatarShape::default(),
+            animate: false,
+            ring: None,
+            alt: None,
+            interactive: true,
+            priority: Priority::Low,
+        }
     }
 
     pub fn default_size() -> f32 {
@@ -32,52 +82,377 @@ impl<'cache, 'url> ProfilePic<'cache, 'url> {
         self.size = size;
         self
     }
+
+    /// Show this blurhash while the picture is loading
+    pub fn blurhash(mut self, blurhash: &'url str) -> Self {
+        self.blurhash = Some(blurhash);
+        self
+    }
+
+    /// The owner of this picture. If the picture fails to load we draw an
+    /// identicon derived from this key instead.
+    pub fn pubkey(mut self, pubkey: &'url [u8; 32]) -> Self {
+        self.pubkey = Some(pubkey);
+        self
+    }
+
+    /// The resize filter used when downscaling the downloaded picture
+    pub fn quality(mut self, quality: ResizeQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Draw the picture as a circle (the default), a square or a rounded
+    /// rectangle
+    pub fn shape(mut self, shape: AvatarShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// Play animated pictures instead of showing a single frame. Off by
+    /// default, since every frame is a texture and timelines show a lot of
+    /// pictures. The still picture is shown until the animation loads, or
+    /// if it fails to.
+    pub fn animate(mut self, animate: bool) -> Self {
+        self.animate = animate;
+        self
+    }
+
+    /// Draw a ring around the picture, a little way out from its edge, in
+    /// the same shape as the picture. It's drawn outside the picture's
+    /// area, so adding one doesn't move anything around.
+    pub fn ring(mut self, color: Color32, width: f32) -> Self {
+        self.ring = Some(Stroke::new(width, color));
+        self
+    }
+
+    /// What screen readers say the picture is. Defaults to "profile
+    /// picture"; see [`ProfilePic::alt_for`].
+    pub fn alt(mut self, alt: impl Into<String>) -> Self {
+        self.alt = Some(alt.into());
+        self
+    }
+
+    /// How soon to download the picture when lots of images are loading.
+    /// Avatars are [`Priority::Low`] by default, since there are so many.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Whether the picture can be clicked to open the profile, and long
+    /// pressed for a menu of quick actions. On by default; turn it off where
+    /// something around the picture, like a note, handles the click.
+    pub fn interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    pub fn show(self, ui: &mut egui::Ui) -> ProfilePicResponse {
+        let interactive = self.interactive;
+        let pubkey = self.pubkey.copied();
+        let menu_id = ui.id().with(("pfp_menu", self.url, pubkey));
+        let response = render_pfp(ui, self);
+        if !interactive {
+            return ProfilePicResponse {
+                response,
+                action: None,
+                long_pressed: false,
+            };
+        }
+
+        let response = response
+            .interact(Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        let menu_open = ui.data(|d| d.get_temp::<Pos2>(menu_id)).is_some();
+
+        let requested = !menu_open
+            && if is_mobile(ui.ctx()) {
+                long_pressed(ui, response.rect)
+            } else {
+                response.secondary_clicked()
+            };
+        if requested {
+            if let Some(pos) = ui.input(|i| i.pointer.interact_pos()) {
+                ui.data_mut(|d| d.insert_temp(menu_id, pos));
+            }
+        }
+
+        let action = if menu_open {
+            quick_actions_menu(ui, menu_id, pubkey.as_ref())
+        } else if response.clicked() {
+            Some(ProfilePicAction::OpenProfile)
+        } else {
+            None
+        };
+
+        ProfilePicResponse {
+            response,
+            action,
+            long_pressed: requested,
+        }
+    }
+
+    /// Alt text for someone's picture, naming them if we can
+    pub fn alt_for(ctx: &egui::Context, name: Option<&str>) -> String {
+        match name {
+            Some(name) => tr!(ctx, "profile picture for {}", name),
+            None => tr!(ctx, "profile picture").into_owned(),
+        }
+    }
+}
+
+/// The menu a long press opens: view the profile, or copy or mute the
+/// pubkey if we know it. The press that opened it ends in a click, which
+/// is ignored since the menu is open by then.
+fn quick_actions_menu(
+    ui: &mut egui::Ui,
+    menu_id: egui::Id,
+    pubkey: Option<&[u8; 32]>,
+) -> Option<ProfilePicAction> {
+    let pos: Pos2 = ui.data(|d| d.get_temp(menu_id))?;
+
+    let mut action = None;
+    let mut copied = None;
+    let menu = egui::Area::new(menu_id)
+        .order(Order::Foreground)
+        .fixed_pos(pos)
+        .show(ui.ctx(), |ui| {
+            egui::Frame::menu(ui.style()).show(ui, |ui| {
+                if ui.button(tr!(ui.ctx(), "View profile")).clicked() {
+                    action = Some(ProfilePicAction::OpenProfile);
+                }
+                if let Some(pubkey) = pubkey {
+                    if ui.button(tr!(ui.ctx(), "Copy npub")).clicked() {
+                        copied = nip19::npub(pubkey).ok();
+                    }
+                    ui.separator();
+                    if ui.button(tr!(ui.ctx(), "Mute")).clicked() {
+                        action = Some(ProfilePicAction::Mute);
+                    }
+                }
+            });
+        })
+        .response;
+
+    let clicked_elsewhere = ui.input(|i| {
+        i.pointer.any_pressed()
+            && i.pointer
+                .interact_pos()
+                .is_some_and(|pos| !menu.rect.contains(pos))
+    });
+
+    let chosen = action.is_some() || copied.is_some();
+    if let Some(text) = copied {
+        ui.output_mut(|o| o.copied_text = text);
+    }
+    if chosen || clicked_elsewhere || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        ui.data_mut(|d| d.remove::<Pos2>(menu_id));
+    }
+    action
 }
 
-fn render_pfp(
+/// Decode and upload a blurhash placeholder, caching the texture in the
+/// image cache alongside the real images.
+fn blurhash_texture(
     ui: &mut egui::Ui,
     img_cache: &mut ImageCache,
-    url: &str,
-    ui_size: f32,
-) -> egui::Response {
+    blurhash: &str,
+    shape: AvatarShape,
+) -> Option<TextureHandle> {
+    let key = format!("blurhash:{}:{}", shape.cache_tag(), blurhash);
+
+    if !img_cache.contains(&key) {
+        let texture = crate::blurhash::decode(blurhash, 32, 32).map(|mut img| {
+            images::apply_shape(&mut img, shape);
+            ui.ctx().load_texture(&key, img, Default::default())
+        });
+        img_cache.insert(key.clone(), Promise::from_ready(texture));
+    }
+
+    match img_cache.ready(&key) {
+        Some(Ok(texture)) => Some(texture.clone()),
+        _ => None,
+    }
+}
+
+fn render_pfp(ui: &mut egui::Ui, mut pfp: ProfilePic<'_, '_>) -> egui::Response {
+    let (ring, shape) = (pfp.ring, pfp.shape);
+    let alt = pfp
+        .alt
+        .take()
+        .unwrap_or_else(|| ProfilePic::alt_for(ui.ctx(), None));
+    let response = draw_pfp(ui, pfp);
+    if let Some(ring) = ring {
+        paint_ring(ui, response.rect, shape, ring);
+    }
+    crate::ui::alt_text(&response, &alt);
+    response
+}
+
+fn paint_ring(ui: &egui::Ui, rect: Rect, shape: AvatarShape, ring: Stroke) {
+    // the stroke is centered on the outline
+    let offset = RING_GAP + ring.width / 2.0;
+    let rounding = ring_rounding(shape, rect.width(), offset);
+    ui.painter()
+        .rect_stroke(rect.expand(offset), rounding, ring);
+}
+
+/// The corner radius of a ring `offset` out from a picture `size` across,
+/// so it follows the picture's corners
+fn ring_rounding(shape: AvatarShape, size: f32, offset: f32) -> f32 {
+    match shape {
+        AvatarShape::Circle => size / 2.0 + offset,
+        AvatarShape::Square => 0.0,
+        AvatarShape::RoundedRect { radius } => radius * size + offset,
+    }
+}
+
+fn draw_pfp(ui: &mut egui::Ui, pfp: ProfilePic<'_, '_>) -> egui::Response {
     #[cfg(feature = "profiling")]
     puffin::profile_function!();
 
-    // We will want to downsample these so it's not blurry on hi res displays
-    let img_size = 128u32;
+    let img_cache = pfp.cache;
+    let url = pfp.url;
+    let ui_size = pfp.size;
+    let shape = pfp.shape;
+    let img_size = fetch_size(ui_size, ui.ctx().pixels_per_point());
+    let key = memory_key(url, shape, img_size);
+    let options = FetchOptions {
+        size: img_size,
+        quality: pfp.quality,
+        img_type: ImageType::Profile(shape),
+        priority: pfp.priority,
+        ..Default::default()
+    };
+
+    if pfp.animate {
+        if let Some(frame) = animated_frame(ui, img_cache, url, options, shape) {
+            return pfp_image(ui, &frame, ui_size);
+        }
+    }
 
-    let m_cached_promise = img_cache.map().get(url);
-    if m_cached_promise.is_none() {
-        let res = crate::images::fetch_img(img_cache, ui.ctx(), url, img_size);
-        img_cache.map_mut().insert(url.to_owned(), res);
+    if !img_cache.contains(&key) {
+        let fetch = images::fetch_img(img_cache, ui.ctx(), url, options);
+        img_cache.insert_fetch(key.clone(), fetch);
+    }
+
+    // failures fall through to the regular error handling below
+    let failed = matches!(img_cache.ready(&key), Some(Err(_)));
+    if let Some(blurhash) = pfp.blurhash.filter(|_| !failed) {
+        if let Some(placeholder) = blurhash_texture(ui, img_cache, blurhash, shape) {
+            let loaded = match img_cache.ready(&key) {
+                Some(Ok(img)) => Some(img.clone()),
+                _ => None,
+            };
+            let fade = anim::fade_in(ui.ctx(), egui::Id::new(("pfp_fade", url)), loaded.is_some());
+            return pfp_crossfade(ui, &placeholder, loaded.as_ref(), ui_size, fade);
+        }
     }
 
-    match img_cache.map()[url].ready() {
-        None => ui.add(egui::Spinner::new().size(ui_size)),
+    match img_cache.ready(&key) {
+        None => match img_cache.dominant_color(url) {
+            Some(color) => color_placeholder(ui, color, ui_size, shape),
+            None => ui.add(egui::Spinner::new().size(ui_size)),
+        },
 
         // Failed to fetch profile!
-        Some(Err(_err)) => {
-            let m_failed_promise = img_cache.map().get(url);
-            if m_failed_promise.is_none() {
-                let no_pfp = crate::images::fetch_img(
-                    img_cache,
-                    ui.ctx(),
-                    ProfilePic::no_pfp_url(),
-                    img_size,
-                );
-                img_cache.map_mut().insert(url.to_owned(), no_pfp);
-            }
+        Some(Err(_err)) => fallback_pfp(ui, img_cache, pfp.pubkey, ui_size, img_size, shape),
+        Some(Ok(img)) => pfp_image(ui, img, ui_size),
+    }
+}
 
-            match img_cache.map().get(url).unwrap().ready() {
-                None => paint_circle(ui, ui_size),
-                Some(Err(_e)) => {
-                    //error!("Image load error: {:?}", e);
-                    paint_circle(ui, ui_size)
-                }
-                Some(Ok(img)) => pfp_image(ui, img, ui_size),
-            }
+/// The picture's shape filled with the color it mostly is, while the
+/// picture itself loads
+fn color_placeholder(
+    ui: &mut egui::Ui,
+    color: Color32,
+    ui_size: f32,
+    shape: AvatarShape,
+) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(vec2(ui_size, ui_size), Sense::hover());
+    ui.painter()
+        .rect_filled(rect, ring_rounding(shape, ui_size, 0.0), color);
+    response
+}
+
+/// How many pixels across to fetch a picture drawn `ui_size` points across,
+/// so it stays sharp on high DPI displays. This only depends on the
+/// physical size, so 38 points at 2x shares a texture with 76 points at 1x.
+fn fetch_size(ui_size: f32, pixels_per_point: f32) -> u32 {
+    let pixels = (ui_size * pixels_per_point).round().max(1.0) as u32;
+    pixels.div_ceil(FETCH_STEP) * FETCH_STEP
+}
+
+fn memory_key(url: &str, shape: AvatarShape, img_size: u32) -> String {
+    ImageCache::key(url, img_size, ImageType::Profile(shape))
+}
+
+/// The frame of an animated picture to draw now, once it has loaded
+fn animated_frame(
+    ui: &egui::Ui,
+    img_cache: &mut ImageCache,
+    url: &str,
+    options: FetchOptions,
+    shape: AvatarShape,
+) -> Option<TextureHandle> {
+    let key = format!("animated:{}", memory_key(url, shape, options.size));
+    if !img_cache.contains_animated(&key) {
+        let fetch = images::fetch_animated_img(img_cache, ui.ctx(), url, options);
+        img_cache.insert_animated(key.clone(), fetch);
+    }
+
+    match img_cache.ready_animated(&key) {
+        Some(Ok(anim)) => Some(anim.current_frame(ui.ctx()).clone()),
+        _ => None,
+    }
+}
+
+/// What to draw when a profile picture fails to load: an identicon if we
+/// know whose picture it is, otherwise the default silhouette.
+fn fallback_pfp(
+    ui: &mut egui::Ui,
+    img_cache: &mut ImageCache,
+    pubkey: Option<&[u8; 32]>,
+    ui_size: f32,
+    img_size: u32,
+    shape: AvatarShape,
+) -> egui::Response {
+    let Some(pubkey) = pubkey else {
+        let no_pfp_url = ProfilePic::no_pfp_url();
+        let key = memory_key(no_pfp_url, shape, img_size);
+        if !img_cache.contains(&key) {
+            let options = FetchOptions {
+                size: img_size,
+                img_type: ImageType::Profile(shape),
+                priority: Priority::Low,
+                ..Default::default()
+            };
+            let no_pfp = images::fetch_img(img_cache, ui.ctx(), no_pfp_url, options);
+            img_cache.insert_fetch(key.clone(), no_pfp);
         }
+
+        return match img_cache.ready(&key) {
+            Some(Ok(img)) => pfp_image(ui, img, ui_size),
+            _ => paint_circle(ui, ui_size),
+        };
+    };
+
+    let key = format!(
+        "identicon:{}:{}:{}",
+        hex::encode(pubkey),
+        img_size,
+        shape.cache_tag()
+    );
+    if !img_cache.contains(&key) {
+        let img = images::identicon(pubkey, img_size, shape);
+        let texture = ui.ctx().load_texture(&key, img, Default::default());
+        img_cache.insert(key.clone(), Promise::from_ready(Ok(texture)));
+    }
+
+    match img_cache.ready(&key) {
         Some(Ok(img)) => pfp_image(ui, img, ui_size),
+        _ => paint_circle(ui, ui_size),
     }
 }
 
@@ -90,6 +465,31 @@ fn pfp_image(ui: &mut egui::Ui, img: &TextureHandle, size: f32) -> egui::Respons
     //.with_options()
 }
 
+/// Draw the placeholder fading out while the loaded image fades in
+fn pfp_crossfade(
+    ui: &mut egui::Ui,
+    placeholder: &TextureHandle,
+    img: Option<&TextureHandle>,
+    size: f32,
+    fade: f32,
+) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(vec2(size, size), Sense::hover());
+
+    if fade < 1.0 {
+        egui::Image::new(placeholder)
+            .tint(Color32::WHITE.gamma_multiply(1.0 - fade))
+            .paint_at(ui, rect);
+    }
+
+    if let Some(img) = img {
+        egui::Image::new(img)
+            .tint(Color32::WHITE.gamma_multiply(fade))
+            .paint_at(ui, rect);
+    }
+
+    response
+}
+
 fn paint_circle(ui: &mut egui::Ui, size: f32) -> egui::Response {
     let (rect, response) = ui.allocate_at_least(vec2(size, size), Sense::hover());
     ui.painter()
@@ -111,12 +511,11 @@ mod preview {
     }
 
     impl ProfilePicPreview {
-        fn new() -> Self {
+        fn new(cache: ImageCache) -> Self {
             let config = Config::new();
             let ndb = Ndb::new(".", &config).expect("ndb");
             let txn = Transaction::new(&ndb).unwrap();
             let filters = vec![Filter::new().kinds(vec![0]).build()];
-            let cache = ImageCache::new("cache/img".into());
             let mut pks = HashSet::new();
             let mut keys = HashSet::new();
 
@@ -149,7 +548,7 @@ mod preview {
     }
 
     impl View for ProfilePicPreview {
-        fn ui(&mut self, ui: &mut egui::Ui) {
+        fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
             ui.horizontal_wrapped(|ui| {
                 let txn = Transaction::new(&self.ndb).unwrap();
                 for key in &self.keys {
@@ -178,7 +577,12 @@ mod preview {
                             ui.add(ui::ProfilePreview::new(&profile, &mut self.cache));
                         });
                 }
-            });
+            })
+            .response
+        }
+
+        fn is_loading(&self) -> bool {
+            self.cache.has_pending()
         }
     }
 
@@ -186,7 +590,36 @@ mod preview {
         type Prev = ProfilePicPreview;
 
         fn preview() -> Self::Prev {
-            ProfilePicPreview::new()
+            Self::with_image_cache(ui::preview_image_cache())
         }
+
+        fn with_image_cache(cache: ImageCache) -> Self::Prev {
+            ProfilePicPreview::new(cache)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rings_follow_the_shape() {
+        assert_eq!(ring_rounding(AvatarShape::Circle, 40.0, 3.0), 23.0);
+        assert_eq!(ring_rounding(AvatarShape::Square, 40.0, 3.0), 0.0);
+        assert_eq!(
+            ring_rounding(AvatarShape::RoundedRect { radius: 0.25 }, 40.0, 3.0),
+            13.0
+        );
+    }
+
+    #[test]
+    fn fetch_size_follows_physical_pixels() {
+        assert_eq!(fetch_size(38.0, 1.0), 64);
+        assert_eq!(fetch_size(38.0, 2.0), 96);
+        assert_eq!(fetch_size(76.0, 1.0), fetch_size(38.0, 2.0));
+        // a hover expansion stays on the same texture
+        assert_eq!(fetch_size(43.0, 2.0), fetch_size(38.0, 2.0));
+        assert_eq!(fetch_size(80.0, 3.0), 256);
     }
 }
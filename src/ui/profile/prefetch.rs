@@ -0,0 +1,52 @@
+/// Pubkeys that widgets laid out this frame wanted a profile for but didn't
+/// have. Widgets add to it while they're laid out, and the host takes them
+/// once the frame is done, so it can ask relays for all of them at once.
+#[derive(Debug, Clone, Default)]
+pub struct ProfileRequests {
+    pubkeys: Vec<[u8; 32]>,
+}
+
+impl ProfileRequests {
+    fn id() -> egui::Id {
+        egui::Id::new("profile_requests")
+    }
+
+    /// Ask for `pubkey`'s profile this frame
+    pub fn request(ctx: &egui::Context, pubkey: &[u8; 32]) {
+        ctx.data_mut(|d| {
+            d.get_temp_mut_or_default::<ProfileRequests>(Self::id())
+                .insert(pubkey)
+        });
+    }
+
+    /// Everything asked for since the last time, each pubkey once
+    pub fn take(ctx: &egui::Context) -> Vec<[u8; 32]> {
+        ctx.data_mut(|d| {
+            let requests = d.get_temp::<ProfileRequests>(Self::id());
+            d.remove::<ProfileRequests>(Self::id());
+            requests
+                .map(|requests| requests.pubkeys)
+                .unwrap_or_default()
+        })
+    }
+
+    fn insert(&mut self, pubkey: &[u8; 32]) {
+        if !self.pubkeys.contains(pubkey) {
+            self.pubkeys.push(*pubkey);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pubkeys_are_requested_once() {
+        let mut requests = ProfileRequests::default();
+        requests.insert(&[1; 32]);
+        requests.insert(&[2; 32]);
+        requests.insert(&[1; 32]);
+        assert_eq!(requests.pubkeys, vec![[1; 32], [2; 32]]);
+    }
+}
@@ -1,119 +1,268 @@
 use crate::app_style::NotedeckTextStyle;
+use crate::images::{self, FetchOptions, ImageType};
 use crate::imgcache::ImageCache;
-use crate::ui::ProfilePic;
-use crate::{colors, images, DisplayName};
-use egui::load::TexturePoll;
-use egui::{RichText, Sense};
-use egui_extras::Size;
+use crate::ui::anim;
+use crate::ui::direction::{self, LayoutDirection};
+use crate::ui::i18n::tr;
+use crate::ui::nip19::{self, Nip19Entity};
+use crate::ui::profile::about::about_ui;
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::{keys, ProfilePic, ProfileSource};
+use crate::DisplayName;
+use egui::{pos2, vec2, Image, Rect, RichText, Sense, TextureHandle};
 use nostrdb::ProfileRecord;
 
+/// How wide we fetch banners, in points. Previews are rarely wider.
+const BANNER_FETCH_WIDTH: f32 = 600.0;
+
+const AVATAR_SIZE: f32 = 80.0;
+
+/// The gap around the avatar where it overlaps the banner
+const AVATAR_RING: f32 = 3.0;
+
+/// NIP-19 suggests keeping nprofile relay hints to a few
+const MAX_NPROFILE_RELAYS: usize = 3;
+
 pub struct ProfilePreview<'a, 'cache> {
     profile: &'a ProfileRecord<'a>,
     cache: &'cache mut ImageCache,
-    banner_height: Size,
+    banner_height: f32,
+    pubkey: Option<&'a [u8; 32]>,
+    profiles: Option<&'a dyn ProfileSource>,
+    relays: &'a [String],
 }
 
 impl<'a, 'cache> ProfilePreview<'a, 'cache> {
     pub fn new(profile: &'a ProfileRecord<'a>, cache: &'cache mut ImageCache) -> Self {
-        let banner_height = Size::exact(80.0);
         ProfilePreview {
             profile,
             cache,
-            banner_height,
+            banner_height: 80.0,
+            pubkey: None,
+            profiles: None,
+            relays: &[],
         }
     }
 
-    pub fn banner_height(&mut self, size: Size) {
-        self.banner_height = size;
+    /// Whose profile this is. Whether their about text is expanded is
+    /// remembered by pubkey, so it stays that way wherever they show up.
+    pub fn pubkey(mut self, pubkey: &'a [u8; 32]) -> Self {
+        self.pubkey = Some(pubkey);
+        self
+    }
+
+    /// Relays to hint at in a copied nprofile
+    pub fn relays(mut self, relays: &'a [String]) -> Self {
+        self.relays = relays;
+        self
+    }
+
+    /// How to name the people mentioned in the about text
+    pub fn profiles(mut self, profiles: &'a dyn ProfileSource) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    pub fn banner_height(mut self, height: f32) -> Self {
+        self.banner_height = height;
+        self
     }
 
     fn banner_texture(
-        ui: &mut egui::Ui,
-        profile: &ProfileRecord<'_>,
-    ) -> Option<egui::load::SizedTexture> {
-        // TODO: cache banner
-        let banner = profile.record().profile().and_then(|p| p.banner());
-
-        if let Some(banner) = banner {
-            let texture_load_res =
-                egui::Image::new(banner).load_for_size(ui.ctx(), ui.available_size());
-            if let Ok(texture_poll) = texture_load_res {
-                match texture_poll {
-                    TexturePoll::Pending { .. } => {}
-                    TexturePoll::Ready { texture, .. } => return Some(texture),
-                }
+        ui: &egui::Ui,
+        cache: &mut ImageCache,
+        banner: &str,
+    ) -> Option<TextureHandle> {
+        let size = (BANNER_FETCH_WIDTH * ui.ctx().pixels_per_point()) as u32;
+        let key = ImageCache::key(banner, size, ImageType::Banner);
+        if !cache.contains(&key) {
+            let options = FetchOptions {
+                size,
+                img_type: ImageType::Banner,
+                ..Default::default()
+            };
+            let fetch = images::fetch_img(cache, ui.ctx(), banner, options);
+            cache.insert_fetch(key.clone(), fetch);
+        }
+
+        match cache.ready(&key) {
+            Some(Ok(texture)) => Some(texture.clone()),
+            _ => None,
+        }
+    }
+
+    /// The banner across the top, or an accent colored strip when there
+    /// isn't one or it hasn't loaded
+    fn banner(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        let size = vec2(ui.available_width(), self.banner_height);
+        let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let texture = self
+            .profile
+            .record()
+            .profile()
+            .and_then(|p| p.banner())
+            .and_then(|banner| Self::banner_texture(ui, self.cache, banner));
+
+        match texture {
+            Some(texture) => {
+                let texture_size = texture.size_vec2();
+                let uv = images::cover_uv(
+                    texture_size.x / texture_size.y,
+                    rect.width() / rect.height(),
+                );
+                Image::new(&texture).uv(uv).paint_at(ui, rect);
+            }
+            None => {
+                ui.painter().rect_filled(
+                    rect,
+                    0.0,
+                    NotedeckTheme::get(ui.ctx()).accent.gamma_multiply(0.6),
+                );
             }
         }
 
-        None
+        response
     }
 
-    fn banner(ui: &mut egui::Ui, profile: &ProfileRecord<'_>) -> egui::Response {
-        if let Some(texture) = Self::banner_texture(ui, profile) {
-            images::aspect_fill(
-                ui,
-                Sense::hover(),
-                texture.id,
-                texture.size.x / texture.size.y,
-            )
+    /// The avatar, hanging off the bottom of the banner
+    fn avatar(&mut self, ui: &mut egui::Ui, banner: Rect) -> egui::Response {
+        let url = self
+            .profile
+            .record()
+            .profile()
+            .and_then(|p| p.picture())
+            .unwrap_or(ProfilePic::no_pfp_url());
+
+        let top = banner.bottom() - AVATAR_SIZE / 2.0;
+        let left = if LayoutDirection::get(ui.ctx()).is_rtl() {
+            banner.right() - 12.0 - AVATAR_SIZE
         } else {
-            // TODO: default banner texture
-            ui.label("")
-        }
+            banner.left() + 12.0
+        };
+        let rect = Rect::from_min_size(pos2(left, top), vec2(AVATAR_SIZE, AVATAR_SIZE));
+        ui.painter().circle_filled(
+            rect.center(),
+            AVATAR_SIZE / 2.0 + AVATAR_RING,
+            ui.visuals().panel_fill,
+        );
+        ui.put(
+            rect,
+            ProfilePic::new(self.cache, url)
+                .size(AVATAR_SIZE)
+                .animate(true)
+                .alt(ProfilePic::alt_for(
+                    ui.ctx(),
+                    crate::profile::get_profile_name(self.profile).map(|name| name.display_name()),
+                )),
+        )
     }
 
     fn body(self, ui: &mut egui::Ui) {
+        // without a name we go by their npub, if we know whose profile it is
+        let short_npub = self.pubkey.map(keys::short_npub);
         let name = if let Some(name) = crate::profile::get_profile_name(self.profile) {
             name
         } else {
-            DisplayName::One("??")
+            DisplayName::One(short_npub.as_deref().unwrap_or("??"))
         };
 
         crate::ui::padding(12.0, ui, |ui| {
-            let url = if let Some(url) = self.profile.record().profile().and_then(|p| p.picture()) {
-                url
-            } else {
-                ProfilePic::no_pfp_url()
-            };
-
-            ui.add(ProfilePic::new(self.cache, url).size(80.0));
+            ui.with_layout(LayoutDirection::get(ui.ctx()).column(), |ui| {
+                let (heading, username) = match name {
+                    DisplayName::One(n) => (n, None),
+                    DisplayName::Both {
+                        display_name,
+                        username,
+                    } => (display_name, Some(username)),
+                };
 
-            match name {
-                DisplayName::One(n) => {
-                    ui.label(RichText::new(n).text_style(NotedeckTextStyle::Heading3.text_style()));
-                }
-
-                DisplayName::Both {
-                    display_name,
-                    username,
-                } => {
+                direction::horizontal(ui, |ui| {
                     ui.label(
-                        RichText::new(display_name)
-                            .text_style(NotedeckTextStyle::Heading3.text_style()),
+                        RichText::new(heading).text_style(NotedeckTextStyle::Heading3.text_style()),
                     );
+                    if let Some(pubkey) = self.pubkey {
+                        copy_menu(ui, pubkey, self.relays);
+                    }
+                });
 
+                if let Some(username) = username {
                     ui.label(
                         RichText::new(format!("@{}", username))
                             .size(12.0)
-                            .color(colors::MID_GRAY),
+                            .color(NotedeckTheme::get(ui.ctx()).muted_text),
                     );
                 }
-            }
 
-            if let Some(about) = self.profile.record().profile().and_then(|p| p.about()) {
-                ui.label(about);
-            }
+                if let Some(about) = self.profile.record().profile().and_then(|p| p.about()) {
+                    let id = match self.pubkey {
+                        Some(pubkey) => egui::Id::new(("profile_about", pubkey)),
+                        None => egui::Id::new(("profile_about", self.profile.record().note_key())),
+                    };
+                    about_ui(ui, id, about, self.profiles);
+                }
+            });
         });
     }
 }
 
+fn nprofile(pubkey: &[u8; 32], relays: &[String]) -> Option<String> {
+    nip19::encode(&Nip19Entity::Profile {
+        pubkey: *pubkey,
+        relays: relays.iter().take(MAX_NPROFILE_RELAYS).cloned().collect(),
+    })
+    .ok()
+}
+
+/// A little menu for copying someone's npub, or an nprofile with relay
+/// hints, that says "Copied!" for a moment afterwards
+fn copy_menu(ui: &mut egui::Ui, pubkey: &[u8; 32], relays: &[String]) {
+    let theme = NotedeckTheme::get(ui.ctx());
+    let copied_id = egui::Id::new(("profile_copied", pubkey));
+    let mut copied: Option<String> = None;
+
+    ui.menu_button(RichText::new("⎘").color(theme.muted_text), |ui| {
+        if ui.button(tr!(ui.ctx(), "Copy npub")).clicked() {
+            copied = nip19::npub(pubkey).ok();
+        }
+        if ui.button(tr!(ui.ctx(), "Copy nprofile")).clicked() {
+            copied = nprofile(pubkey, relays);
+        }
+        if copied.is_some() {
+            ui.close_menu();
+        }
+    })
+    .response
+    .on_hover_text(tr!(ui.ctx(), "Copy public key"));
+
+    if let Some(text) = copied {
+        let now = ui.input(|i| i.time);
+        ui.output_mut(|o| o.copied_text = text);
+        ui.data_mut(|d| d.insert_temp(copied_id, now));
+    }
+
+    let shown_at: Option<f64> = ui.data(|d| d.get_temp(copied_id));
+    match shown_at.and_then(|shown_at| anim::toast_opacity(ui.ctx(), shown_at)) {
+        Some(opacity) => {
+            ui.label(
+                RichText::new(tr!(ui.ctx(), "Copied!"))
+                    .size(12.0)
+                    .color(theme.accent.gamma_multiply(opacity)),
+            );
+        }
+        None if shown_at.is_some() => ui.data_mut(|d| d.remove::<f64>(copied_id)),
+        None => {}
+    }
+}
+
 impl<'a, 'cache> egui::Widget for ProfilePreview<'a, 'cache> {
-    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+    fn ui(mut self, ui: &mut egui::Ui) -> egui::Response {
         ui.vertical(|ui| {
-            ui.add_sized([ui.available_size().x, 80.0], |ui: &mut egui::Ui| {
-                ProfilePreview::banner(ui, self.profile)
-            });
-
+            let banner = self.banner(ui);
+            self.avatar(ui, banner.rect);
             self.body(ui);
         })
         .response
@@ -123,31 +272,51 @@ impl<'a, 'cache> egui::Widget for ProfilePreview<'a, 'cache> {
 mod previews {
     use super::*;
     use crate::test_data::test_profile_record;
-    use crate::ui::{Preview, View};
+    use crate::ui::preview::mock::MOCK_PUBKEY;
+    use crate::ui::{MemoryProfiles, Preview, ProfileData, View};
     use egui::Widget;
 
     pub struct ProfilePreviewPreview<'a> {
         profile: ProfileRecord<'a>,
+        pubkey: [u8; 32],
+        relays: Vec<String>,
+        profiles: MemoryProfiles,
         cache: ImageCache,
     }
 
     impl<'a> ProfilePreviewPreview<'a> {
-        pub fn new() -> Self {
+        pub fn new(cache: ImageCache) -> Self {
             let profile = test_profile_record();
-            let cache = ImageCache::new(ImageCache::rel_datadir().into());
-            ProfilePreviewPreview { profile, cache }
+            let mut pubkey = [0; 32];
+            hex::decode_to_slice(MOCK_PUBKEY, &mut pubkey).expect("valid pubkey");
+            let profiles = MemoryProfiles::new().with(pubkey, ProfileData::from_record(&profile));
+            ProfilePreviewPreview {
+                profile,
+                pubkey,
+                relays: vec!["wss://relay.damus.io".to_owned()],
+                profiles,
+                cache,
+            }
         }
     }
 
     impl<'a> Default for ProfilePreviewPreview<'a> {
         fn default() -> Self {
-            ProfilePreviewPreview::new()
+            ProfilePreviewPreview::new(crate::ui::preview_image_cache())
         }
     }
 
     impl<'a> View for ProfilePreviewPreview<'a> {
-        fn ui(&mut self, ui: &mut egui::Ui) {
-            ProfilePreview::new(&self.profile, &mut self.cache).ui(ui);
+        fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+            ProfilePreview::new(&self.profile, &mut self.cache)
+                .pubkey(&self.pubkey)
+                .relays(&self.relays)
+                .profiles(&self.profiles)
+                .ui(ui)
+        }
+
+        fn is_loading(&self) -> bool {
+            self.cache.has_pending()
         }
     }
 
@@ -156,7 +325,11 @@ mod previews {
         type Prev = ProfilePreviewPreview<'a>;
 
         fn preview() -> Self::Prev {
-            ProfilePreviewPreview::new()
+            ProfilePreviewPreview::default()
+        }
+
+        fn with_image_cache(cache: ImageCache) -> Self::Prev {
+            ProfilePreviewPreview::new(cache)
         }
     }
 }
@@ -0,0 +1,83 @@
+use crate::ui::ProfileData;
+use nostrdb::{Ndb, Transaction};
+use std::collections::HashMap;
+
+/// Somewhere widgets can look up what they know about a pubkey, so they
+/// don't have to care whether it's the database, a cache or mock data
+pub trait ProfileSource {
+    fn get(&self, pubkey: &[u8; 32]) -> Option<ProfileData>;
+}
+
+/// Profiles from nostrdb, as of `txn`
+pub struct NdbProfiles<'a> {
+    ndb: &'a Ndb,
+    txn: &'a Transaction,
+}
+
+impl<'a> NdbProfiles<'a> {
+    pub fn new(ndb: &'a Ndb, txn: &'a Transaction) -> Self {
+        NdbProfiles { ndb, txn }
+    }
+}
+
+impl<'a> ProfileSource for NdbProfiles<'a> {
+    fn get(&self, pubkey: &[u8; 32]) -> Option<ProfileData> {
+        self.ndb
+            .get_profile_by_pubkey(self.txn, pubkey)
+            .ok()
+            .map(|profile| ProfileData::from_record(&profile))
+    }
+}
+
+/// Profiles held in memory, for previews and tests
+#[derive(Debug, Clone, Default)]
+pub struct MemoryProfiles {
+    profiles: HashMap<[u8; 32], ProfileData>,
+}
+
+impl MemoryProfiles {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, pubkey: [u8; 32], profile: ProfileData) {
+        self.profiles.insert(pubkey, profile);
+    }
+
+    pub fn with(mut self, pubkey: [u8; 32], profile: ProfileData) -> Self {
+        self.insert(pubkey, profile);
+        self
+    }
+}
+
+impl FromIterator<([u8; 32], ProfileData)> for MemoryProfiles {
+    fn from_iter<I: IntoIterator<Item = ([u8; 32], ProfileData)>>(iter: I) -> Self {
+        MemoryProfiles {
+            profiles: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl ProfileSource for MemoryProfiles {
+    fn get(&self, pubkey: &[u8; 32]) -> Option<ProfileData> {
+        self.profiles.get(pubkey).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_profiles_look_up_by_pubkey() {
+        let jb55 = ProfileData {
+            name: Some("jb55".to_owned()),
+            ..Default::default()
+        };
+        let profiles: MemoryProfiles = [([1; 32], jb55.clone())].into_iter().collect();
+        let source: &dyn ProfileSource = &profiles;
+
+        assert_eq!(source.get(&[1; 32]), Some(jb55));
+        assert_eq!(source.get(&[2; 32]), None);
+    }
+}
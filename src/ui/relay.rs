@@ -1,118 +1,752 @@
-use crate::relay_pool_manager::{RelayPoolManager, RelayStatus};
-use crate::ui::{Preview, View};
-use egui::{Align, Button, Frame, Layout, Margin, Rgba, RichText, Rounding, Ui, Vec2};
+use crate::relay_pool_manager::{RelayInfo, RelayPoolManager, RelayStatus};
+use crate::ui::direction::{self, LayoutDirection};
+use crate::ui::i18n::{self, tr, Strings};
+use crate::ui::theme::NotedeckTheme;
+use crate::ui::time::RelativeTime;
+use crate::ui::{self, Preview, View};
+use egui::{Align, Button, Color32, Frame, Margin, Rgba, RichText, Rounding, Sense, Ui, Vec2};
 
 use crate::app_style::NotedeckTextStyle;
 use enostr::RelayPool;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A change to the relay list requested from a [`RelayView`]. The caller
+/// is responsible for applying and persisting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayAction {
+    Add(String),
+    Remove(String),
+    /// The relay's new read/write permissions
+    SetPermissions(String, RelayPermissions),
+    /// Move the relay at the first index so it ends up at the second, in
+    /// the order [`RelayPoolManager::get_relay_infos`] lists them
+    Reorder(usize, usize),
+}
+
+/// Whether we read from a relay, write to it or both, as in a NIP-65
+/// relay list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayPermissions {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Default for RelayPermissions {
+    fn default() -> Self {
+        RelayPermissions {
+            read: true,
+            write: true,
+        }
+    }
+}
+
+impl RelayPermissions {
+    /// A short marker for relays that are only one of read or write
+    fn summary(&self) -> Option<(&'static str, &'static str)> {
+        match (self.read, self.write) {
+            (true, false) => Some(("↓", "Read only")),
+            (false, true) => Some(("↑", "Write only")),
+            _ => None,
+        }
+    }
+}
 
 pub struct RelayView<'a> {
     manager: RelayPoolManager<'a>,
+    editable: bool,
+    permissions: Option<&'a HashMap<String, RelayPermissions>>,
+    latencies: Option<&'a HashMap<String, Duration>>,
+    sort_by_latency: bool,
+    /// NIP-11 documents the caller fetched, keyed by relay url
+    relay_info: Option<&'a HashMap<String, enostr::RelayInfo>>,
+    searchable: bool,
 }
 
 impl<'a> View for RelayView<'a> {
-    fn ui(&mut self, ui: &mut egui::Ui) {
+    fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+        self.show(ui).response
+    }
+}
+
+/// The add relay form, kept in egui's memory between frames
+#[derive(Clone, Default)]
+struct AddRelayState {
+    open: bool,
+    url: String,
+    error: Option<&'static str>,
+}
+
+impl<'a> RelayView<'a> {
+    fn contents(&mut self, ui: &mut egui::Ui) -> Vec<RelayAction> {
+        let mut actions = vec![];
+        let state_id = ui.id().with("add_relay");
+        let mut state: AddRelayState = ui.data(|d| d.get_temp(state_id)).unwrap_or_default();
+
         ui.add_space(24.0);
 
-        ui.horizontal(|ui| {
-            ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+        let direction = LayoutDirection::get(ui.ctx());
+        direction::horizontal(ui, |ui| {
+            ui.with_layout(direction.row(Align::Center), |ui| {
                 ui.label(
-                    RichText::new("Relays").text_style(NotedeckTextStyle::Heading2.text_style()),
+                    RichText::new(tr!(ui.ctx(), "Relays"))
+                        .text_style(NotedeckTextStyle::Heading2.text_style()),
                 );
             });
 
-            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-                if ui.add(add_relay_button()).clicked() {
-                    // TODO: navigate to 'add relay view'
-                };
-            });
+            if self.editable {
+                ui.with_layout(direction.row_end(Align::Center), |ui| {
+                    if ui.add(add_relay_button(ui.ctx())).clicked() {
+                        state.open = !state.open;
+                    };
+                });
+            }
         });
 
+        if self.editable && state.open {
+            ui.add_space(8.0);
+            if let Some(url) = self.add_relay_form(ui, &mut state) {
+                actions.push(RelayAction::Add(url));
+            }
+        }
+
+        ui.data_mut(|d| d.insert_temp(state_id, state));
+
+        let query_id = ui.id().with("relay_search");
+        let mut query: String = ui.data(|d| d.get_temp(query_id)).unwrap_or_default();
+        if self.searchable {
+            ui.add_space(8.0);
+            self.search_field(ui, &mut query);
+            ui.data_mut(|d| d.insert_temp(query_id, query.clone()));
+        } else {
+            query.clear();
+        }
+
         ui.add_space(8.0);
 
         egui::ScrollArea::vertical()
             .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
             .auto_shrink([false; 2])
             .show(ui, |ui| {
-                if let Some(indices) = self.show_relays(ui) {
-                    self.manager.remove_relays(indices);
-                }
+                actions.extend(self.show_relays(ui, &query));
             });
+
+        actions
+    }
+
+    /// The search field, with how many relays match it
+    fn search_field(&self, ui: &mut Ui, query: &mut String) {
+        let infos = self.manager.get_relay_infos();
+        let shown = infos
+            .iter()
+            .filter(|info| self.matches(query, info.relay_url))
+            .count();
+
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(query)
+                    .hint_text(tr!(ui.ctx(), "Search relays"))
+                    .desired_width(200.0),
+            );
+            ui.label(
+                RichText::new(tr!(
+                    ui.ctx(),
+                    "{} of {} relays",
+                    ui::fmt::compact_number(shown as u64),
+                    ui::fmt::compact_number(infos.len() as u64)
+                ))
+                .color(NotedeckTheme::get(ui.ctx()).muted_text),
+            );
+        });
+    }
+
+    fn matches(&self, query: &str, relay_url: &str) -> bool {
+        let name = self
+            .relay_info
+            .and_then(|infos| infos.get(relay_url)?.name.as_deref());
+        relay_matches(query, relay_url, name)
+    }
+
+    /// The url field for adding a relay. Returns the url to add once the
+    /// user has entered a valid one that isn't already in the list.
+    fn add_relay_form(&self, ui: &mut Ui, state: &mut AddRelayState) -> Option<String> {
+        let mut added = None;
+
+        ui.horizontal(|ui| {
+            let field = ui.add(
+                egui::TextEdit::singleline(&mut state.url)
+                    .hint_text("wss://relay.example.com")
+                    .font(NotedeckTextStyle::Monospace.text_style()),
+            );
+            if field.changed() {
+                state.error = None;
+            }
+
+            let submitted = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button(tr!(ui.ctx(), "Add")).clicked() || submitted {
+                match normalize_relay_url(&state.url) {
+                    Ok(url) => {
+                        let exists =
+                            self.manager.get_relay_infos().iter().any(|info| {
+                                normalize_relay_url(info.relay_url).as_ref() == Ok(&url)
+                            });
+                        if !exists {
+                            added = Some(url);
+                        }
+                        state.url.clear();
+                    }
+                    Err(err) => state.error = Some(err),
+                }
+            }
+        });
+
+        if let Some(err) = state.error {
+            ui.label(RichText::new(tr!(ui.ctx(), err)).color(NotedeckTheme::get(ui.ctx()).error));
+        }
+
+        added
+    }
+}
+
+/// Whether a relay's url or name contains `query`, ignoring case. Every
+/// relay matches an empty query.
+fn relay_matches(query: &str, relay_url: &str, name: Option<&str>) -> bool {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return true;
     }
+    std::iter::once(relay_url)
+        .chain(name)
+        .any(|s| s.to_lowercase().contains(&query))
+}
+
+/// Check that a relay url is a websocket url, returning it trimmed and
+/// without a trailing slash so that duplicates compare equal
+fn normalize_relay_url(url: &str) -> Result<String, &'static str> {
+    let url = url.trim();
+    let Some(host) = url
+        .strip_prefix("wss://")
+        .or_else(|| url.strip_prefix("ws://"))
+    else {
+        return Err("Relay urls start with wss:// or ws://");
+    };
+
+    if host.trim_end_matches('/').is_empty() {
+        return Err("Relay url is missing a host");
+    }
+
+    if host.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("Relay urls can't contain spaces");
+    }
+
+    Ok(url.trim_end_matches('/').to_owned())
 }
 
 impl<'a> RelayView<'a> {
     pub fn new(manager: RelayPoolManager<'a>) -> Self {
-        RelayView { manager }
+        RelayView {
+            manager,
+            editable: false,
+            permissions: None,
+            latencies: None,
+            sort_by_latency: false,
+            relay_info: None,
+            searchable: false,
+        }
+    }
+
+    /// Show each relay's round trip time, keyed by relay url. Relays that
+    /// haven't been measured are shown as unknown.
+    pub fn latencies(mut self, latencies: &'a HashMap<String, Duration>) -> Self {
+        self.latencies = Some(latencies);
+        self
+    }
+
+    /// List the fastest relays first, and unmeasured ones last
+    pub fn sort_by_latency(mut self, sort: bool) -> Self {
+        self.sort_by_latency = sort;
+        self
+    }
+
+    fn latency(&self, relay_url: &str) -> Option<Duration> {
+        self.latencies?.get(relay_url).copied()
+    }
+
+    /// Let each relay be clicked to show its NIP-11 information document,
+    /// keyed by relay url. Relays that aren't in the map are shown as not
+    /// having one.
+    pub fn relay_info(mut self, relay_info: &'a HashMap<String, enostr::RelayInfo>) -> Self {
+        self.relay_info = Some(relay_info);
+        self
+    }
+
+    /// Show a search field above the list that filters relays by url, or
+    /// by name if we have their NIP-11 document
+    pub fn searchable(mut self, searchable: bool) -> Self {
+        self.searchable = searchable;
+        self
+    }
+
+    /// Show each relay's read/write permissions, keyed by relay url.
+    /// Relays that aren't in the map can be read from and written to. When
+    /// editable, changes come back as [`RelayAction::SetPermissions`].
+    pub fn permissions(mut self, permissions: &'a HashMap<String, RelayPermissions>) -> Self {
+        self.permissions = Some(permissions);
+        self
+    }
+
+    /// Let the user add, remove and reorder relays. Relays can't be
+    /// reordered while they're sorted by latency.
+    pub fn editable(mut self, editable: bool) -> Self {
+        self.editable = editable;
+        self
+    }
+
+    /// Render the view, returning the changes the user asked for
+    pub fn show(&mut self, ui: &mut egui::Ui) -> egui::InnerResponse<Vec<RelayAction>> {
+        ui.scope(|ui| self.contents(ui))
     }
 
-    pub fn panel(&mut self, ui: &mut egui::Ui) {
-        egui::CentralPanel::default().show(ui.ctx(), |ui| self.ui(ui));
+    pub fn panel(&mut self, ui: &mut egui::Ui) -> Vec<RelayAction> {
+        egui::CentralPanel::default()
+            .show(ui.ctx(), |ui| self.show(ui).inner)
+            .inner
     }
 
-    /// Show the current relays, and returns the indices of relays the user requested to delete
-    fn show_relays(&'a self, ui: &mut Ui) -> Option<Vec<usize>> {
-        let mut indices_to_remove: Option<Vec<usize>> = None;
-        for (index, relay_info) in self.manager.get_relay_infos().iter().enumerate() {
+    /// Show the current relays, returning the changes the user requested
+    fn show_relays(&self, ui: &mut Ui, query: &str) -> Vec<RelayAction> {
+        let mut actions = vec![];
+        let mut relay_infos = self.manager.get_relay_infos();
+        relay_infos.retain(|info| self.matches(query, info.relay_url));
+        if relay_infos.is_empty() && !query.trim().is_empty() {
+            ui.label(
+                RichText::new(tr!(ui.ctx(), "No relays match"))
+                    .color(NotedeckTheme::get(ui.ctx()).muted_text),
+            );
+        }
+        if self.sort_by_latency {
+            // stable, so relays we haven't measured keep their order
+            relay_infos.sort_by_key(|info| self.latency(info.relay_url).unwrap_or(Duration::MAX));
+        }
+
+        // by dragging a handle, or with buttons on mobile where dragging
+        // fights with scrolling
+        // or while filtered, since the list's indices aren't the pool's
+        let reorderable = self.editable && !self.sort_by_latency && query.trim().is_empty();
+        let mobile = ui::is_mobile(ui.ctx());
+        let mut row_rects = Vec::with_capacity(relay_infos.len());
+
+        for (index, relay_info) in relay_infos.iter().enumerate() {
+            let permissions = self
+                .permissions
+                .map(|perms| perms.get(relay_info.relay_url).copied().unwrap_or_default());
+            let right_side_width = get_right_side_width(relay_info.status)
+                + match permissions {
+                    Some(_) if self.editable => 130.0,
+                    Some(_) => 20.0,
+                    None => 0.0,
+                }
+                + if self.latencies.is_some() { 60.0 } else { 0.0 }
+                + match reorderable {
+                    true if mobile => 60.0,
+                    true => 20.0,
+                    false => 0.0,
+                };
+            let warning_id = ui
+                .id()
+                .with(("relay_permissions_warning", relay_info.relay_url));
+            let details_id = ui.id().with(("relay_details", relay_info.relay_url));
+
             ui.add_space(8.0);
-            ui.vertical_centered_justified(|ui| {
+            let row = ui.vertical_centered_justified(|ui| {
                 relay_frame(ui).show(ui, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+                    let direction = LayoutDirection::get(ui.ctx());
+                    direction::horizontal(ui, |ui| {
+                        ui.with_layout(direction.row(Align::Center), |ui| {
+                            if reorderable && mobile {
+                                if let Some(to) = move_buttons(ui, index, relay_infos.len()) {
+                                    actions.push(RelayAction::Reorder(index, to));
+                                }
+                            } else if reorderable {
+                                let handle_id = ui.id().with(("relay_drag", relay_info.relay_url));
+                                drag_handle(ui, handle_id, index);
+                            }
+
+                            status_dot(ui, relay_info.status)
+                                .on_hover_text(status_tooltip(ui.ctx(), relay_info));
+                            ui.add_space(8.0);
+
                             Frame::none()
                                 // This frame is needed to add margin because the label will be added to the outer frame first and centered vertically before the connection status is added so the vertical centering isn't accurate.
                                 // TODO: remove this hack and actually center the url & status at the same time
                                 .inner_margin(Margin::symmetric(0.0, 4.0))
                                 .show(ui, |ui| {
                                     egui::ScrollArea::horizontal()
-                                        .id_source(index)
-                                        .max_width(
-                                            ui.max_rect().width()
-                                                - get_right_side_width(relay_info.status),
-                                        ) // TODO: refactor to dynamically check the size of the 'right to left' portion and set the max width to be the screen width minus padding minus 'right to left' width
+                                        .id_source(relay_info.relay_url)
+                                        .max_width(ui.max_rect().width() - right_side_width) // TODO: refactor to dynamically check the size of the 'right to left' portion and set the max width to be the screen width minus padding minus 'right to left' width
                                         .show(ui, |ui| {
-                                            ui.label(
-                                                RichText::new(relay_info.relay_url)
-                                                    .text_style(
-                                                        NotedeckTextStyle::Monospace.text_style(),
-                                                    )
-                                                    .color(
-                                                        ui.style()
-                                                            .visuals
-                                                            .noninteractive()
-                                                            .fg_stroke
-                                                            .color,
-                                                    ),
+                                            let url = ui.add(
+                                                egui::Label::new(
+                                                    RichText::new(relay_info.relay_url)
+                                                        .text_style(
+                                                            NotedeckTextStyle::Monospace
+                                                                .text_style(),
+                                                        )
+                                                        .color(
+                                                            ui.style()
+                                                                .visuals
+                                                                .noninteractive()
+                                                                .fg_stroke
+                                                                .color,
+                                                        ),
+                                                )
+                                                .sense(Sense::click()),
                                             );
+                                            if self.relay_info.is_some() {
+                                                let url = url
+                                                    .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                                    .on_hover_text(tr!(
+                                                        ui.ctx(),
+                                                        "Show relay info"
+                                                    ));
+                                                if url.clicked() {
+                                                    toggle(ui, details_id);
+                                                }
+                                            }
                                         });
                                 });
                         });
 
-                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
-                            if ui.add(delete_button(ui.visuals().dark_mode)).clicked() {
-                                indices_to_remove.get_or_insert_with(Vec::new).push(index);
+                        ui.with_layout(direction.row_end(Align::Center), |ui| {
+                            if self.editable
+                                && ui.add(delete_button(ui.visuals().dark_mode)).clicked()
+                            {
+                                actions.push(RelayAction::Remove(relay_info.relay_url.to_owned()));
                             };
 
-                            show_connection_status(ui, relay_info.status);
+                            show_connection_status(ui, relay_info.status)
+                                .on_hover_text(status_tooltip(ui.ctx(), relay_info));
+
+                            if self.latencies.is_some() {
+                                show_latency(ui, self.latency(relay_info.relay_url));
+                            }
+
+                            if let Some(perms) = permissions {
+                                if let Some(changed) =
+                                    self.permission_controls(ui, perms, warning_id)
+                                {
+                                    actions.push(RelayAction::SetPermissions(
+                                        relay_info.relay_url.to_owned(),
+                                        changed,
+                                    ));
+                                }
+                            }
                         });
                     });
+
+                    if ui.data(|d| d.get_temp(warning_id)).unwrap_or(false) {
+                        ui.label(
+                            RichText::new(tr!(
+                                ui.ctx(),
+                                "A relay needs to be read or write. Remove it instead."
+                            ))
+                            .color(ui.visuals().warn_fg_color),
+                        );
+                    }
+
+                    if let Some(infos) = self.relay_info {
+                        if ui.data(|d| d.get_temp(details_id)).unwrap_or(false) {
+                            ui.separator();
+                            relay_details(ui, infos.get(relay_info.relay_url));
+                        }
+                    }
                 });
             });
+            row_rects.push(row.response.rect);
         }
 
-        indices_to_remove
+        if reorderable && !mobile {
+            actions.extend(
+                drop_indicator(ui, &row_rects).map(|(from, to)| RelayAction::Reorder(from, to)),
+            );
+        }
+
+        actions
     }
 }
 
+impl<'a> RelayView<'a> {
+    /// The read/write summary, plus toggles when editable. Returns the new
+    /// permissions if they were changed. Turning off both is refused with
+    /// a warning, since a relay we neither read nor write is pointless.
+    fn permission_controls(
+        &self,
+        ui: &mut Ui,
+        perms: RelayPermissions,
+        warning_id: egui::Id,
+    ) -> Option<RelayPermissions> {
+        let mut changed = None;
+
+        // right to left, so the write toggle comes first
+        if self.editable {
+            let mut edited = perms;
+            let toggled = ui
+                .checkbox(&mut edited.write, tr!(ui.ctx(), "Write"))
+                .changed()
+                | ui.checkbox(&mut edited.read, tr!(ui.ctx(), "Read"))
+                    .changed();
+
+            if toggled {
+                let refused = !edited.read && !edited.write;
+                ui.data_mut(|d| d.insert_temp(warning_id, refused));
+                if !refused {
+                    changed = Some(edited);
+                }
+            }
+        }
+
+        if let Some((icon, tooltip)) = perms.summary() {
+            ui.label(RichText::new(icon).color(ui.visuals().weak_text_color()))
+                .on_hover_text(tr!(ui.ctx(), tooltip));
+        }
+
+        changed
+    }
+}
+
+/// What's being dragged: the index of the relay
+struct RelayDrag(usize);
+
+fn drag_handle(ui: &mut Ui, id: egui::Id, index: usize) {
+    let handle = ui
+        .dnd_drag_source(id, RelayDrag(index), |ui| {
+            ui.label(RichText::new("☰").color(ui.visuals().weak_text_color()));
+        })
+        .response;
+    handle
+        .on_hover_cursor(egui::CursorIcon::Grab)
+        .on_hover_text(tr!(ui.ctx(), "Drag to reorder"));
+}
+
+/// Move up and down buttons. Returns where the relay was moved to.
+fn move_buttons(ui: &mut Ui, index: usize, len: usize) -> Option<usize> {
+    let mut moved = None;
+    if ui
+        .add_enabled(index > 0, Button::new("▲").small())
+        .clicked()
+    {
+        moved = Some(index - 1);
+    }
+    if ui
+        .add_enabled(index + 1 < len, Button::new("▼").small())
+        .clicked()
+    {
+        moved = Some(index + 1);
+    }
+    moved
+}
+
+/// While a relay is dragged over the list, draw a line in the gap it would
+/// be dropped into. Returns the move once it's dropped.
+fn drop_indicator(ui: &mut Ui, rows: &[egui::Rect]) -> Option<(usize, usize)> {
+    let dragged = egui::DragAndDrop::payload::<RelayDrag>(ui.ctx())?.0;
+    let pointer = ui.input(|i| i.pointer.interact_pos())?;
+    let list = rows
+        .iter()
+        .fold(egui::Rect::NOTHING, |list, row| list.union(*row));
+    if !list.expand(8.0).contains(pointer) {
+        return None;
+    }
+
+    let centers: Vec<f32> = rows.iter().map(|row| row.center().y).collect();
+    let slot = drop_slot(&centers, pointer.y);
+    // halfway across the space between rows
+    let y = match slot {
+        0 => list.top() - 4.0,
+        slot => rows[slot - 1].bottom() + 4.0,
+    };
+    ui.painter()
+        .hline(list.x_range(), y, (2.0, ui.visuals().selection.bg_fill));
+
+    if !ui.input(|i| i.pointer.any_released()) {
+        return None;
+    }
+    egui::DragAndDrop::clear_payload(ui.ctx());
+    let to = drop_target(dragged, slot)?;
+    Some((dragged, to))
+}
+
+/// Which gap between rows a drop at `y` lands in, counting the one before
+/// the first row as 0
+fn drop_slot(centers: &[f32], y: f32) -> usize {
+    centers.iter().take_while(|center| **center < y).count()
+}
+
+/// Where the relay at `from` ends up when dropped into gap `slot`, or
+/// `None` if that's where it already is
+fn drop_target(from: usize, slot: usize) -> Option<usize> {
+    // taking it out shifts everything after it up one
+    let to = if slot > from { slot - 1 } else { slot };
+    (to != from).then_some(to)
+}
+
+fn toggle(ui: &Ui, id: egui::Id) {
+    ui.data_mut(|d| {
+        let open = d.get_temp_mut_or_default::<bool>(id);
+        *open = !*open;
+    });
+}
+
+/// A relay's NIP-11 document: who runs it, what it supports and what it
+/// limits
+fn relay_details(ui: &mut Ui, info: Option<&enostr::RelayInfo>) {
+    let theme = NotedeckTheme::get(ui.ctx());
+    let Some(info) = info else {
+        ui.label(RichText::new(tr!(ui.ctx(), "Relay info unavailable")).color(theme.muted_text));
+        return;
+    };
+
+    ui.vertical(|ui| {
+        if let Some(name) = &info.name {
+            ui.label(RichText::new(name).strong());
+        }
+        if let Some(description) = &info.description {
+            ui.label(description);
+        }
+        if let Some(contact) = &info.contact {
+            ui.label(RichText::new(tr!(ui.ctx(), "Contact: {}", contact)).color(theme.muted_text));
+        }
+        if let Some(software) = &info.software {
+            let software = match &info.version {
+                Some(version) => format!("{} {}", software, version),
+                None => software.to_owned(),
+            };
+            ui.label(RichText::new(software).color(theme.muted_text));
+        }
+
+        if !info.supported_nips.is_empty() {
+            ui.add_space(4.0);
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing = Vec2::splat(4.0);
+                for nip in &info.supported_nips {
+                    nip_chip(ui, *nip);
+                }
+            });
+        }
+
+        let strings = Strings::get_for(ui.ctx());
+        let limits = info
+            .limitation
+            .as_ref()
+            .map(|limitation| limitation_lines(&strings, limitation))
+            .unwrap_or_default();
+        if !limits.is_empty() {
+            ui.add_space(4.0);
+            for limit in limits {
+                ui.label(RichText::new(limit).size(12.0));
+            }
+        }
+        if let Some(url) = info
+            .payments_url
+            .as_ref()
+            .filter(|_| info.limitation.as_ref().and_then(|l| l.payment_required) == Some(true))
+        {
+            ui.hyperlink_to(tr!(ui.ctx(), "Pay for access"), url);
+        }
+    });
+}
+
+fn nip_chip(ui: &mut Ui, nip: u32) -> egui::Response {
+    let theme = NotedeckTheme::get(ui.ctx());
+    Frame::none()
+        .rounding(Rounding::same(100.0))
+        .fill(theme.surface)
+        .inner_margin(Margin::symmetric(8.0, 2.0))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(format!("NIP-{:02}", nip))
+                    .text_style(NotedeckTextStyle::Monospace.text_style())
+                    .size(11.0),
+            );
+        })
+        .response
+}
+
+/// The limits worth telling people about, one per line
+fn limitation_lines(strings: &Strings, limitation: &enostr::RelayLimitation) -> Vec<String> {
+    let count = ui::fmt::compact_number;
+    let line = |english, arg: &dyn std::fmt::Display| i18n::fill(strings.get(english), &[arg]);
+    let mut lines = vec![];
+    if limitation.payment_required == Some(true) {
+        lines.push(strings.get("Payment required").to_owned());
+    }
+    if limitation.auth_required == Some(true) {
+        lines.push(strings.get("Requires authentication").to_owned());
+    }
+    if limitation.restricted_writes == Some(true) {
+        lines.push(strings.get("Only some users can publish").to_owned());
+    }
+    if let Some(len) = limitation.max_message_length {
+        lines.push(line("Messages up to {} bytes", &count(len)));
+    }
+    if let Some(len) = limitation.max_content_length {
+        lines.push(line("Notes up to {} characters", &count(len)));
+    }
+    if let Some(subs) = limitation.max_subscriptions {
+        lines.push(line("Up to {} subscriptions", &count(subs)));
+    }
+    if let Some(limit) = limitation.max_limit {
+        lines.push(line("Up to {} notes per filter", &count(limit)));
+    }
+    if let Some(bits) = limitation.min_pow_difficulty.filter(|bits| *bits > 0) {
+        lines.push(line("Proof of work of {} bits", &bits));
+    }
+    lines
+}
+
+/// How far a round trip time is from good (under 100ms, 0.0) to bad (over
+/// a second, 1.0)
+fn latency_badness(latency: Duration) -> f32 {
+    let ms = latency.as_secs_f32() * 1000.0;
+    ((ms - 100.0) / 900.0).clamp(0.0, 1.0)
+}
+
+fn format_latency(latency: Option<Duration>) -> String {
+    match latency {
+        Some(latency) => format!("{}ms", latency.as_millis()),
+        None => "—".to_string(),
+    }
+}
+
+fn show_latency(ui: &mut Ui, latency: Option<Duration>) {
+    let theme = NotedeckTheme::get(ui.ctx());
+    let color = match latency {
+        Some(latency) => {
+            let good = Rgba::from(ui.visuals().selection.bg_fill);
+            let bad = Rgba::from(theme.error);
+            egui::lerp(good..=bad, latency_badness(latency)).into()
+        }
+        None => theme.muted_text,
+    };
+
+    ui.label(
+        RichText::new(format_latency(latency))
+            .text_style(NotedeckTextStyle::Monospace.text_style())
+            .color(color),
+    );
+}
+
 fn get_right_side_width(status: &RelayStatus) -> f32 {
     match status {
         RelayStatus::Connected => 150.0,
         RelayStatus::Connecting => 160.0,
         RelayStatus::Disconnected => 175.0,
+        RelayStatus::Error(_) => 130.0,
     }
 }
 
-fn add_relay_button() -> egui::Button<'static> {
-    Button::new("+ Add relay").min_size(Vec2::new(0.0, 32.0))
+fn add_relay_button(ctx: &egui::Context) -> egui::Button<'static> {
+    Button::new(tr!(ctx, "+ Add relay")).min_size(Vec2::new(0.0, 32.0))
 }
 
 fn delete_button(_dark_mode: bool) -> egui::Button<'static> {
@@ -136,18 +770,59 @@ fn relay_frame(ui: &mut Ui) -> Frame {
         .stroke(ui.style().visuals.noninteractive().bg_stroke)
 }
 
-fn show_connection_status(ui: &mut Ui, status: &RelayStatus) {
-    let fg_color = match status {
+fn status_color(ui: &Ui, status: &RelayStatus) -> Color32 {
+    let theme = NotedeckTheme::get(ui.ctx());
+    match status {
         RelayStatus::Connected => ui.visuals().selection.bg_fill,
         RelayStatus::Connecting => ui.visuals().warn_fg_color,
-        RelayStatus::Disconnected => ui.visuals().error_fg_color,
+        RelayStatus::Disconnected => theme.muted_text,
+        RelayStatus::Error(_) => theme.error,
+    }
+}
+
+fn status_dot(ui: &mut Ui, status: &RelayStatus) -> egui::Response {
+    let size = 8.0;
+    let (rect, response) = ui.allocate_exact_size(Vec2::splat(size), Sense::hover());
+    ui.painter()
+        .circle_filled(rect.center(), size / 2.0, status_color(ui, status));
+    response
+}
+
+/// The last error, or how long it's been since the relay sent us anything
+fn status_tooltip(ctx: &egui::Context, relay_info: &RelayInfo) -> String {
+    if let RelayStatus::Error(err) = relay_info.status {
+        return err.to_owned();
+    }
+
+    let Some(last_message) = relay_info.last_message else {
+        return tr!(ctx, "No messages yet").into_owned();
     };
-    let bg_color = egui::lerp(Rgba::from(fg_color)..=Rgba::BLACK, 0.8).into();
+
+    let secs = last_message.elapsed().as_secs();
+    match RelativeTime::new(0, secs) {
+        RelativeTime::Now => tr!(ctx, "Last message just now").into_owned(),
+        RelativeTime::Yesterday => tr!(ctx, "Last message yesterday").into_owned(),
+        ago => tr!(
+            ctx,
+            "Last message {} ago",
+            ago.format(&Strings::get_for(ctx))
+        ),
+    }
+}
+
+fn show_connection_status(ui: &mut Ui, status: &RelayStatus) -> egui::Response {
+    let theme = NotedeckTheme::get(ui.ctx());
+    let fg_color = match status {
+        RelayStatus::Disconnected => theme.error,
+        _ => status_color(ui, status),
+    };
+    let bg_color = egui::lerp(Rgba::from(fg_color)..=Rgba::from(theme.surface), 0.8).into();
 
     let label_text = match status {
         RelayStatus::Connected => "Connected",
         RelayStatus::Connecting => "Connecting...",
         RelayStatus::Disconnected => "Not Connected",
+        RelayStatus::Error(_) => "Error",
     };
 
     let frame = Frame::none()
@@ -155,10 +830,12 @@ fn show_connection_status(ui: &mut Ui, status: &RelayStatus) {
         .fill(bg_color)
         .inner_margin(Margin::symmetric(12.0, 4.0));
 
-    frame.show(ui, |ui| {
-        ui.label(RichText::new(label_text).color(fg_color));
-        ui.add(get_connection_icon(status));
-    });
+    frame
+        .show(ui, |ui| {
+            ui.label(RichText::new(tr!(ui.ctx(), label_text)).color(fg_color));
+            ui.add(get_connection_icon(status));
+        })
+        .response
 }
 
 fn get_connection_icon(status: &RelayStatus) -> egui::Image<'static> {
@@ -167,7 +844,7 @@ fn get_connection_icon(status: &RelayStatus) -> egui::Image<'static> {
         RelayStatus::Connecting => {
             egui::include_image!("../../assets/icons/connecting_icon_4x.png")
         }
-        RelayStatus::Disconnected => {
+        RelayStatus::Disconnected | RelayStatus::Error(_) => {
             egui::include_image!("../../assets/icons/disconnected_icon_4x.png")
         }
     };
@@ -183,20 +860,60 @@ mod preview {
 
     pub struct RelayViewPreview {
         pool: RelayPool,
+        permissions: HashMap<String, RelayPermissions>,
+        relay_info: HashMap<String, enostr::RelayInfo>,
     }
 
     impl RelayViewPreview {
         fn new() -> Self {
+            let damus = enostr::RelayInfo {
+                name: Some("damus.io".to_owned()),
+                description: Some("Damus strfry relay".to_owned()),
+                contact: Some("jb55@jb55.com".to_owned()),
+                supported_nips: vec![1, 2, 4, 9, 11, 22, 28, 40],
+                software: Some("git+https://github.com/hoytech/strfry.git".to_owned()),
+                version: Some("0.9.6".to_owned()),
+                limitation: Some(enostr::RelayLimitation {
+                    max_message_length: Some(393216),
+                    max_subscriptions: Some(300),
+                    max_limit: Some(5000),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
             RelayViewPreview {
                 pool: sample_pool(),
+                permissions: HashMap::new(),
+                relay_info: [("wss://relay.damus.io".to_owned(), damus)]
+                    .into_iter()
+                    .collect(),
             }
         }
     }
 
     impl View for RelayViewPreview {
-        fn ui(&mut self, ui: &mut egui::Ui) {
+        fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
             self.pool.try_recv();
-            RelayView::new(RelayPoolManager::new(&mut self.pool)).ui(ui);
+            let resp = RelayView::new(RelayPoolManager::new(&mut self.pool))
+                .editable(true)
+                .searchable(true)
+                .permissions(&self.permissions)
+                .relay_info(&self.relay_info)
+                .show(ui);
+
+            let mut manager = RelayPoolManager::new(&mut self.pool);
+            for action in resp.inner {
+                match action {
+                    RelayAction::Add(url) => manager.add_relay(ui.ctx(), url),
+                    RelayAction::Remove(url) => manager.remove_relay_url(&url),
+                    RelayAction::SetPermissions(url, perms) => {
+                        self.permissions.insert(url, perms);
+                    }
+                    RelayAction::Reorder(from, to) => manager.move_relay(from, to),
+                }
+            }
+
+            resp.response
         }
     }
 
@@ -208,3 +925,90 @@ mod preview {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_relay_urls() {
+        assert_eq!(
+            normalize_relay_url(" wss://relay.damus.io/ "),
+            Ok("wss://relay.damus.io".to_string())
+        );
+        assert_eq!(
+            normalize_relay_url("ws://localhost:8080"),
+            Ok("ws://localhost:8080".to_string())
+        );
+        assert!(normalize_relay_url("https://relay.damus.io").is_err());
+        assert!(normalize_relay_url("wss://").is_err());
+        assert!(normalize_relay_url("wss://relay damus.io").is_err());
+    }
+
+    #[test]
+    fn latency_labels() {
+        assert_eq!(format_latency(None), "—");
+        assert_eq!(format_latency(Some(Duration::from_millis(42))), "42ms");
+        assert_eq!(latency_badness(Duration::from_millis(50)), 0.0);
+        assert_eq!(latency_badness(Duration::from_secs(2)), 1.0);
+    }
+
+    #[test]
+    fn searches_relay_urls_and_names() {
+        let url = "wss://relay.damus.io";
+        assert!(relay_matches("", url, None));
+        assert!(relay_matches(" DAMUS ", url, None));
+        assert!(relay_matches("strfry", url, Some("Damus Strfry")));
+        assert!(!relay_matches("nos.lol", url, Some("damus")));
+    }
+
+    #[test]
+    fn drops_between_rows() {
+        let centers = [10.0, 30.0, 50.0];
+        assert_eq!(drop_slot(&centers, 0.0), 0);
+        assert_eq!(drop_slot(&centers, 35.0), 2);
+        assert_eq!(drop_slot(&centers, 60.0), 3);
+
+        // dropping either side of itself doesn't move it
+        assert_eq!(drop_target(1, 1), None);
+        assert_eq!(drop_target(1, 2), None);
+        assert_eq!(drop_target(0, 3), Some(2));
+        assert_eq!(drop_target(2, 0), Some(0));
+    }
+
+    #[test]
+    fn lists_relay_limitations() {
+        let limitation = enostr::RelayLimitation {
+            max_message_length: Some(16384),
+            payment_required: Some(true),
+            auth_required: Some(false),
+            min_pow_difficulty: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            limitation_lines(&Strings::default(), &limitation),
+            vec![
+                "Payment required".to_owned(),
+                "Messages up to 16.3k bytes".to_owned()
+            ]
+        );
+        assert!(limitation_lines(&Strings::default(), &Default::default()).is_empty());
+
+        let spanish = Strings::new().with("Messages up to {} bytes", "Mensajes de hasta {} bytes");
+        assert_eq!(
+            limitation_lines(&spanish, &limitation)[1],
+            "Mensajes de hasta 16.3k bytes"
+        );
+    }
+
+    #[test]
+    fn summarizes_one_sided_permissions() {
+        let both = RelayPermissions::default();
+        let read = RelayPermissions {
+            read: true,
+            write: false,
+        };
+        assert_eq!(both.summary(), None);
+        assert_eq!(read.summary().map(|(_, tip)| tip), Some("Read only"));
+    }
+}
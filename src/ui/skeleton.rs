@@ -0,0 +1,267 @@
+use crate::ui::anim;
+use crate::ui::ProfilePic;
+use egui::epaint::{Mesh, Vertex, WHITE_UV};
+use egui::{pos2, vec2, Color32, Pos2, Rect, Sense};
+
+/// How long the shimmer takes to sweep across, in seconds
+const SHIMMER_PERIOD: f32 = 1.4;
+
+/// How wide the bright part of the shimmer is, in points
+const SHIMMER_WIDTH: f32 = 120.0;
+
+/// How many points along each edge of a block, so the shimmer's gradient
+/// comes out smooth
+const SEGMENTS: usize = 24;
+
+/// Where the shimmer is across `rect`, sweeping from just off its left
+/// edge to just off its right
+fn shimmer_x(ctx: &egui::Context, rect: Rect) -> Option<f32> {
+    let phase = anim::loop_phase(ctx, SHIMMER_PERIOD)?;
+    let travel = rect.width() + SHIMMER_WIDTH * 2.0;
+    Some(rect.left() - SHIMMER_WIDTH + phase * travel)
+}
+
+/// Placeholder blocks, all lit by the same sweep
+struct Blocks {
+    base: Color32,
+    highlight: Color32,
+    shimmer: Option<f32>,
+}
+
+impl Blocks {
+    fn new(ui: &egui::Ui, rect: Rect) -> Self {
+        let base = ui.visuals().widgets.noninteractive.bg_fill;
+        let highlight = if ui.visuals().dark_mode {
+            base.linear_multiply(1.6)
+        } else {
+            Color32::WHITE
+        };
+        Blocks {
+            base,
+            highlight,
+            shimmer: shimmer_x(ui.ctx(), rect),
+        }
+    }
+
+    fn color_at(&self, x: f32) -> Color32 {
+        let Some(shimmer) = self.shimmer else {
+            return self.base;
+        };
+        let t = (1.0 - (x - shimmer).abs() / (SHIMMER_WIDTH / 2.0)).max(0.0);
+        lerp_color(
+            self.base,
+            self.highlight,
+            anim::ease(anim::Curve::EaseInOut, t),
+        )
+    }
+
+    /// Fill a convex outline, shading each point by where the shimmer is
+    fn fill(&self, painter: &egui::Painter, outline: &[Pos2]) {
+        let mut mesh = Mesh::default();
+        let center =
+            outline.iter().fold(Pos2::ZERO, |sum, p| sum + p.to_vec2()) / outline.len() as f32;
+        mesh.vertices.push(Vertex {
+            pos: center,
+            uv: WHITE_UV,
+            color: self.color_at(center.x),
+        });
+        for p in outline {
+            mesh.vertices.push(Vertex {
+                pos: *p,
+                uv: WHITE_UV,
+                color: self.color_at(p.x),
+            });
+        }
+        let n = outline.len() as u32;
+        for i in 0..n {
+            mesh.add_triangle(0, 1 + i, 1 + (i + 1) % n);
+        }
+        painter.add(mesh);
+    }
+
+    fn rect(&self, painter: &egui::Painter, rect: Rect) {
+        let top = (0..=SEGMENTS).map(|i| {
+            pos2(
+                rect.left() + rect.width() * i as f32 / SEGMENTS as f32,
+                rect.top(),
+            )
+        });
+        let bottom = (0..=SEGMENTS).rev().map(|i| {
+            pos2(
+                rect.left() + rect.width() * i as f32 / SEGMENTS as f32,
+                rect.bottom(),
+            )
+        });
+        let outline: Vec<Pos2> = top.chain(bottom).collect();
+        self.fill(painter, &outline);
+    }
+
+    fn circle(&self, painter: &egui::Painter, center: Pos2, radius: f32) {
+        let outline: Vec<Pos2> = (0..SEGMENTS * 2)
+            .map(|i| {
+                let angle = std::f32::consts::TAU * i as f32 / (SEGMENTS * 2) as f32;
+                center + radius * vec2(angle.cos(), angle.sin())
+            })
+            .collect();
+        self.fill(painter, &outline);
+    }
+
+    /// A line of text, `fraction` of `width` long
+    fn line(
+        &self,
+        painter: &egui::Painter,
+        left_top: Pos2,
+        width: f32,
+        fraction: f32,
+        height: f32,
+    ) {
+        self.rect(
+            painter,
+            Rect::from_min_size(left_top, vec2(width * fraction, height)),
+        );
+    }
+}
+
+fn lerp_color(a: Color32, b: Color32, t: f32) -> Color32 {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color32::from_rgba_premultiplied(
+        mix(a.r(), b.r()),
+        mix(a.g(), b.g()),
+        mix(a.b(), b.b()),
+        mix(a.a(), b.a()),
+    )
+}
+
+/// How tall a skeleton line of body text is
+fn line_height(ui: &egui::Ui) -> f32 {
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    ui.fonts(|f| f.row_height(&font_id)) * 0.7
+}
+
+/// A stand in for a note that hasn't loaded yet: an avatar, a name and a
+/// couple of lines of text
+#[derive(Default)]
+pub struct NoteSkeleton;
+
+impl NoteSkeleton {
+    pub fn new() -> Self {
+        NoteSkeleton
+    }
+}
+
+impl egui::Widget for NoteSkeleton {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let margin = 8.0;
+        let avatar = ProfilePic::default_size();
+        let line = line_height(ui);
+        let gap = line * 0.8;
+        let height = avatar.max(line * 3.0 + gap * 2.0) + margin * 2.0;
+
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(ui.available_width(), height), Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter_at(rect);
+        let blocks = Blocks::new(ui, rect);
+        let inner = rect.shrink(margin);
+
+        blocks.circle(
+            &painter,
+            inner.left_top() + vec2(avatar / 2.0, avatar / 2.0),
+            avatar / 2.0,
+        );
+
+        let text_left = inner.left() + avatar + 16.0;
+        let text_width = inner.right() - text_left;
+        let mut y = inner.top();
+        for fraction in [0.3, 0.95, 0.7] {
+            blocks.line(&painter, pos2(text_left, y), text_width, fraction, line);
+            y += line + gap;
+        }
+
+        response
+    }
+}
+
+/// A stand in for a profile preview that hasn't loaded yet: a banner, an
+/// avatar, a name and an about line or two
+#[derive(Default)]
+pub struct ProfileSkeleton;
+
+impl ProfileSkeleton {
+    pub fn new() -> Self {
+        ProfileSkeleton
+    }
+}
+
+impl egui::Widget for ProfileSkeleton {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let banner_height = 80.0;
+        let avatar = 80.0;
+        let margin = 12.0;
+        let line = line_height(ui);
+        let gap = line * 0.8;
+        let height = banner_height + avatar / 2.0 + margin * 2.0 + line * 3.0 + gap * 2.0;
+
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(ui.available_width(), height), Sense::hover());
+        if !ui.is_rect_visible(rect) {
+            return response;
+        }
+
+        let painter = ui.painter_at(rect);
+        let blocks = Blocks::new(ui, rect);
+
+        let banner = Rect::from_min_size(rect.min, vec2(rect.width(), banner_height));
+        blocks.rect(&painter, banner);
+
+        // the avatar hangs off the banner, like in ProfilePreview
+        let center = banner.left_bottom() + vec2(12.0 + avatar / 2.0, 0.0);
+        painter.circle_filled(center, avatar / 2.0 + 3.0, ui.visuals().panel_fill);
+        blocks.circle(&painter, center, avatar / 2.0);
+
+        let width = rect.width() - margin * 2.0;
+        let mut y = banner.bottom() + avatar / 2.0 + margin;
+        for (fraction, height) in [(0.4, line * 1.3), (0.9, line), (0.6, line)] {
+            blocks.line(
+                &painter,
+                pos2(rect.left() + margin, y),
+                width,
+                fraction,
+                height,
+            );
+            y += height + gap;
+        }
+
+        response
+    }
+}
+
+mod previews {
+    use super::*;
+    use crate::ui::{Preview, View};
+
+    pub struct SkeletonPreview;
+
+    impl View for SkeletonPreview {
+        fn ui(&mut self, ui: &mut egui::Ui) -> egui::Response {
+            ui.vertical(|ui| {
+                ui.add(ProfileSkeleton::new());
+                for _ in 0..4 {
+                    ui.add(NoteSkeleton::new());
+                }
+            })
+            .response
+        }
+    }
+
+    impl Preview for NoteSkeleton {
+        type Prev = SkeletonPreview;
+
+        fn preview() -> Self::Prev {
+            SkeletonPreview
+        }
+    }
+}
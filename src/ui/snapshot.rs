@@ -0,0 +1,185 @@
+use egui::epaint::{ClippedPrimitive, ImageData, Mesh, Primitive, TexturesDelta, Vertex};
+use egui::{Color32, ColorImage, Pos2, Rect, TextureId};
+use std::collections::HashMap;
+
+/// A software rasterizer for egui's output, so previews can be drawn to an
+/// image without a window or a GPU. Triangles are filled at pixel centers
+/// and textures are sampled nearest neighbor, which is plenty for
+/// comparing snapshots but not pixel identical to the real renderers.
+pub struct Canvas {
+    pub image: ColorImage,
+    textures: HashMap<TextureId, ColorImage>,
+}
+
+impl Canvas {
+    pub fn new(size: [usize; 2], background: Color32) -> Self {
+        Canvas {
+            image: ColorImage::new(size, background),
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Upload new and updated textures, like the font atlas. Call this
+    /// before painting the frame the delta came with.
+    pub fn set_textures(&mut self, delta: &TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            let image = match &image_delta.image {
+                ImageData::Color(image) => (**image).clone(),
+                ImageData::Font(font) => ColorImage {
+                    size: font.size,
+                    pixels: font.srgba_pixels(None).collect(),
+                },
+            };
+
+            let Some([x, y]) = image_delta.pos else {
+                self.textures.insert(*id, image);
+                continue;
+            };
+
+            let Some(texture) = self.textures.get_mut(id) else {
+                continue;
+            };
+            for row in 0..image.size[1] {
+                let src = row * image.size[0];
+                let dst = (y + row) * texture.size[0] + x;
+                texture.pixels[dst..dst + image.size[0]]
+                    .copy_from_slice(&image.pixels[src..src + image.size[0]]);
+            }
+        }
+    }
+
+    /// Drop textures, after painting the frame the delta came with
+    pub fn free_textures(&mut self, delta: &TexturesDelta) {
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    pub fn paint(&mut self, primitives: &[ClippedPrimitive]) {
+        for primitive in primitives {
+            // paint callbacks draw straight to the GPU, so we can't see them
+            if let Primitive::Mesh(mesh) = &primitive.primitive {
+                let texture = self.textures.get(&mesh.texture_id);
+                fill_mesh(&mut self.image, primitive.clip_rect, mesh, texture);
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+fn sample(texture: Option<&ColorImage>, uv: Pos2) -> Color32 {
+    let Some(texture) = texture else {
+        return Color32::WHITE;
+    };
+    let [w, h] = texture.size;
+    let x = ((uv.x * w as f32) as usize).min(w.saturating_sub(1));
+    let y = ((uv.y * h as f32) as usize).min(h.saturating_sub(1));
+    texture.pixels[y * w + x]
+}
+
+/// Mix the three vertices of a triangle by barycentric `weights`
+fn mix_color(vertices: [&Vertex; 3], weights: [f32; 3]) -> [f32; 4] {
+    let mut out = [0.0; 4];
+    for (vertex, weight) in vertices.iter().zip(weights) {
+        for (channel, value) in out.iter_mut().zip(vertex.color.to_array()) {
+            *channel += value as f32 * weight;
+        }
+    }
+    out
+}
+
+/// Draw premultiplied `src` over `dst`
+fn blend(dst: Color32, src: [f32; 4]) -> Color32 {
+    let keep = 1.0 - src[3] / 255.0;
+    let channel = |i: usize| (src[i] + dst.to_array()[i] as f32 * keep).round() as u8;
+    Color32::from_rgba_premultiplied(channel(0), channel(1), channel(2), channel(3))
+}
+
+fn fill_mesh(target: &mut ColorImage, clip: Rect, mesh: &Mesh, texture: Option<&ColorImage>) {
+    let [width, height] = target.size;
+    let bounds = clip.intersect(Rect::from_min_size(
+        Pos2::ZERO,
+        egui::vec2(width as f32, height as f32),
+    ));
+    if !bounds.is_positive() {
+        return;
+    }
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let vertices = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+        let [a, b, c] = vertices.map(|v| v.pos);
+        let area = edge(a, b, c);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let tri_rect = Rect::from_points(&[a, b, c]).intersect(bounds);
+        if !tri_rect.is_positive() {
+            continue;
+        }
+
+        let (x0, x1) = (tri_rect.min.x as usize, tri_rect.max.x.ceil() as usize);
+        let (y0, y1) = (tri_rect.min.y as usize, tri_rect.max.y.ceil() as usize);
+        for y in y0..y1.min(height) {
+            for x in x0..x1.min(width) {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                if !bounds.contains(p) {
+                    continue;
+                }
+
+                let weights = [
+                    edge(b, c, p) / area,
+                    edge(c, a, p) / area,
+                    edge(a, b, p) / area,
+                ];
+                if weights.iter().any(|w| *w < 0.0) {
+                    continue;
+                }
+
+                let uv = vertices
+                    .iter()
+                    .zip(weights)
+                    .fold(Pos2::ZERO, |uv, (v, w)| uv + v.uv.to_vec2() * w);
+                let texel = sample(texture, uv).to_array();
+                let mut color = mix_color(vertices, weights);
+                for (channel, t) in color.iter_mut().zip(texel) {
+                    *channel *= t as f32 / 255.0;
+                }
+
+                let pixel = &mut target.pixels[y * width + x];
+                *pixel = blend(*pixel, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_rects_inside_clip() {
+        let mut canvas = Canvas::new([8, 8], Color32::BLACK);
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(
+            Rect::from_min_max(Pos2::new(2.0, 2.0), Pos2::new(6.0, 6.0)),
+            Color32::RED,
+        );
+        canvas.paint(&[ClippedPrimitive {
+            clip_rect: Rect::from_min_max(Pos2::ZERO, Pos2::new(4.0, 8.0)),
+            primitive: Primitive::Mesh(mesh),
+        }]);
+
+        let at = |x: usize, y: usize| canvas.image.pixels[y * 8 + x];
+        assert_eq!(at(2, 2), Color32::RED);
+        assert_eq!(at(3, 5), Color32::RED);
+        // clipped
+        assert_eq!(at(4, 3), Color32::BLACK);
+        // outside the rect
+        assert_eq!(at(1, 1), Color32::BLACK);
+    }
+}
@@ -0,0 +1,105 @@
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The byte offset `max` graphemes into `s`, or its length if it's shorter
+pub fn grapheme_boundary(s: &str, max: usize) -> usize {
+    s.grapheme_indices(true)
+        .nth(max)
+        .map(|(i, _)| i)
+        .unwrap_or(s.len())
+}
+
+/// The last grapheme boundary at or before byte `index`, so cutting there
+/// never splits an emoji sequence or a letter from its accents
+pub fn floor_grapheme_boundary(s: &str, index: usize) -> usize {
+    if index >= s.len() {
+        return s.len();
+    }
+
+    s.grapheme_indices(true)
+        .map(|(i, _)| i)
+        .take_while(|i| *i <= index)
+        .last()
+        .unwrap_or(0)
+}
+
+/// The first `max` graphemes of `s`, with an ellipsis after them if
+/// anything was cut
+pub fn truncate_graphemes(s: &str, max: usize) -> Cow<'_, str> {
+    let end = grapheme_boundary(s, max);
+    if end == s.len() {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(format!("{}…", &s[..end]))
+    }
+}
+
+/// Whether a codepoint starts an emoji by itself, going by the blocks
+/// emoji live in
+fn is_emoji_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2300..=0x23FF | 0x2600..=0x27BF | 0x2B00..=0x2BFF
+            | 0x3030 | 0x303D | 0x3297 | 0x3299
+    )
+}
+
+/// Whether a grapheme is one emoji. Flags, skin tones, keycaps and
+/// sequences joined with zero width joiners are all a single grapheme.
+pub fn is_emoji(grapheme: &str) -> bool {
+    match grapheme.chars().next() {
+        // a variation selector or keycap turns eg. a digit or © into an emoji
+        Some(first) => is_emoji_char(first) || grapheme.contains(['\u{FE0F}', '\u{20E3}']),
+        None => false,
+    }
+}
+
+/// How many emoji `s` is made of, if it's nothing but emoji and whitespace
+pub fn emoji_count(s: &str) -> Option<usize> {
+    let mut count = 0;
+    for grapheme in s.graphemes(true) {
+        if grapheme.chars().all(char::is_whitespace) {
+            continue;
+        }
+        if !is_emoji(grapheme) {
+            return None;
+        }
+        count += 1;
+    }
+    (count > 0).then_some(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAMILY: &str = "👨\u{200d}👩\u{200d}👧\u{200d}👦";
+
+    #[test]
+    fn truncates_zwj_sequences_whole() {
+        let s = format!("{}{}{}", FAMILY, FAMILY, FAMILY);
+        assert_eq!(truncate_graphemes(&s, 2), format!("{}{}…", FAMILY, FAMILY));
+        assert_eq!(truncate_graphemes(&s, 3), s);
+
+        // a byte in the middle of the second family snaps back to its start
+        assert_eq!(floor_grapheme_boundary(&s, FAMILY.len() + 5), FAMILY.len());
+    }
+
+    #[test]
+    fn counts_emoji_only_text() {
+        assert_eq!(emoji_count("🤙"), Some(1));
+        assert_eq!(emoji_count(&format!(" {} 🇯🇵\n👍🏽 ", FAMILY)), Some(3));
+        assert_eq!(emoji_count("1\u{FE0F}\u{20E3}❤\u{FE0F}⭐"), Some(3));
+        assert_eq!(emoji_count("gm 🤙"), None);
+        assert_eq!(emoji_count("123"), None);
+        assert_eq!(emoji_count("  "), None);
+    }
+
+    #[test]
+    fn keeps_combining_marks_with_their_letter() {
+        let s = "cafe\u{301} noir";
+        assert_eq!(truncate_graphemes(s, 4), "cafe\u{301}…");
+        assert_eq!(floor_grapheme_boundary(s, 4), 3);
+        assert_eq!(truncate_graphemes("abc", 10), "abc");
+    }
+}
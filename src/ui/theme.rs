@@ -0,0 +1,114 @@
+use crate::colors;
+use egui::Color32;
+
+/// The colors our widgets use on top of egui's visuals. There's a preset
+/// for light and dark mode, and the one in use follows whichever mode the
+/// context is in. The accent can be overridden for both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotedeckTheme {
+    /// Highlights, like active action bar buttons and verified badges
+    pub accent: Color32,
+    /// Links, mentions and hashtags
+    pub link: Color32,
+    /// Secondary text, like timestamps and "replying to"
+    pub muted_text: Color32,
+    /// Cards and placeholders drawn on top of the panel
+    pub surface: Color32,
+    pub error: Color32,
+}
+
+impl NotedeckTheme {
+    pub fn dark() -> Self {
+        NotedeckTheme {
+            accent: colors::PURPLE,
+            link: colors::PURPLE,
+            muted_text: colors::GRAY_SECONDARY,
+            surface: colors::DARK_ISH_BG,
+            error: colors::RED_700,
+        }
+    }
+
+    pub fn light() -> Self {
+        NotedeckTheme {
+            accent: colors::PURPLE,
+            link: colors::PURPLE,
+            muted_text: colors::EVEN_DARKER_GRAY,
+            surface: colors::LIGHTER_GRAY,
+            error: colors::RED_700,
+        }
+    }
+
+    pub fn preset(dark_mode: bool) -> Self {
+        if dark_mode {
+            Self::dark()
+        } else {
+            Self::light()
+        }
+    }
+
+    /// Use `accent` for highlights, and for links so they match
+    pub fn with_accent(mut self, accent: Color32) -> Self {
+        self.accent = accent;
+        self.link = accent;
+        self
+    }
+
+    fn accent_id() -> egui::Id {
+        egui::Id::new("notedeck_theme_accent")
+    }
+
+    /// The theme for the mode `ctx` is in, with any accent override
+    pub fn get(ctx: &egui::Context) -> Self {
+        let theme = Self::preset(ctx.style().visuals.dark_mode);
+        match ctx.data(|d| d.get_temp::<Color32>(Self::accent_id())) {
+            Some(accent) => theme.with_accent(accent),
+            None => theme,
+        }
+    }
+
+    /// Override the accent color in both modes, or go back to the presets'
+    pub fn set_accent(ctx: &egui::Context, accent: Option<Color32>) {
+        ctx.data_mut(|d| match accent {
+            Some(accent) => d.insert_temp(Self::accent_id(), accent),
+            None => d.remove::<Color32>(Self::accent_id()),
+        });
+        Self::install(ctx);
+    }
+
+    /// Copy the theme into egui's visuals, so plain egui widgets like
+    /// hyperlinks match. Call this again after changing visuals.
+    pub fn install(ctx: &egui::Context) {
+        let theme = Self::get(ctx);
+        ctx.style_mut(|style| {
+            style.visuals.hyperlink_color = theme.link;
+            style.visuals.error_fg_color = theme.error;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accent_overrides_links_too() {
+        let theme = NotedeckTheme::light().with_accent(Color32::GOLD);
+        assert_eq!(theme.accent, Color32::GOLD);
+        assert_eq!(theme.link, Color32::GOLD);
+        assert_eq!(theme.muted_text, NotedeckTheme::light().muted_text);
+    }
+
+    #[test]
+    fn follows_the_context_mode() {
+        let ctx = egui::Context::default();
+        ctx.set_visuals(egui::Visuals::light());
+        assert_eq!(NotedeckTheme::get(&ctx), NotedeckTheme::light());
+
+        NotedeckTheme::set_accent(&ctx, Some(Color32::GOLD));
+        ctx.set_visuals(egui::Visuals::dark());
+        assert_eq!(
+            NotedeckTheme::get(&ctx),
+            NotedeckTheme::dark().with_accent(Color32::GOLD)
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::ui::i18n::{self, tr, Strings};
+use crate::ui::keys;
+use crate::ui::theme::NotedeckTheme;
+use chrono::{Local, TimeZone};
+use egui::{Label, RichText};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// The current unix time in seconds
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
+
+/// How long ago something was, rounded down to the largest unit that fits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeTime {
+    Now,
+    Minutes(u64),
+    Hours(u64),
+    Yesterday,
+    Days(u64),
+    Weeks(u64),
+    Months(u64),
+    Years(u64),
+}
+
+impl RelativeTime {
+    /// How long before `now` `created_at` was. Timestamps in the future
+    /// are treated as now.
+    pub fn new(created_at: u64, now: u64) -> Self {
+        let age = now.saturating_sub(created_at);
+
+        if age < MINUTE {
+            RelativeTime::Now
+        } else if age < HOUR {
+            RelativeTime::Minutes(age / MINUTE)
+        } else if age < DAY {
+            RelativeTime::Hours(age / HOUR)
+        } else if age < 2 * DAY {
+            RelativeTime::Yesterday
+        } else if age < WEEK {
+            RelativeTime::Days(age / DAY)
+        } else if age < MONTH {
+            RelativeTime::Weeks(age / WEEK)
+        } else if age < YEAR {
+            RelativeTime::Months(age / MONTH)
+        } else {
+            RelativeTime::Years(age / YEAR)
+        }
+    }
+
+    /// The short form, eg. "2m", "3h" or "yesterday". The English forms,
+    /// like "{}m", are the keys to translate them by.
+    pub fn format(&self, strings: &Strings) -> String {
+        let (template, count) = match *self {
+            RelativeTime::Now => return strings.get("now").to_owned(),
+            RelativeTime::Yesterday => return strings.get("yesterday").to_owned(),
+            RelativeTime::Minutes(n) => ("{}m", n),
+            RelativeTime::Hours(n) => ("{}h", n),
+            RelativeTime::Days(n) => ("{}d", n),
+            RelativeTime::Weeks(n) => ("{}wk", n),
+            RelativeTime::Months(n) => ("{}mth", n),
+            RelativeTime::Years(n) => ("{}yr", n),
+        };
+        i18n::fill(strings.get(template), &[&count])
+    }
+}
+
+/// A short description of how long ago `created_at` was, eg. "2m", "3h" or
+/// "yesterday", in the language of `strings`. Timestamps in the future are
+/// treated as now.
+pub fn relative_time(created_at: u64, now: u64, strings: &Strings) -> String {
+    RelativeTime::new(created_at, now).format(strings)
+}
+
+/// How long until [`relative_time`] next changes: at the next minute under
+/// an hour, at the next hour under a day and daily after that
+pub fn refresh_interval(created_at: u64, now: u64) -> Duration {
+    let age = now.saturating_sub(created_at);
+    let bucket = if age < HOUR {
+        MINUTE
+    } else if age < DAY {
+        HOUR
+    } else {
+        DAY
+    };
+    Duration::from_secs(bucket - age % bucket)
+}
+
+/// The full date and time of a timestamp in the local timezone
+pub fn absolute_time(created_at: u64) -> String {
+    match Local.timestamp_opt(created_at as i64, 0).single() {
+        Some(datetime) => datetime.format("%Y-%m-%d %H:%M").to_string(),
+        None => created_at.to_string(),
+    }
+}
+
+/// A note's relative timestamp, showing the full date on hover along with
+/// the note's id and the relays it was seen on, when they're given.
+/// Schedules a repaint for when the label next goes stale.
+pub struct Timestamp<'a> {
+    created_at: u64,
+    reltime: &'a str,
+    note_id: Option<&'a [u8; 32]>,
+    seen_on: &'a [String],
+}
+
+impl<'a> Timestamp<'a> {
+    /// `reltime` is the [`relative_time`] of `created_at`, which callers
+    /// usually cache
+    pub fn new(created_at: u64, reltime: &'a str) -> Self {
+        Timestamp {
+            created_at,
+            reltime,
+            note_id: None,
+            seen_on: &[],
+        }
+    }
+
+    /// The id of the note this is the timestamp of, shortened on hover
+    pub fn note_id(mut self, id: &'a [u8; 32]) -> Self {
+        self.note_id = Some(id);
+        self
+    }
+
+    /// The relays the note was seen on, as far as the caller knows
+    pub fn seen_on(mut self, relays: &'a [String]) -> Self {
+        self.seen_on = relays;
+        self
+    }
+
+    fn hover_ui(&self, ui: &mut egui::Ui) {
+        ui.label(absolute_time(self.created_at));
+        if let Some(id) = self.note_id {
+            ui.weak(keys::short_note_id(id));
+        }
+        if !self.seen_on.is_empty() {
+            ui.separator();
+            ui.label(tr!(ui.ctx(), "Seen on"));
+            for relay in self.seen_on {
+                ui.weak(relay);
+            }
+        }
+    }
+}
+
+impl egui::Widget for Timestamp<'_> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        ui.ctx()
+            .request_repaint_after(refresh_interval(self.created_at, unix_now()));
+
+        ui.add(Label::new(
+            RichText::new(self.reltime)
+                .size(10.0)
+                .color(NotedeckTheme::get(ui.ctx()).muted_text),
+        ))
+        .on_hover_ui(|ui| self.hover_ui(ui))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_time_buckets() {
+        let now = 1_700_000_000;
+        let english = |created_at| relative_time(created_at, now, &Strings::default());
+        assert_eq!(english(now), "now");
+        assert_eq!(english(now + 30), "now");
+        assert_eq!(english(now - 2 * MINUTE - 5), "2m");
+        assert_eq!(english(now - 3 * HOUR), "3h");
+        assert_eq!(english(now - DAY - HOUR), "yesterday");
+        assert_eq!(english(now - 3 * DAY), "3d");
+        assert_eq!(english(now - 2 * WEEK), "2wk");
+        assert_eq!(english(now - 2 * YEAR), "2yr");
+    }
+
+    #[test]
+    fn relative_times_are_translated() {
+        let now = 1_700_000_000;
+        let german = Strings::new()
+            .with("{}h", "{} Std.")
+            .with("yesterday", "gestern");
+        assert_eq!(relative_time(now - 3 * HOUR, now, &german), "3 Std.");
+        assert_eq!(relative_time(now - DAY - HOUR, now, &german), "gestern");
+        // what a table doesn't have stays English
+        assert_eq!(relative_time(now - 2 * WEEK, now, &german), "2wk");
+    }
+
+    #[test]
+    fn refreshes_when_label_changes() {
+        let now = 1_700_000_000;
+        assert_eq!(refresh_interval(now - 90, now), Duration::from_secs(30));
+        assert_eq!(
+            refresh_interval(now - 2 * HOUR - 10, now),
+            Duration::from_secs(HOUR - 10)
+        );
+    }
+}
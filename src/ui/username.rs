@@ -1,12 +1,40 @@
 use crate::fonts::NamedFontFamily;
+use crate::imgcache::ImageCache;
+use crate::profile::usable_name;
+use crate::ui::emoji::{emoji_image, CustomEmojis, EmojiSpan};
+use crate::ui::i18n::tr;
+use crate::ui::keys::short_npub;
+use crate::ui::profile::{ProfileHoverCard, ProfileRequests, ProfileSource};
+use crate::ui::text::grapheme_boundary;
+use crate::ui::theme::NotedeckTheme;
+use crate::Nip05Status;
 use egui::{Color32, RichText, Widget};
 use nostrdb::ProfileRecord;
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Which part of the profile a [`Username`] was shown from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    DisplayName,
+    Name,
+    /// The profile had no usable name, or we don't have the profile
+    Npub,
+}
 
 pub struct Username<'a> {
-    profile: Option<&'a ProfileRecord<'a>>,
+    display_name: Option<Cow<'a, str>>,
+    name: Option<Cow<'a, str>>,
     pk: &'a [u8; 32],
     pk_colored: bool,
+    bold: bool,
     abbrev: usize,
+    nip05: Option<&'a Nip05Status>,
+    img_cache: Option<&'a mut ImageCache>,
+    hover_card: Option<&'a dyn ProfileSource>,
+    emojis: CustomEmojis,
+    /// We don't have their profile, so it's requested when shown
+    missing: bool,
 }
 
 impl<'a> Username<'a> {
@@ -15,26 +43,98 @@ impl<'a> Username<'a> {
         self
     }
 
+    /// Use the bold font family instead of medium
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.bold = bold;
+        self
+    }
+
+    /// Show a badge for the profile's NIP-05 verification status
+    pub fn nip05(mut self, status: &'a Nip05Status) -> Self {
+        self.nip05 = Some(status);
+        self
+    }
+
+    /// Where the hover card and custom emoji get their pictures from.
+    /// Neither is shown without it.
+    pub fn image_cache(mut self, img_cache: &'a mut ImageCache) -> Self {
+        self.img_cache = Some(img_cache);
+        self
+    }
+
+    /// Open a profile card when the name is hovered, filled in from
+    /// `profiles`
+    pub fn hover_card(mut self, profiles: &'a dyn ProfileSource) -> Self {
+        self.hover_card = Some(profiles);
+        self
+    }
+
+    /// Show these custom emoji in place of `:shortcode:`s in the name
+    pub fn emojis(mut self, emojis: CustomEmojis) -> Self {
+        self.emojis = emojis;
+        self
+    }
+
     pub fn abbreviated(mut self, amount: usize) -> Self {
         self.abbrev = amount;
         self
     }
 
     pub fn new(profile: Option<&'a ProfileRecord>, pk: &'a [u8; 32]) -> Self {
+        let prof = profile.and_then(|p| p.record().profile());
+        let display_name = prof.and_then(|p| p.display_name()).and_then(usable_name);
+        let name = prof.and_then(|p| p.name()).and_then(usable_name);
+        let mut username =
+            Self::with_names(display_name.map(Cow::Borrowed), name.map(Cow::Borrowed), pk);
+        username.missing = profile.is_none();
+        username
+    }
+
+    /// The name `profiles` has for `pk`
+    pub fn from_source(profiles: &dyn ProfileSource, pk: &'a [u8; 32]) -> Self {
+        let profile = profiles.get(pk);
+        let missing = profile.is_none();
+        let profile = profile.unwrap_or_default();
+        let owned = |name: Option<String>| {
+            name.as_deref()
+                .and_then(usable_name)
+                .map(|name| Cow::Owned(name.to_owned()))
+        };
+        let mut username = Self::with_names(owned(profile.display_name), owned(profile.name), pk);
+        username.missing = missing;
+        username
+    }
+
+    fn with_names(
+        display_name: Option<Cow<'a, str>>,
+        name: Option<Cow<'a, str>>,
+        pk: &'a [u8; 32],
+    ) -> Self {
         let pk_colored = false;
         let abbrev: usize = 1000;
         Username {
-            profile,
+            display_name,
+            name,
             pk,
             pk_colored,
+            bold: false,
             abbrev,
+            nip05: None,
+            img_cache: None,
+            hover_card: None,
+            emojis: CustomEmojis::default(),
+            missing: false,
         }
     }
-}
 
-impl<'a> Widget for Username<'a> {
-    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        ui.horizontal(|ui| {
+    /// Render the name, returning where it came from so callers can style
+    /// fallbacks differently
+    pub fn show(mut self, ui: &mut egui::Ui) -> egui::InnerResponse<NameSource> {
+        if self.missing {
+            ProfileRequests::request(ui.ctx(), self.pk);
+        }
+
+        let resp = ui.horizontal(|ui| {
             ui.spacing_mut().item_spacing.x = 0.0;
 
             let color = if self.pk_colored {
@@ -42,29 +142,52 @@ impl<'a> Widget for Username<'a> {
             } else {
                 None
             };
+            let family = if self.bold {
+                NamedFontFamily::Bold
+            } else {
+                NamedFontFamily::Medium
+            };
 
-            if let Some(profile) = self.profile {
-                if let Some(prof) = profile.record().profile() {
-                    if prof.display_name().is_some() && prof.display_name().unwrap() != "" {
-                        ui_abbreviate_name(ui, prof.display_name().unwrap(), self.abbrev, color);
-                    } else if let Some(name) = prof.name() {
-                        ui_abbreviate_name(ui, name, self.abbrev, color);
-                    }
-                }
+            let style = NameStyle {
+                len: self.abbrev,
+                color,
+                family,
+            };
+            let img_cache = self.img_cache.as_deref_mut();
+            let source = if let Some(display_name) = self.display_name.as_deref() {
+                ui_abbreviate_name(ui, display_name, style, img_cache, &self.emojis);
+                NameSource::DisplayName
+            } else if let Some(name) = self.name.as_deref() {
+                ui_abbreviate_name(ui, name, style, img_cache, &self.emojis);
+                NameSource::Name
             } else {
-                let mut txt = RichText::new("nostrich").family(NamedFontFamily::Medium.as_family());
-                if let Some(col) = color {
-                    txt = txt.color(col)
-                }
-                ui.label(txt);
+                ui.label(colored_name(&short_npub(self.pk), color, family));
+                NameSource::Npub
+            };
+
+            if let Some(status) = self.nip05 {
+                nip05_badge(ui, status);
             }
-        })
-        .response
+
+            source
+        });
+
+        if let (Some(img_cache), Some(profiles)) = (self.img_cache, self.hover_card) {
+            ProfileHoverCard::new(self.pk, profiles).show(ui, &resp.response, img_cache);
+        }
+
+        resp
+    }
+}
+
+impl<'a> Widget for Username<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        self.show(ui).response
     }
 }
 
-fn colored_name(name: &str, color: Option<Color32>) -> RichText {
-    let mut txt = RichText::new(name).family(NamedFontFamily::Medium.as_family());
+fn colored_name(name: &str, color: Option<Color32>, mut family: NamedFontFamily) -> RichText {
+    let mut txt = RichText::new(name).family(family.as_family());
 
     if let Some(color) = color {
         txt = txt.color(color);
@@ -73,22 +196,79 @@ fn colored_name(name: &str, color: Option<Color32>) -> RichText {
     txt
 }
 
-fn ui_abbreviate_name(ui: &mut egui::Ui, name: &str, len: usize, color: Option<Color32>) {
-    let should_abbrev = name.len() > len;
-    let name = if should_abbrev {
-        let closest = crate::abbrev::floor_char_boundary(name, len);
-        &name[..closest]
-    } else {
-        name
-    };
+#[derive(Clone, Copy)]
+struct NameStyle {
+    /// How many graphemes to show before abbreviating. Custom emoji count
+    /// as one.
+    len: usize,
+    color: Option<Color32>,
+    family: NamedFontFamily,
+}
 
-    ui.label(colored_name(name, color));
+fn ui_abbreviate_name(
+    ui: &mut egui::Ui,
+    name: &str,
+    style: NameStyle,
+    mut img_cache: Option<&mut ImageCache>,
+    emojis: &CustomEmojis,
+) {
+    crate::fonts::load_fonts_for(ui.ctx(), name);
+    let NameStyle { color, family, .. } = style;
+
+    let mut remaining = style.len;
+    let mut should_abbrev = false;
+    for span in emojis.spans(name) {
+        if remaining == 0 {
+            should_abbrev = true;
+            break;
+        }
+        match span {
+            EmojiSpan::Emoji { shortcode, url } => {
+                // without an image cache they stay as text
+                match img_cache.as_deref_mut() {
+                    Some(img_cache) => emoji_image(ui, img_cache, shortcode, url),
+                    None => ui.label(colored_name(&format!(":{}:", shortcode), color, family)),
+                };
+                remaining -= 1;
+            }
+            EmojiSpan::Text(text) => {
+                let end = grapheme_boundary(text, remaining);
+                ui.label(colored_name(&text[..end], color, family));
+                if end < text.len() {
+                    should_abbrev = true;
+                    break;
+                }
+                remaining -= text.graphemes(true).count();
+            }
+        }
+    }
 
     if should_abbrev {
-        ui.label(colored_name("..", color));
+        ui.label(colored_name("..", color, family));
     }
 }
 
+fn nip05_badge(ui: &mut egui::Ui, status: &Nip05Status) {
+    let theme = NotedeckTheme::get(ui.ctx());
+    let (badge, color, tooltip) = match status {
+        Nip05Status::Verified(nip05) => ("✔", theme.accent, nip05.clone()),
+        Nip05Status::Unverified => (
+            "✔",
+            theme.muted_text,
+            tr!(ui.ctx(), "NIP-05 not verified yet").into_owned(),
+        ),
+        Nip05Status::Failed => (
+            "⚠",
+            theme.error,
+            tr!(ui.ctx(), "NIP-05 verification failed").into_owned(),
+        ),
+    };
+
+    ui.add_space(4.0);
+    ui.label(RichText::new(badge).size(10.0).color(color))
+        .on_hover_text(tooltip);
+}
+
 fn pk_color(pk: &[u8; 32]) -> Color32 {
     Color32::from_rgb(pk[8], pk[10], pk[12])
 }
@@ -0,0 +1,153 @@
+use egui::{vec2, Align, Id, Layout, Rect, Sense};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::Range;
+
+/// How many items past either edge of the viewport we lay out anyway, so
+/// short scrolls and keyboard moves land on something already measured
+pub const DEFAULT_OVERSCAN: usize = 3;
+
+/// A list in a [`egui::ScrollArea`] that only lays out the items in view.
+/// The rest are reserved space, as tall as they were the last time they
+/// were laid out or as the estimate says if they never were. Heights are
+/// remembered by item rather than position in egui's memory, so items can
+/// be added to the front without everything below them being remeasured.
+pub struct VirtualList {
+    id: Id,
+    overscan: usize,
+}
+
+/// How tall a list's items measured, by their ids
+#[derive(Debug, Clone, Default)]
+struct MeasuredHeights {
+    heights: HashMap<Id, f32>,
+}
+
+/// Which of the items, `heights` points tall each, overlap `top..bottom`,
+/// with `overscan` more either side
+fn visible_range(heights: &[f32], top: f32, bottom: f32, overscan: usize) -> Range<usize> {
+    let len = heights.len();
+    let mut start = len;
+    let mut end = len;
+    let mut y = 0.0;
+    for (i, height) in heights.iter().enumerate() {
+        if y >= bottom {
+            end = i;
+            break;
+        }
+        if start == len && y + height > top {
+            start = i;
+        }
+        y += height;
+    }
+    start.min(end).saturating_sub(overscan)..(end + overscan).min(len)
+}
+
+impl VirtualList {
+    pub fn new(id: Id) -> Self {
+        VirtualList {
+            id,
+            overscan: DEFAULT_OVERSCAN,
+        }
+    }
+
+    pub fn overscan(mut self, overscan: usize) -> Self {
+        self.overscan = overscan;
+        self
+    }
+
+    /// Lay out the `items` overlapping `viewport`, which is what
+    /// [`egui::ScrollArea::show_viewport`] hands its closure. `estimate`
+    /// guesses how tall an item is before it's been laid out, and `render`
+    /// lays out the item at an index.
+    pub fn show<T: Hash>(
+        self,
+        ui: &mut egui::Ui,
+        viewport: Rect,
+        items: &[T],
+        estimate: impl Fn(usize) -> f32,
+        mut render: impl FnMut(&mut egui::Ui, usize, &T),
+    ) {
+        #[cfg(feature = "profiling")]
+        puffin::profile_function!();
+
+        let mut measured = ui
+            .data(|d| d.get_temp::<MeasuredHeights>(self.id))
+            .unwrap_or_default();
+        let ids: Vec<Id> = items.iter().map(|item| self.id.with(item)).collect();
+        let mut heights: Vec<f32> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                measured
+                    .heights
+                    .get(id)
+                    .copied()
+                    .unwrap_or_else(|| estimate(i))
+            })
+            .collect();
+
+        let range = visible_range(&heights, viewport.top(), viewport.bottom(), self.overscan);
+        let origin = ui.max_rect().left_top();
+        let width = ui.available_width();
+
+        let mut y: f32 = heights[..range.start].iter().sum();
+        // how far things in view were pushed down by items above them
+        // turning out taller than we thought
+        let mut shifted = 0.0;
+        for i in range.clone() {
+            let rect = Rect::from_min_size(origin + vec2(0.0, y), vec2(width, heights[i]));
+            let mut child = ui.child_ui(rect, Layout::top_down(Align::Min));
+            render(&mut child, i, &items[i]);
+
+            let height = child.min_rect().height();
+            if y < viewport.top() {
+                shifted += height - heights[i];
+            }
+            heights[i] = height;
+            measured.heights.insert(ids[i], height);
+            y += height;
+        }
+
+        let total = y + heights[range.end..].iter().sum::<f32>();
+        ui.allocate_rect(
+            Rect::from_min_size(origin, vec2(width, total)),
+            Sense::hover(),
+        );
+
+        // keep what's in view where it is
+        if shifted != 0.0 {
+            ui.scroll_with_delta(vec2(0.0, -shifted));
+            ui.ctx().request_repaint();
+        }
+
+        // forget items that left the list, once there are enough of them
+        if measured.heights.len() > 2 * items.len() {
+            let ids: HashSet<Id> = ids.into_iter().collect();
+            measured.heights.retain(|id, _| ids.contains(id));
+        }
+        ui.data_mut(|d| d.insert_temp(self.id, measured));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_only_what_is_in_view() {
+        let heights = [100.0; 10];
+        assert_eq!(visible_range(&heights, 0.0, 250.0, 0), 0..3);
+        assert_eq!(visible_range(&heights, 250.0, 450.0, 0), 2..5);
+        assert_eq!(visible_range(&heights, 250.0, 450.0, 1), 1..6);
+        assert_eq!(visible_range(&heights, 850.0, 1200.0, 3), 5..10);
+    }
+
+    #[test]
+    fn handles_uneven_and_empty_lists() {
+        let heights = [10.0, 300.0, 10.0, 10.0];
+        assert_eq!(visible_range(&heights, 50.0, 100.0, 0), 1..2);
+        assert_eq!(visible_range(&heights, 2000.0, 2100.0, 1), 3..4);
+        assert_eq!(visible_range(&[], 0.0, 100.0, 3), 0..0);
+    }
+}
@@ -2,7 +2,8 @@ use notedeck::account_login_view::AccountLoginView;
 use notedeck::app_creation::{
     generate_mobile_emulator_native_options, generate_native_options, setup_cc,
 };
-use notedeck::ui::{Preview, PreviewApp, ProfilePic, ProfilePreview, RelayView};
+use notedeck::ui::skeleton::NoteSkeleton;
+use notedeck::ui::{Lightbox, Preview, PreviewApp, ProfilePic, ProfilePreview, RelayView};
 use std::env;
 
 struct PreviewRunner {
@@ -79,6 +80,8 @@ async fn main() {
         RelayView,
         AccountLoginView,
         ProfilePreview,
-        ProfilePic
+        ProfilePic,
+        NoteSkeleton,
+        Lightbox,
     );
 }